@@ -0,0 +1,88 @@
+use super::matrix4::Matrix4;
+use super::tuple::Tuple;
+use super::util::Float;
+
+// An axis-aligned bounding box. Shapes that go on forever, like planes, are
+// given infinite bounds rather than trying to track which axes are open.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: Tuple,
+    pub max: Tuple,
+}
+
+impl Bounds {
+    pub fn new(min: Tuple, max: Tuple) -> Bounds {
+        Bounds { min, max }
+    }
+
+    pub fn infinite() -> Bounds {
+        Bounds::new(
+            Tuple::point(Float::NEG_INFINITY, Float::NEG_INFINITY, Float::NEG_INFINITY),
+            Tuple::point(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+        )
+    }
+
+    pub fn is_finite(&self) -> bool {
+        (0..3).all(|i| self.min.get(i).is_finite() && self.max.get(i).is_finite())
+    }
+
+    pub fn contains(&self, point: Tuple) -> bool {
+        (0..3).all(|i| self.min.get(i) <= point.get(i) && point.get(i) <= self.max.get(i))
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        Bounds::new(
+            Tuple::point(
+                self.min.x().min(other.min.x()),
+                self.min.y().min(other.min.y()),
+                self.min.z().min(other.min.z()),
+            ),
+            Tuple::point(
+                self.max.x().max(other.max.x()),
+                self.max.y().max(other.max.y()),
+                self.max.z().max(other.max.z()),
+            ),
+        )
+    }
+
+    // The box around all eight transformed corners.
+    pub fn transform(&self, m: &Matrix4) -> Bounds {
+        if !self.is_finite() {
+            return Bounds::infinite();
+        }
+        let mut corners = (0..8).map(|i| {
+            let pick = |bit: usize, axis: usize| if i & bit == 0 { self.min.get(axis) } else { self.max.get(axis) };
+            let p = m * Tuple::point(pick(1, 0), pick(2, 1), pick(4, 2));
+            Bounds::new(p, p)
+        });
+        let first = corners.next().unwrap();
+        corners.fold(first, |b, c| b.union(&c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::consts::PI;
+
+    #[test]
+    fn union_and_contains() {
+        let a = Bounds::new(Tuple::point(-1.0, -1.0, -1.0), Tuple::point(1.0, 1.0, 1.0));
+        let b = Bounds::new(Tuple::point(0.0, 2.0, -3.0), Tuple::point(4.0, 3.0, 0.0));
+        let u = a.union(&b);
+        assert_eq!(u, Bounds::new(Tuple::point(-1.0, -1.0, -3.0), Tuple::point(4.0, 3.0, 1.0)));
+        assert!(u.contains(Tuple::point(3.0, -1.0, 0.5)));
+        assert!(!u.contains(Tuple::point(3.0, -1.5, 0.5)));
+    }
+
+    #[test]
+    fn transform() {
+        let cube = Bounds::new(Tuple::point(-1.0, -1.0, -1.0), Tuple::point(1.0, 1.0, 1.0));
+        let b = cube.transform(&Matrix4::scaling(2.0, 1.0, 1.0).translate(5.0, 0.0, 0.0));
+        assert_eq!(b, Bounds::new(Tuple::point(3.0, -1.0, -1.0), Tuple::point(7.0, 1.0, 1.0)));
+        let k = Float::sqrt(2.0);
+        let b = cube.transform(&Matrix4::rotation_y(PI / 4.0));
+        assert_eq!(b, Bounds::new(Tuple::point(-k, -1.0, -k), Tuple::point(k, 1.0, k)));
+        assert!(!Bounds::infinite().transform(&Matrix4::translation(1.0, 0.0, 0.0)).is_finite());
+    }
+}
@@ -0,0 +1,594 @@
+use super::canvas::Canvas;
+use super::color::Color;
+use super::integrator::{Integrator, IntegratorKind};
+use super::intersection::Intersections;
+use super::matrix4::Matrix4;
+use super::ray::Ray;
+use super::rng::Rng;
+use super::sampling::Pattern;
+use super::tuple::Tuple;
+use super::world::World;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use super::util::consts::PI;
+use super::util::Float;
+
+const TILE_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderOptions {
+    pub samples: usize,
+    pub pattern: Pattern,
+    pub depth: usize,
+    pub threads: usize,
+    pub integrator: IntegratorKind,
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions { samples: 1, pattern: Pattern::Regular, depth: 5, threads: 1, integrator: IntegratorKind::Whitted }
+    }
+}
+
+// How pixels map to rays. Perspective and fisheye spread rays over the
+// camera's field of view; orthographic rays are parallel and cover `width`
+// world units across the image; equirectangular covers every direction,
+// 360 degrees across and 180 degrees down.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective,
+    Orthographic { width: Float },
+    // Equidistant: the angle from the view direction grows linearly with
+    // distance from the image center, reaching half the field of view at
+    // the middle of the shorter edge. Outside that circle the image is black.
+    Fisheye,
+    Equirectangular,
+}
+
+#[derive(Clone)]
+pub struct Camera {
+    hsize: usize,
+    vsize: usize,
+    field_of_view: Float,
+    transform: Matrix4,
+    inverse: Matrix4,
+    half_width: Float,
+    half_height: Float,
+    pixel_size: Float,
+    aperture: Float,
+    focal_distance: Float,
+    shutter: (Float, Float),
+    projection: Projection,
+    shift: (Float, Float),
+}
+
+impl Camera {
+    pub fn new(hsize: usize, vsize: usize, field_of_view: Float) -> Camera {
+        let half_view = (field_of_view / 2.0).tan();
+        let aspect = hsize as Float / vsize as Float;
+        let (half_width, half_height) = if aspect >= 1.0 {
+            (half_view, half_view / aspect)
+        } else {
+            (half_view * aspect, half_view)
+        };
+        Camera {
+            hsize,
+            vsize,
+            field_of_view,
+            transform: Matrix4::identity(),
+            inverse: Matrix4::identity(),
+            half_width,
+            half_height,
+            pixel_size: (half_width * 2.0) / hsize as Float,
+            aperture: 0.0,
+            focal_distance: 1.0,
+            shutter: (0.0, 0.0),
+            projection: Projection::Perspective,
+            shift: (0.0, 0.0),
+        }
+    }
+
+    pub fn hsize(&self) -> usize {
+        self.hsize
+    }
+
+    pub fn vsize(&self) -> usize {
+        self.vsize
+    }
+
+    pub fn field_of_view(&self) -> Float {
+        self.field_of_view
+    }
+
+    pub fn transform(&self) -> &Matrix4 {
+        &self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix4) {
+        self.inverse = transform.inverse();
+        self.transform = transform;
+    }
+
+    pub fn aperture(&self) -> Float {
+        self.aperture
+    }
+
+    pub fn focal_distance(&self) -> Float {
+        self.focal_distance
+    }
+
+    // Turns the pinhole into a thin lens of the given radius. Only things
+    // `focal_distance` in front of the camera are sharp; an aperture of 0
+    // goes back to a pinhole.
+    pub fn set_lens(&mut self, aperture: Float, focal_distance: Float) {
+        self.aperture = aperture;
+        self.focal_distance = focal_distance;
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    pub fn shift(&self) -> (Float, Float) {
+        self.shift
+    }
+
+    // Slides the image window of a perspective camera without turning it,
+    // like a shift lens. Offsets are in camera space one unit in front of
+    // the camera, where the window is `2 * tan(fov / 2)` across.
+    pub fn set_shift(&mut self, x: Float, y: Float) {
+        self.shift = (x, y);
+    }
+
+    pub fn shutter(&self) -> (Float, Float) {
+        self.shutter
+    }
+
+    // The times the shutter opens and closes. Moving shapes go from their
+    // start transform at 0 to their end transform at 1, so (0, 1) blurs
+    // across the whole motion and equal times give a sharp still.
+    pub fn set_shutter(&mut self, open: Float, close: Float) {
+        self.shutter = (open, close);
+    }
+
+    fn sample_time(&self, rng: &mut Rng) -> Float {
+        let (open, close) = self.shutter;
+        if close > open {
+            open + (close - open) * rng.next_float()
+        } else {
+            open
+        }
+    }
+
+    pub fn ray_for_pixel(&self, px: usize, py: usize) -> Option<Ray> {
+        self.ray_through(px, py, 0.5, 0.5)
+    }
+
+    // A ray through the point at (u, v) within the pixel, where (0, 0) is its
+    // top left corner and (1, 1) its bottom right. None where the camera
+    // doesn't see anything, outside a fisheye's image circle.
+    pub fn ray_through(&self, px: usize, py: usize, u: Float, v: Float) -> Option<Ray> {
+        let (x, y) = (px as Float + u, py as Float + v);
+        Some(match self.projection {
+            Projection::Perspective => {
+                let (world_x, world_y) = self.image_point(x, y);
+                let pixel = self.inverse * Tuple::point(world_x, world_y, -1.0);
+                let origin = self.inverse * Tuple::point(0.0, 0.0, 0.0);
+                Ray::new(origin, (pixel - origin).normalize())
+            }
+            Projection::Orthographic { width } => {
+                let pixel_size = width / self.hsize as Float;
+                let world_x = (self.hsize as Float / 2.0 - x) * pixel_size;
+                let world_y = (self.vsize as Float / 2.0 - y) * pixel_size;
+                let origin = self.inverse * Tuple::point(world_x, world_y, 0.0);
+                Ray::new(origin, (self.inverse * Tuple::vector(0.0, 0.0, -1.0)).normalize())
+            }
+            Projection::Fisheye => {
+                let radius = self.hsize.min(self.vsize) as Float / 2.0;
+                let nx = (self.hsize as Float / 2.0 - x) / radius;
+                let ny = (self.vsize as Float / 2.0 - y) / radius;
+                if nx.hypot(ny) > 1.0 {
+                    return None;
+                }
+                let theta = nx.hypot(ny) * self.field_of_view / 2.0;
+                let phi = ny.atan2(nx);
+                let direction = Tuple::vector(theta.sin() * phi.cos(), theta.sin() * phi.sin(), -theta.cos());
+                self.camera_ray(direction)
+            }
+            Projection::Equirectangular => {
+                let longitude = (0.5 - x / self.hsize as Float) * 2.0 * PI;
+                let latitude = (0.5 - y / self.vsize as Float) * PI;
+                let direction = Tuple::vector(
+                    longitude.sin() * latitude.cos(),
+                    latitude.sin(),
+                    -longitude.cos() * latitude.cos(),
+                );
+                self.camera_ray(direction)
+            }
+        })
+    }
+
+    // Where image position (x, y), in pixels, lies on the perspective image
+    // plane one unit in front of the camera.
+    fn image_point(&self, x: Float, y: Float) -> (Float, Float) {
+        (self.half_width - x * self.pixel_size + self.shift.0, self.half_height - y * self.pixel_size + self.shift.1)
+    }
+
+    // A ray from the camera's position along a camera-space direction.
+    fn camera_ray(&self, direction: Tuple) -> Ray {
+        let origin = self.inverse * Tuple::point(0.0, 0.0, 0.0);
+        Ray::new(origin, (self.inverse * direction).normalize())
+    }
+
+    // Like `ray_through`, but starting from a random point on the lens and
+    // aimed so every ray through (u, v) meets on the plane of focus. Only
+    // perspective cameras have a lens; other projections stay sharp.
+    pub fn lens_ray(&self, px: usize, py: usize, u: Float, v: Float, rng: &mut Rng) -> Option<Ray> {
+        if self.aperture <= 0.0 || self.projection != Projection::Perspective {
+            return self.ray_through(px, py, u, v);
+        }
+        let (world_x, world_y) = self.image_point(px as Float + u, py as Float + v);
+        let f = self.focal_distance;
+        let focus = self.inverse * Tuple::point(world_x * f, world_y * f, -f);
+        let d = rng.in_unit_disk() * self.aperture;
+        let origin = self.inverse * Tuple::point(d.x(), d.y(), 0.0);
+        Some(Ray::new(origin, (focus - origin).normalize()))
+    }
+
+    pub fn render(&self, world: &World, options: &RenderOptions) -> Canvas {
+        self.render_with_progress(world, options, &|_, _| {})
+    }
+
+    // The canvas is split into tiles that worker threads take from a shared
+    // queue. `progress` is called with the number of finished tiles and the
+    // total after each tile completes. Every pixel is rendered the same way
+    // whichever thread picks it up, so the thread count doesn't change the
+    // image.
+    pub fn render_with_progress(
+        &self,
+        world: &World,
+        options: &RenderOptions,
+        progress: &(dyn Fn(usize, usize) + Sync),
+    ) -> Canvas {
+        let columns = self.hsize.div_ceil(TILE_SIZE);
+        let tiles = columns * self.vsize.div_ceil(TILE_SIZE);
+        let threads = options.threads.clamp(1, tiles.max(1));
+        let integrator = options.integrator.build(options.depth);
+        let integrator = integrator.as_ref();
+        let next = AtomicUsize::new(0);
+        let finished = AtomicUsize::new(0);
+        let rendered: Vec<Vec<(usize, Vec<Color>)>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads)
+                .map(|_| {
+                    let (next, finished) = (&next, &finished);
+                    scope.spawn(move || {
+                        let mut xs = Intersections::new();
+                        let mut samples = Vec::new();
+                        let mut done = Vec::new();
+                        loop {
+                            let tile = next.fetch_add(1, Ordering::SeqCst);
+                            if tile >= tiles {
+                                return done;
+                            }
+                            let (xr, yr) = self.tile_bounds(tile, columns);
+                            let mut colors = Vec::with_capacity(xr.len() * yr.len());
+                            for y in yr {
+                                for x in xr.clone() {
+                                    colors.push(self.render_pixel(world, integrator, options, (x, y), &mut xs, &mut samples));
+                                }
+                            }
+                            progress(finished.fetch_add(1, Ordering::SeqCst) + 1, tiles);
+                            done.push((tile, colors));
+                        }
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let mut canvas = Canvas::new(self.hsize, self.vsize);
+        for (tile, colors) in rendered.into_iter().flatten() {
+            let (xr, yr) = self.tile_bounds(tile, columns);
+            let pixels = yr.flat_map(|y| xr.clone().map(move |x| (x, y)));
+            for ((x, y), color) in pixels.zip(colors) {
+                canvas.write_pixel(x, y, color);
+            }
+        }
+        canvas
+    }
+
+    fn tile_bounds(&self, tile: usize, columns: usize) -> (Range<usize>, Range<usize>) {
+        let x = (tile % columns) * TILE_SIZE;
+        let y = (tile / columns) * TILE_SIZE;
+        (x..(x + TILE_SIZE).min(self.hsize), y..(y + TILE_SIZE).min(self.vsize))
+    }
+
+    fn render_pixel<'a>(
+        &self,
+        world: &'a World,
+        integrator: &dyn Integrator,
+        options: &RenderOptions,
+        (x, y): (usize, usize),
+        xs: &mut Intersections<'a>,
+        samples: &mut Vec<(Float, Float)>,
+    ) -> Color {
+        options.pattern.fill(options.samples, x, y, samples);
+        let mut color = Color::black();
+        for (i, &(u, v)) in samples.iter().enumerate() {
+            // Stream 0 belongs to the sampling pattern.
+            let mut rng = Rng::for_sample(x, y, i + 1);
+            if let Some(ray) = self.lens_ray(x, y, u, v, &mut rng) {
+                let ray = ray.with_time(self.sample_time(&mut rng));
+                color = color + integrator.radiance(world, &ray, xs, &mut rng);
+            }
+        }
+        color * (1.0 / samples.len() as Float)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use environment::{Background, Environment};
+    use light::PointLight;
+    use material::Material;
+    use shapes::sphere::Sphere;
+    use util;
+
+    #[test]
+    fn create_camera() {
+        let c = Camera::new(160, 120, PI / 2.0);
+        assert_eq!(c.hsize(), 160);
+        assert_eq!(c.vsize(), 120);
+        assert_eq!(c.field_of_view(), PI / 2.0);
+        assert_eq!(*c.transform(), Matrix4::identity());
+    }
+
+    #[test]
+    fn pixel_size() {
+        assert!(util::approx_eq(Camera::new(200, 125, PI / 2.0).pixel_size, 0.01));
+        assert!(util::approx_eq(Camera::new(125, 200, PI / 2.0).pixel_size, 0.01));
+    }
+
+    #[test]
+    fn ray_through_center() {
+        let c = Camera::new(201, 101, PI / 2.0);
+        let r = c.ray_for_pixel(100, 50).unwrap();
+        assert_eq!(r.origin, Tuple::point(0.0, 0.0, 0.0));
+        assert_eq!(r.direction, Tuple::vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn ray_through_corner() {
+        let c = Camera::new(201, 101, PI / 2.0);
+        let r = c.ray_for_pixel(0, 0).unwrap();
+        assert_eq!(r.origin, Tuple::point(0.0, 0.0, 0.0));
+        assert_eq!(r.direction, Tuple::vector(0.66519, 0.33259, -0.66851));
+    }
+
+    #[test]
+    fn ray_with_transformed_camera() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        c.set_transform(Matrix4::rotation_y(PI / 4.0) * Matrix4::translation(0.0, -2.0, 5.0));
+        let r = c.ray_for_pixel(100, 50).unwrap();
+        let k = Float::sqrt(2.0) / 2.0;
+        assert_eq!(r.origin, Tuple::point(0.0, 2.0, -5.0));
+        assert_eq!(r.direction, Tuple::vector(k, 0.0, -k));
+    }
+
+    #[test]
+    fn orthographic_rays_are_parallel() {
+        let mut c = Camera::new(200, 100, PI / 2.0);
+        c.set_projection(Projection::Orthographic { width: 10.0 });
+        let center = c.ray_for_pixel(100, 50).unwrap();
+        assert_eq!(center.origin, Tuple::point(-0.025, -0.025, 0.0));
+        assert_eq!(center.direction, Tuple::vector(0.0, 0.0, -1.0));
+        let corner = c.ray_through(0, 0, 0.0, 0.0).unwrap();
+        assert_eq!(corner.origin, Tuple::point(5.0, 2.5, 0.0));
+        assert_eq!(corner.direction, Tuple::vector(0.0, 0.0, -1.0));
+        c.set_transform(Matrix4::view_transform(
+            Tuple::point(0.0, 0.0, -5.0),
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+        ));
+        let r = c.ray_through(0, 0, 0.0, 0.0).unwrap();
+        assert_eq!(r.origin, Tuple::point(-5.0, 2.5, -5.0));
+        assert_eq!(r.direction, Tuple::vector(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn fisheye_angle_grows_with_radius() {
+        let mut c = Camera::new(200, 100, PI);
+        c.set_projection(Projection::Fisheye);
+        let r = c.ray_through(100, 50, 0.0, 0.0).unwrap();
+        assert_eq!(r.direction, Tuple::vector(0.0, 0.0, -1.0));
+        // The top edge is half the field of view away: straight up.
+        assert_eq!(c.ray_through(100, 0, 0.0, 0.0).unwrap().direction, Tuple::vector(0.0, 1.0, 0.0));
+        // Halfway there is a quarter of it.
+        let k = Float::sqrt(2.0) / 2.0;
+        assert_eq!(c.ray_through(75, 50, 0.0, 0.0).unwrap().direction, Tuple::vector(k, 0.0, -k));
+        // The image stops at the circle; beyond it the camera sees nothing.
+        assert_eq!(c.ray_through(50, 50, 0.0, 0.0).unwrap().direction, Tuple::vector(1.0, 0.0, 0.0));
+        assert!(c.ray_through(0, 50, 0.0, 0.0).is_none());
+        assert!(c.ray_through(49, 50, 0.0, 0.0).is_none());
+        // So the corners render black even against a bright background.
+        let mut w = World::new();
+        w.environment = Some(Environment::new(Background::Solid(Color::new(1.0, 1.0, 1.0))));
+        let mut c = Camera::new(20, 10, PI);
+        c.set_projection(Projection::Fisheye);
+        let image = c.render(&w, &RenderOptions::default());
+        assert_eq!(*image.pixel_at(10, 5), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(*image.pixel_at(0, 0), Color::black());
+    }
+
+    #[test]
+    fn equirectangular_covers_the_sphere() {
+        let mut c = Camera::new(360, 180, PI / 2.0);
+        c.set_projection(Projection::Equirectangular);
+        assert_eq!(c.ray_through(180, 90, 0.0, 0.0).unwrap().direction, Tuple::vector(0.0, 0.0, -1.0));
+        assert_eq!(c.ray_through(90, 90, 0.0, 0.0).unwrap().direction, Tuple::vector(1.0, 0.0, 0.0));
+        assert_eq!(c.ray_through(270, 90, 0.0, 0.0).unwrap().direction, Tuple::vector(-1.0, 0.0, 0.0));
+        assert_eq!(c.ray_through(0, 90, 0.0, 0.0).unwrap().direction, Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(c.ray_through(180, 0, 0.0, 0.0).unwrap().direction, Tuple::vector(0.0, 1.0, 0.0));
+        assert_eq!(c.ray_through(180, 180, 0.0, 0.0).unwrap().direction, Tuple::vector(0.0, -1.0, 0.0));
+        // The lens is ignored away from perspective.
+        c.set_lens(1.0, 2.0);
+        let r = c.lens_ray(10, 20, 0.5, 0.5, &mut Rng::new(0)).unwrap();
+        assert_eq!(r.origin, Tuple::point(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn lens_rays_meet_on_focal_plane() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        c.set_transform(Matrix4::translation(0.0, 0.0, 5.0));
+        let pinhole = c.ray_for_pixel(10, 20).unwrap();
+        let r = c.lens_ray(10, 20, 0.5, 0.5, &mut Rng::new(0)).unwrap();
+        assert_eq!((r.origin, r.direction), (pinhole.origin, pinhole.direction));
+        c.set_lens(0.5, 4.0);
+        let focus = pinhole.position(4.0 / -pinhole.direction.z());
+        let mut rng = Rng::new(1);
+        let mut origins = Vec::new();
+        for _ in 0..16 {
+            let r = c.lens_ray(10, 20, 0.5, 0.5, &mut rng).unwrap();
+            // Every ray starts on the lens and passes through the same point.
+            assert!(util::approx_eq(r.origin.z(), -5.0));
+            assert!(r.origin.x().hypot(r.origin.y()) <= 0.5);
+            assert_eq!(r.position(4.0 / -r.direction.z()), focus);
+            origins.push(r.origin);
+        }
+        assert!(origins.iter().any(|o| *o != origins[0]));
+    }
+
+    #[test]
+    fn depth_of_field_blurs_out_of_focus() {
+        // A sphere's edge stays crisp when focused on and blurs when the
+        // plane of focus is far behind it.
+        let w = test_world();
+        let mut c = test_camera();
+        let options = RenderOptions { samples: 16, pattern: Pattern::Jittered, ..RenderOptions::default() };
+        let pinhole = c.render(&w, &options);
+        c.set_lens(0.25, 4.0);
+        let focused = c.render(&w, &options);
+        c.set_lens(0.25, 40.0);
+        let blurred = c.render(&w, &options);
+        let difference = |a: &Canvas, b: &Canvas| -> i32 {
+            let pixels = (0..11).flat_map(|y| (0..11).map(move |x| (x, y)));
+            pixels
+                .map(|(x, y)| {
+                    let (p, q) = (a.pixel_at(x, y).to_rgb8(), b.pixel_at(x, y).to_rgb8());
+                    (0..3).map(|i| (p[i] as i32 - q[i] as i32).abs()).sum::<i32>()
+                })
+                .sum()
+        };
+        assert!(difference(&pinhole, &focused) < difference(&pinhole, &blurred));
+    }
+
+    #[test]
+    fn motion_blur() {
+        // A sphere sliding sideways leaves a streak when the shutter is open
+        // over its motion, and is sharp at either end when it is not.
+        let mut w = test_world();
+        w.objects.truncate(1);
+        let mut s = Sphere::new();
+        s.set_motion(Matrix4::translation(-0.5, 0.0, 0.0), Matrix4::translation(0.5, 0.0, 0.0));
+        w.objects[0] = Box::new(s);
+        let mut c = Camera::new(21, 11, PI / 2.0);
+        c.set_transform(*test_camera().transform());
+        let options = RenderOptions { samples: 16, pattern: Pattern::Jittered, ..RenderOptions::default() };
+        let lit = |canvas: &Canvas| (0..21).filter(|&x| canvas.pixel_at(x, 5).to_rgb8() != [0, 0, 0]).count();
+
+        let still = c.render(&w, &options);
+        c.set_shutter(1.0, 1.0);
+        let moved = c.render(&w, &options);
+        c.set_shutter(0.0, 1.0);
+        let blurred = c.render(&w, &options);
+        assert_eq!(lit(&still), lit(&moved));
+        assert_ne!(still.to_ppm_string(), moved.to_ppm_string());
+        assert!(lit(&blurred) > lit(&still));
+        // The streak is dimmer at its ends, where the sphere only spent part
+        // of the exposure.
+        let edge = (0..21).find(|&x| blurred.pixel_at(x, 5).to_rgb8() != [0, 0, 0]).unwrap();
+        assert!(blurred.pixel_at(edge, 5).to_rgb8()[1] < blurred.pixel_at(10, 5).to_rgb8()[1]);
+        assert_eq!(blurred.to_ppm_string(), c.render(&w, &RenderOptions { threads: 3, ..options }).to_ppm_string());
+    }
+
+    fn test_world() -> World {
+        let mut s1 = Sphere::new();
+        let mut m = Material::new();
+        m.color = Color::new(0.8, 1.0, 0.6);
+        m.diffuse = 0.7;
+        m.specular = 0.2;
+        s1.set_material(m);
+        let mut s2 = Sphere::new();
+        s2.set_transform(Matrix4::scaling(0.5, 0.5, 0.5));
+        let mut w = World::new();
+        w.objects.push(Box::new(s1));
+        w.objects.push(Box::new(s2));
+        w.lights.push(Box::new(PointLight::new(Tuple::point(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0))));
+        w
+    }
+
+    fn test_camera() -> Camera {
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        c.set_transform(Matrix4::view_transform(from, to, up));
+        c
+    }
+
+    #[test]
+    fn render_world() {
+        let image = test_camera().render(&test_world(), &RenderOptions::default());
+        assert_eq!(*image.pixel_at(5, 5), Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn render_with_threads() {
+        let w = test_world();
+        // 3x2 tiles, the last column and row partial.
+        let mut c = Camera::new(40, 20, PI / 2.0);
+        c.set_transform(*test_camera().transform());
+        let options = RenderOptions { samples: 4, pattern: Pattern::Jittered, ..RenderOptions::default() };
+        let single = c.render(&w, &options).to_ppm_string();
+        for &threads in [2, 5, 64].iter() {
+            let finished = AtomicUsize::new(0);
+            let multi = c.render_with_progress(&w, &RenderOptions { threads, ..options }, &|done, total| {
+                assert_eq!(total, 6);
+                finished.fetch_max(done, Ordering::SeqCst);
+            });
+            assert_eq!(finished.load(Ordering::SeqCst), 6);
+            assert_eq!(single, multi.to_ppm_string());
+        }
+    }
+
+    #[test]
+    fn render_with_samples() {
+        let w = test_world();
+        let c = test_camera();
+        let options = RenderOptions { samples: 4, ..RenderOptions::default() };
+        let image = c.render(&w, &options);
+        let mut expected = Color::black();
+        for &(u, v) in [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)].iter() {
+            expected = expected + w.color_at(&c.ray_through(3, 4, u, v).unwrap(), 5) * 0.25;
+        }
+        assert_eq!(*image.pixel_at(3, 4), expected);
+    }
+
+    #[test]
+    fn sample_patterns_are_repeatable() {
+        let w = test_world();
+        let c = test_camera();
+        for &pattern in [Pattern::Jittered, Pattern::Stratified, Pattern::Halton, Pattern::Sobol].iter() {
+            let options = RenderOptions { samples: 8, pattern, ..RenderOptions::default() };
+            let first = c.render(&w, &options).to_ppm_string();
+            let threaded = c.render(&w, &RenderOptions { threads: 3, ..options }).to_ppm_string();
+            assert_eq!(first, threaded);
+            assert_ne!(first, c.render(&w, &RenderOptions::default()).to_ppm_string());
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use super::color::Color;
+use super::png;
 
 pub struct Canvas {
     width: usize,
@@ -15,6 +16,14 @@ impl Canvas {
         }
     }
 
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
     pub fn write_pixel(&mut self, x: usize, y: usize, p: Color) {
         self.pixels[y * self.width + x] = p;
     }
@@ -39,6 +48,14 @@ impl Canvas {
         // Done!
         s
     }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut rgb = Vec::with_capacity(self.pixels.len() * 3);
+        for pixel in self.pixels.iter() {
+            rgb.extend_from_slice(&pixel.to_rgb8());
+        }
+        png::encode(self.width, self.height, &rgb)
+    }
 }
 
 #[cfg(test)]
@@ -93,4 +110,14 @@ mod tests {
             "P3\n5 5\n255\n0 0 0\n0 0 0\n0 0 0\n0 0 0\n0 0 0\n0 0 0\n0 0 0\n0 0 0\n0 0 0\n0 0 0\n0 0 0\n0 0 0\n0 0 0\n0 255 0\n0 0 0\n0 0 0\n0 0 0\n255 0 0\n0 0 0\n0 0 0\n0 0 0\n0 0 0\n0 0 0\n0 0 0\n0 0 0\n",
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn to_png() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(1, 0, Color::new(1.0, 0.5, 0.0));
+        let png = c.to_png();
+        assert_eq!(&png[1..4], b"PNG");
+        // First scanline: filter byte, then black, orange, black
+        assert_eq!(&png[48..58], &[0, 0, 0, 0, 255, 127, 0, 0, 0, 0]);
+    }
+}
@@ -1,21 +1,119 @@
 use super::util;
-use std::clone::Clone;
 use std::ops;
+use super::util::Float;
 
-#[derive(Debug)]
-pub struct Color (f64, f64, f64);
+#[derive(Debug, Clone, Copy)]
+pub struct Color (Float, Float, Float);
 
 impl Color {
-    pub fn new(r: f64, g: f64, b: f64) -> Color {
+    pub fn new(r: Float, g: Float, b: Float) -> Color {
         Color(r, g, b)
     }
 
+    pub fn black() -> Color {
+        Color(0.0, 0.0, 0.0)
+    }
+
+    // The color of a blackbody radiator at `kelvin`, in linear sRGB scaled
+    // so its brightest channel is 1. Around 6500K is white, tungsten at
+    // 3200K is orange and a clear sky at 10000K+ is blue.
+    pub fn from_kelvin(kelvin: Float) -> Color {
+        let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+        for (i, &(xbar, ybar, zbar)) in CIE_1931.iter().enumerate() {
+            // Planck's law up to a constant factor, with the wavelength in
+            // micrometres to keep the numbers small.
+            let lambda = (CIE_FIRST_WAVELENGTH + CIE_STEP * i as Float) / 1000.0;
+            let radiance = lambda.powi(-5) / ((PLANCK_C2 / (lambda * kelvin)).exp() - 1.0);
+            x += xbar * radiance;
+            y += ybar * radiance;
+            z += zbar * radiance;
+        }
+        let Color (r, g, b) = Color::from_xyz(x, y, z);
+        let max = r.max(g).max(b);
+        // So cold that every term underflows; the color heads for deep red.
+        if max <= 0.0 {
+            return Color(1.0, 0.0, 0.0);
+        }
+        Color(r / max, g / max, b / max)
+    }
+
+    // Converts CIE XYZ to linear sRGB, clipping colors outside its gamut.
+    pub fn from_xyz(x: Float, y: Float, z: Float) -> Color {
+        Color(
+            (3.2404542 * x - 1.5371385 * y - 0.4985314 * z).max(0.0),
+            (-0.969266 * x + 1.8760108 * y + 0.041556 * z).max(0.0),
+            (0.0556434 * x - 0.2040259 * y + 1.0572252 * z).max(0.0),
+        )
+    }
+
+    // Perceived brightness, using the Rec. 709 weights for linear sRGB.
+    pub fn luminance(&self) -> Float {
+        let &Color (r, g, b) = self;
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    pub fn to_rgb8(&self) -> [u8; 3] {
+        let &Color (r, g, b) = self;
+        [util::scale(r, 255) as u8, util::scale(g, 255) as u8, util::scale(b, 255) as u8]
+    }
+
     pub fn to_ppm_string(&self) -> String {
         let &Color (r, g, b) = self;
         format!("{} {} {}", util::scale(r, 255), util::scale(g, 255), util::scale(b, 255))
     }
 }
 
+// Second radiation constant hc/k, in micrometre kelvins.
+const PLANCK_C2: Float = 14387.769;
+
+const CIE_FIRST_WAVELENGTH: Float = 380.0;
+const CIE_STEP: Float = 10.0;
+
+// CIE 1931 2-degree color matching functions (x, y, z) from 380nm to 780nm.
+const CIE_1931: [(Float, Float, Float); 41] = [
+    (0.001368, 0.000039, 0.00645),
+    (0.004243, 0.00012, 0.02005),
+    (0.01431, 0.000396, 0.06785),
+    (0.04351, 0.00121, 0.2074),
+    (0.13438, 0.004, 0.6456),
+    (0.2839, 0.0116, 1.3856),
+    (0.34828, 0.023, 1.74706),
+    (0.3362, 0.038, 1.77211),
+    (0.2908, 0.06, 1.6692),
+    (0.19536, 0.09098, 1.28764),
+    (0.09564, 0.13902, 0.81295),
+    (0.03201, 0.20802, 0.46518),
+    (0.0049, 0.323, 0.272),
+    (0.0093, 0.503, 0.1582),
+    (0.06327, 0.71, 0.07825),
+    (0.1655, 0.862, 0.04216),
+    (0.2904, 0.954, 0.0203),
+    (0.43345, 0.99495, 0.00875),
+    (0.5945, 0.995, 0.0039),
+    (0.7621, 0.952, 0.0021),
+    (0.9163, 0.87, 0.00165),
+    (1.0263, 0.757, 0.0011),
+    (1.0622, 0.631, 0.0008),
+    (1.0026, 0.503, 0.00034),
+    (0.85445, 0.381, 0.00019),
+    (0.6424, 0.265, 0.00005),
+    (0.4479, 0.175, 0.00002),
+    (0.2835, 0.107, 0.0),
+    (0.1649, 0.061, 0.0),
+    (0.0874, 0.032, 0.0),
+    (0.04677, 0.017, 0.0),
+    (0.0227, 0.00821, 0.0),
+    (0.011359, 0.004102, 0.0),
+    (0.00579, 0.002091, 0.0),
+    (0.002899, 0.001047, 0.0),
+    (0.00144, 0.00052, 0.0),
+    (0.00069, 0.000249, 0.0),
+    (0.000332, 0.00012, 0.0),
+    (0.000166, 0.00006, 0.0),
+    (0.000083, 0.00003, 0.0),
+    (0.000042, 0.000015, 0.0),
+];
+
 impl PartialEq<Color> for Color {
   fn eq(&self, &Color (rr, rg, rb): &Color) -> bool {
       let &Color (r, g, b) = self;
@@ -52,22 +150,15 @@ impl ops::Mul<Color> for Color {
     }
 }
 
-impl ops::Mul<f64> for Color {
+impl ops::Mul<Float> for Color {
     type Output = Color;
 
-    fn mul(self, rf: f64) -> Color {
+    fn mul(self, rf: Float) -> Color {
         let Color (r, g, b) = self;
         Color(r * rf, g * rf, b * rf)
     }
 }
 
-impl Clone for Color {
-    fn clone(&self) -> Color {
-        let &Color (r, g, b) = self;
-        Color(r, g, b)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,6 +196,22 @@ mod tests {
         assert_eq!(c1 * 2.0, Color(0.4, 0.6, 0.8));
     }
 
+    #[test]
+    fn xyz() {
+        // D65 white, and luminance carrying over from Y.
+        assert_eq!(Color::from_xyz(0.95047, 1.0, 1.08883), Color(1.0, 1.0, 1.0));
+        assert!((Color::from_xyz(0.3, 0.4, 0.2).luminance() - 0.4).abs() < 1e-4);
+    }
+
+    #[test]
+    fn from_kelvin() {
+        assert_eq!(Color::from_kelvin(1900.0), Color(1.0, 0.23262, 0.0));
+        assert_eq!(Color::from_kelvin(3200.0), Color(1.0, 0.51582, 0.19377));
+        assert_eq!(Color::from_kelvin(6500.0), Color(1.0, 0.94333, 0.99129));
+        assert_eq!(Color::from_kelvin(10000.0), Color(0.60983, 0.69616, 1.0));
+        assert_eq!(Color::from_kelvin(10.0), Color(1.0, 0.0, 0.0));
+    }
+
     #[test]
     fn to_ppm_string() {
         let c1 = Color(1.0, 0.2, 0.4);
@@ -113,4 +220,10 @@ mod tests {
         let c2 = Color(0.9, 1.0, 0.1);
         assert_eq!(c2.to_ppm_string(), "229 255 25");
     }
+
+    #[test]
+    fn to_rgb8() {
+        assert_eq!(Color(1.0, 0.2, 0.4).to_rgb8(), [255, 51, 102]);
+        assert_eq!(Color(-0.5, 1.5, 0.0).to_rgb8(), [0, 255, 0]);
+    }
 }
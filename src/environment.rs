@@ -0,0 +1,308 @@
+use super::canvas::Canvas;
+use super::color::Color;
+use super::rng::Rng;
+use super::sky::Sky;
+use super::tuple::Tuple;
+use super::util::consts::PI;
+use super::util::Float;
+
+// Analytic backgrounds are tabulated at this resolution to importance
+// sample them.
+const TABLE_WIDTH: usize = 64;
+const TABLE_HEIGHT: usize = 32;
+
+// What a ray sees when it leaves the scene without hitting anything.
+pub enum Background {
+    Solid(Color),
+    // Blends from the horizon up to the zenith and down to the ground.
+    Gradient { zenith: Color, horizon: Color, ground: Color },
+    // A daylight sky lit by the sun.
+    Sky(Sky),
+    // An equirectangular map laid out like the equirectangular camera's
+    // image: -z at the center, +y along the top edge.
+    Image(Canvas),
+}
+
+impl Background {
+    fn radiance(&self, direction: &Tuple) -> Color {
+        match *self {
+            Background::Solid(color) => color,
+            Background::Gradient { zenith, horizon, ground } => {
+                let y = direction.y();
+                if y >= 0.0 {
+                    horizon * (1.0 - y) + zenith * y
+                } else {
+                    horizon * (1.0 + y) + ground * -y
+                }
+            }
+            Background::Sky(ref sky) => sky.radiance(direction),
+            Background::Image(ref image) => {
+                let (u, v) = to_uv(direction);
+                let x = ((u * image.width() as Float) as usize).min(image.width() - 1);
+                let y = ((v * image.height() as Float) as usize).min(image.height() - 1);
+                *image.pixel_at(x, y)
+            }
+        }
+    }
+}
+
+pub struct EnvironmentSample {
+    pub direction: Tuple,
+    pub radiance: Color,
+    // Probability density of `direction`, per steradian.
+    pub pdf: Float,
+}
+
+// A background that also lights the scene. Directions are sampled in
+// proportion to how bright the background is that way, so a small sun in an
+// HDR map gets most of the samples rather than the dim sky around it.
+pub struct Environment {
+    background: Background,
+    intensity: Float,
+    samples: usize,
+    distribution: Distribution,
+}
+
+impl Environment {
+    pub fn new(background: Background) -> Environment {
+        let (width, height) = match background {
+            Background::Image(ref image) => (image.width(), image.height()),
+            _ => (TABLE_WIDTH, TABLE_HEIGHT),
+        };
+        let mut weights = Vec::with_capacity(width * height);
+        let mut areas = Vec::with_capacity(width * height);
+        for y in 0..height {
+            // Rows near the poles cover less of the sphere.
+            let v = (y as Float + 0.5) / height as Float;
+            let area = (v * PI).sin();
+            for x in 0..width {
+                let direction = from_uv((x as Float + 0.5) / width as Float, v);
+                weights.push(background.radiance(&direction).luminance().max(0.0) * area);
+                areas.push(area);
+            }
+        }
+        // Nothing to favour in a black background, so sample it uniformly.
+        if weights.iter().all(|&w| w == 0.0) {
+            weights = areas;
+        }
+        Environment {
+            background,
+            intensity: 1.0,
+            samples: 16,
+            distribution: Distribution::new(width, height, &weights),
+        }
+    }
+
+    pub fn intensity(&self) -> Float {
+        self.intensity
+    }
+
+    pub fn set_intensity(&mut self, intensity: Float) {
+        self.intensity = intensity;
+    }
+
+    // Light samples taken at each shading point. Zero keeps the background
+    // but leaves lighting to the scene's lights.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    pub fn set_samples(&mut self, samples: usize) {
+        self.samples = samples;
+    }
+
+    pub fn radiance(&self, direction: &Tuple) -> Color {
+        self.background.radiance(&direction.normalize()) * self.intensity
+    }
+
+    pub fn sample(&self, rng: &mut Rng) -> EnvironmentSample {
+        let (u, v) = self.distribution.sample(rng);
+        let direction = from_uv(u, v);
+        EnvironmentSample { direction, radiance: self.radiance(&direction), pdf: self.pdf(&direction) }
+    }
+
+    pub fn pdf(&self, direction: &Tuple) -> Float {
+        let (u, v) = to_uv(&direction.normalize());
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        // From density over the unit square to density over the sphere.
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+// Image coordinates in [0, 1) for a unit direction, matching
+// `Projection::Equirectangular`.
+fn to_uv(direction: &Tuple) -> (Float, Float) {
+    let longitude = direction.x().atan2(-direction.z());
+    let latitude = direction.y().clamp(-1.0, 1.0).asin();
+    (wrap(0.5 - longitude / (2.0 * PI)), (0.5 - latitude / PI).clamp(0.0, 1.0))
+}
+
+fn from_uv(u: Float, v: Float) -> Tuple {
+    let longitude = (0.5 - u) * 2.0 * PI;
+    let latitude = (0.5 - v) * PI;
+    Tuple::vector(longitude.sin() * latitude.cos(), latitude.sin(), -longitude.cos() * latitude.cos())
+}
+
+fn wrap(u: Float) -> Float {
+    let u = u - u.floor();
+    if u >= 1.0 { 0.0 } else { u }
+}
+
+// A piecewise constant density over the unit square, sampled by picking a
+// row from the marginal distribution and then a cell within it.
+struct Distribution {
+    width: usize,
+    height: usize,
+    // Cumulative sums, each running from 0 to 1: one over the rows, then one
+    // per row over its cells.
+    rows: Vec<Float>,
+    cells: Vec<Float>,
+}
+
+impl Distribution {
+    fn new(width: usize, height: usize, weights: &[Float]) -> Distribution {
+        let mut rows = vec![0.0; height + 1];
+        let mut cells = vec![0.0; height * (width + 1)];
+        for y in 0..height {
+            let row = &mut cells[y * (width + 1)..(y + 1) * (width + 1)];
+            for x in 0..width {
+                row[x + 1] = row[x] + weights[y * width + x];
+            }
+            let sum = row[width];
+            rows[y + 1] = rows[y] + sum;
+            for c in row.iter_mut() {
+                *c = if sum > 0.0 { *c / sum } else { 0.0 };
+            }
+        }
+        let sum = rows[height];
+        for r in rows.iter_mut() {
+            *r /= sum;
+        }
+        Distribution { width, height, rows, cells }
+    }
+
+    fn row(&self, y: usize) -> &[Float] {
+        &self.cells[y * (self.width + 1)..(y + 1) * (self.width + 1)]
+    }
+
+    fn sample(&self, rng: &mut Rng) -> (Float, Float) {
+        let y = pick(&self.rows, rng.next_float());
+        let x = pick(self.row(y), rng.next_float());
+        let u = (x as Float + rng.next_float()) / self.width as Float;
+        let v = (y as Float + rng.next_float()) / self.height as Float;
+        (u, v)
+    }
+
+    fn pdf(&self, u: Float, v: Float) -> Float {
+        let x = ((u * self.width as Float) as usize).min(self.width - 1);
+        let y = ((v * self.height as Float) as usize).min(self.height - 1);
+        let row = self.row(y);
+        let p = (self.rows[y + 1] - self.rows[y]) * (row[x + 1] - row[x]);
+        p * (self.width * self.height) as Float
+    }
+}
+
+// The index of the interval of `cdf` that `u` falls in, skipping empty ones.
+fn pick(cdf: &[Float], u: Float) -> usize {
+    let i = cdf.partition_point(|&c| c <= u);
+    i.max(1).min(cdf.len() - 1) - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn white() -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    #[test]
+    fn uv_round_trip() {
+        assert_eq!(to_uv(&Tuple::vector(0.0, 0.0, -1.0)), (0.5, 0.5));
+        assert_eq!(to_uv(&Tuple::vector(1.0, 0.0, 0.0)), (0.25, 0.5));
+        assert_eq!(to_uv(&Tuple::vector(0.0, 1.0, 0.0)).1, 0.0);
+        for &(u, v) in [(0.1, 0.2), (0.6, 0.5), (0.9, 0.8)].iter() {
+            let (u2, v2) = to_uv(&from_uv(u, v));
+            assert!((u - u2).abs() < 1e-4 && (v - v2).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn gradient() {
+        let sky = Environment::new(Background::Gradient {
+            zenith: Color::new(0.2, 0.4, 1.0),
+            horizon: white(),
+            ground: Color::new(0.2, 0.2, 0.2),
+        });
+        assert_eq!(sky.radiance(&Tuple::vector(0.0, 1.0, 0.0)), Color::new(0.2, 0.4, 1.0));
+        assert_eq!(sky.radiance(&Tuple::vector(1.0, 0.0, 0.0)), white());
+        assert_eq!(sky.radiance(&Tuple::vector(0.0, -2.0, 0.0)), Color::new(0.2, 0.2, 0.2));
+        assert_eq!(sky.radiance(&Tuple::vector(0.0, 1.0, -1.0)), Color::new(0.43431, 0.57574, 1.0));
+    }
+
+    #[test]
+    fn image_lookup() {
+        let mut image = Canvas::new(4, 2);
+        image.write_pixel(2, 0, Color::new(5.0, 4.0, 3.0));
+        let mut env = Environment::new(Background::Image(image));
+        env.set_intensity(2.0);
+        assert_eq!(env.radiance(&Tuple::vector(-0.1, 0.5, -1.0)), Color::new(10.0, 8.0, 6.0));
+        assert_eq!(env.radiance(&Tuple::vector(0.1, 0.5, -1.0)), Color::black());
+    }
+
+    #[test]
+    fn sky_samples_favour_the_sun() {
+        let sky = Sky::new(3.0, 0.6, 1.0);
+        let env = Environment::new(Background::Sky(sky));
+        let mut rng = Rng::new(9);
+        let near = (0..1000).filter(|_| env.sample(&mut rng).direction.dot(&sky.sun_direction()) > 0.7).count();
+        // The cap around the sun is 15% of the sphere.
+        assert!(near > 200, "{}", near);
+    }
+
+    #[test]
+    fn samples_follow_luminance() {
+        // One bright pixel in an otherwise dim map.
+        let mut image = Canvas::new(16, 8);
+        for y in 0..8 {
+            for x in 0..16 {
+                image.write_pixel(x, y, Color::new(0.1, 0.1, 0.1));
+            }
+        }
+        image.write_pixel(5, 3, Color::new(100.0, 100.0, 100.0));
+        let env = Environment::new(Background::Image(image));
+        let mut rng = Rng::new(5);
+        let n = 20000;
+        let (mut bright, mut estimate) = (0, 0.0);
+        for _ in 0..n {
+            let s = env.sample(&mut rng);
+            assert!(close(s.pdf, env.pdf(&s.direction)));
+            if s.radiance.luminance() > 1.0 {
+                bright += 1;
+            }
+            estimate += s.radiance.luminance() / s.pdf;
+        }
+        assert!(bright as Float / n as Float > 0.9);
+        // The integral of radiance over the sphere: 0.1 over all of it plus
+        // the bright pixel's solid angle.
+        let rows = |y: Float| (PI * y / 8.0).cos();
+        let pixel = 2.0 * PI / 16.0 * (rows(3.0) - rows(4.0));
+        let expected = 0.1 * 4.0 * PI + 99.9 * pixel;
+        assert!((estimate / n as Float - expected).abs() / expected < 0.02);
+    }
+
+    #[test]
+    fn black_background_samples_uniformly() {
+        let env = Environment::new(Background::Solid(Color::black()));
+        let s = env.sample(&mut Rng::new(1));
+        assert!(s.pdf > 0.0);
+        assert!((env.pdf(&Tuple::vector(0.0, 0.0, 1.0)) - 1.0 / (4.0 * PI)).abs() < 0.01);
+    }
+
+    fn close(a: Float, b: Float) -> bool {
+        (a - b).abs() <= 1e-3 * a.abs().max(1.0)
+    }
+}
@@ -0,0 +1,172 @@
+// A decoder for Radiance RGBE (.hdr) images, the usual format for
+// high dynamic range environment maps. Each pixel is three 8-bit mantissas
+// sharing an 8-bit exponent. Scanlines may be flat or use the run-length
+// encoding that stores each channel separately.
+
+use super::canvas::Canvas;
+use super::color::Color;
+use super::util::Float;
+
+pub fn decode(data: &[u8]) -> Result<Canvas, String> {
+    let mut pos = 0;
+    let mut first = true;
+    loop {
+        let line = read_line(data, &mut pos).ok_or("unexpected end of header")?;
+        if first && !line.starts_with("#?") {
+            return Err("not a Radiance HDR image".to_string());
+        }
+        first = false;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+            return Err(format!("unsupported pixel format '{}'", &line[7..]));
+        }
+    }
+    let resolution = read_line(data, &mut pos).ok_or("missing image size")?;
+    let (width, height) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", h, "+X", w] => match (w.parse::<usize>(), h.parse::<usize>()) {
+            (Ok(w), Ok(h)) if w > 0 && h > 0 => (w, h),
+            _ => return Err(format!("bad image size '{}'", resolution)),
+        },
+        _ => return Err(format!("unsupported image orientation '{}'", resolution)),
+    };
+    if width.checked_mul(height).and_then(|n| n.checked_mul(4)).is_none() {
+        return Err(format!("bad image size '{}'", resolution));
+    }
+    // Checked before allocating, so a corrupt size can't claim more memory
+    // than the file could fill.
+    if height.saturating_mul(min_scanline_bytes(width)) > data.len() - pos {
+        return Err("image data ends early".to_string());
+    }
+
+    let mut canvas = Canvas::new(width, height);
+    let mut scanline = vec![0u8; width * 4];
+    for y in 0..height {
+        read_scanline(data, &mut pos, &mut scanline)?;
+        for x in 0..width {
+            let p = &scanline[x * 4..x * 4 + 4];
+            canvas.write_pixel(x, y, rgbe(p[0], p[1], p[2], p[3]));
+        }
+    }
+    Ok(canvas)
+}
+
+fn read_line<'a>(data: &'a [u8], pos: &mut usize) -> Option<&'a str> {
+    let end = *pos + data[*pos..].iter().position(|&b| b == b'\n')?;
+    let line = std::str::from_utf8(&data[*pos..end]).ok()?;
+    *pos = end + 1;
+    Some(line.trim_end_matches('\r'))
+}
+
+// The fewest bytes a scanline `width` pixels wide can be stored in: four
+// flat bytes a pixel, or for run-length encoding the four byte marker and
+// one two-byte run per 127 pixels of each channel.
+fn min_scanline_bytes(width: usize) -> usize {
+    if (8..0x8000).contains(&width) {
+        4 + 4 * 2 * width.div_ceil(127)
+    } else {
+        width.saturating_mul(4)
+    }
+}
+
+// Fills `out` with one scanline of RGBE quadruples.
+fn read_scanline(data: &[u8], pos: &mut usize, out: &mut [u8]) -> Result<(), String> {
+    let width = out.len() / 4;
+    let rest = &data[*pos..];
+    let run_length = (8..0x8000).contains(&width)
+        && rest.len() >= 4
+        && rest[0] == 2
+        && rest[1] == 2
+        && rest[2] & 0x80 == 0;
+    if !run_length {
+        let bytes = rest.get(..out.len()).ok_or("image data ends early")?;
+        out.copy_from_slice(bytes);
+        *pos += out.len();
+        return Ok(());
+    }
+    if ((rest[2] as usize) << 8 | rest[3] as usize) != width {
+        return Err("scanline width does not match the image".to_string());
+    }
+    *pos += 4;
+    let mut next = || {
+        let byte = *data.get(*pos).ok_or("image data ends early")?;
+        *pos += 1;
+        Ok::<u8, String>(byte)
+    };
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = next()? as usize;
+            let (count, run) = if count > 128 { (count - 128, true) } else { (count, false) };
+            if count == 0 || x + count > width {
+                return Err("bad run in scanline".to_string());
+            }
+            let value = if run { next()? } else { 0 };
+            for _ in 0..count {
+                out[x * 4 + channel] = if run { value } else { next()? };
+                x += 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn rgbe(r: u8, g: u8, b: u8, e: u8) -> Color {
+    if e == 0 {
+        return Color::black();
+    }
+    // The mantissas are fractions of 256 scaled by 2^(e - 128).
+    let f = 2f64.powi(e as i32 - 136) as Float;
+    Color::new(r as Float * f, g as Float * f, b as Float * f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(size: &str) -> Vec<u8> {
+        format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\nEXPOSURE=1.0\n\n{}\n", size).into_bytes()
+    }
+
+    #[test]
+    fn flat_scanlines() {
+        let mut data = header("-Y 2 +X 1");
+        data.extend_from_slice(&[128, 64, 0, 129, 0, 0, 0, 0]);
+        let image = decode(&data).unwrap();
+        assert_eq!((image.width(), image.height()), (1, 2));
+        assert_eq!(*image.pixel_at(0, 0), Color::new(1.0, 0.5, 0.0));
+        assert_eq!(*image.pixel_at(0, 1), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn run_length_scanlines() {
+        let mut data = header("-Y 1 +X 8");
+        data.extend_from_slice(&[2, 2, 0, 8]);
+        // Red: a run of eight. Green: eight literals. Blue: two runs.
+        data.extend_from_slice(&[136, 128]);
+        data.extend_from_slice(&[8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        data.extend_from_slice(&[132, 0, 132, 255]);
+        data.extend_from_slice(&[136, 130]);
+        let image = decode(&data).unwrap();
+        assert_eq!(*image.pixel_at(0, 0), Color::new(2.0, 0.0, 0.0));
+        assert_eq!(*image.pixel_at(7, 0), Color::new(2.0, 1.75, 3.984375));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(decode(b"P3\n1 1\n").err().unwrap(), "not a Radiance HDR image");
+        assert_eq!(decode(&header("+Y 1 +X 1")).err().unwrap(), "unsupported image orientation '+Y 1 +X 1'");
+        let mut data = header("-Y 1 +X 2");
+        data.extend_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(decode(&data).err().unwrap(), "image data ends early");
+        // Sizes the data can't hold are rejected before anything is allocated.
+        let mut data = header("-Y 100000 +X 100000");
+        data.extend_from_slice(&[2, 2, 0x86, 0xa0, 0x80, 0, 0x80, 0]);
+        assert_eq!(decode(&data).err().unwrap(), "image data ends early");
+        let huge = "-Y 4000000000 +X 4000000000";
+        assert_eq!(decode(&header(huge)).err().unwrap(), format!("bad image size '{}'", huge));
+        let data = b"#?RGBE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n";
+        assert_eq!(decode(data).err().unwrap(), "unsupported pixel format '32-bit_rle_xyze'");
+    }
+}
@@ -0,0 +1,428 @@
+use super::color::Color;
+use super::intersection::{Computations, Intersections};
+use super::ray::Ray;
+use super::rng::Rng;
+use super::util;
+use super::util::consts::PI;
+use super::util::Float;
+use super::world::World;
+
+// Bounces a path tracer takes before Russian roulette starts ending paths.
+const ROULETTE_DEPTH: usize = 3;
+
+// Works out the light arriving along a camera ray. `xs` is scratch space
+// for intersections, reused from ray to ray.
+pub trait Integrator: Send + Sync {
+    fn radiance<'a>(&self, world: &'a World, ray: &Ray, xs: &mut Intersections<'a>, rng: &mut Rng) -> Color;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IntegratorKind {
+    Whitted,
+    Path,
+}
+
+impl IntegratorKind {
+    pub fn from_name(name: &str) -> Option<IntegratorKind> {
+        match name.to_ascii_lowercase().as_str() {
+            "whitted" => Some(IntegratorKind::Whitted),
+            "path" => Some(IntegratorKind::Path),
+            _ => None,
+        }
+    }
+
+    // An integrator of this kind that follows rays `depth` bounces deep.
+    pub fn build(self, depth: usize) -> Box<dyn Integrator> {
+        match self {
+            IntegratorKind::Whitted => Box::new(Whitted { depth }),
+            IntegratorKind::Path => Box::new(PathTracer { depth }),
+        }
+    }
+}
+
+// Phong shading from each light plus mirror reflections, recursing up to
+// `depth` times.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Whitted {
+    pub depth: usize,
+}
+
+impl Integrator for Whitted {
+    fn radiance<'a>(&self, world: &'a World, ray: &Ray, xs: &mut Intersections<'a>, rng: &mut Rng) -> Color {
+        world.trace(ray, self.depth, xs, rng)
+    }
+}
+
+// Unidirectional path tracing. Each vertex samples the lights and the
+// environment directly, then carries on in one direction: along the mirror
+// reflection, through a transparent surface, or into a cosine-weighted
+// direction off the diffuse and glossy surface. Indirect light replaces the materials' ambient term.
+//
+// Lights, emissive shapes and the environment are sampled at every vertex.
+// Emissive shapes and the environment are also found by paths that happen
+// to hit them, so the two estimates are blended with the power heuristic.
+// Infinite emissive planes can't be sampled and are only found by chance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathTracer {
+    // Bounces after the camera ray's hit. Russian roulette usually ends
+    // paths well before this.
+    pub depth: usize,
+}
+
+impl PathTracer {
+    // Light arriving straight from the lights, emissive shapes and
+    // environment, reflected
+    // towards the eye. `diffuse_chance` is how likely the path is to carry
+    // on off the surface, rather than reflect like a mirror, refract or stop
+    // here.
+    fn direct(&self, world: &World, comps: &Computations, diffuse_chance: Float, rng: &mut Rng) -> Color {
+        let material = comps.object.material();
+        let point = comps.over_point;
+        let mut sum = Color::black();
+        for light in world.lights.iter() {
+            let i = rng.below(light.samples() as u32) as usize;
+            let sample = light.sample_direction(i, point, rng);
+            let cos = sample.direction.dot(&comps.normalv);
+            if cos <= 0.0 || world.occluded(&Ray::new(point, sample.direction).with_time(comps.time), sample.distance) {
+                continue;
+            }
+            // Light intensities are Phong's, which leaves out the 1/pi of a
+            // Lambertian surface, so they're scaled up to match.
+            let f = material.brdf(sample.direction, comps.eyev, comps.normalv);
+            sum = sum + f * light.intensity_at(point) * (PI * cos);
+        }
+        for object in world.objects.iter() {
+            let emissive = object.material().emissive;
+            if emissive.luminance() <= 0.0 {
+                continue;
+            }
+            let sample = match object.sample_surface(comps.time, rng) {
+                Some(sample) => sample,
+                None => continue,
+            };
+            let to_light = sample.point - point;
+            let distance = to_light.magnitude();
+            let direction = to_light * (1.0 / distance);
+            let cos = direction.dot(&comps.normalv);
+            let cos_light = direction.dot(&sample.normal).abs();
+            if cos <= 0.0 || cos_light <= 0.0 {
+                continue;
+            }
+            // Stop short of the sampled point so the shape doesn't shadow itself.
+            let ray = Ray::new(point, direction).with_time(comps.time);
+            if world.occluded(&ray, distance - util::EPSILON) {
+                continue;
+            }
+            let pdf = sample.pdf * distance * distance / cos_light;
+            let weight = power_heuristic(pdf, cos / PI * diffuse_chance);
+            let f = material.brdf(direction, comps.eyev, comps.normalv);
+            sum = sum + f * emissive * (cos * weight / pdf);
+        }
+        if let Some(ref environment) = world.environment {
+            if environment.samples() > 0 {
+                let sample = environment.sample(rng);
+                let cos = sample.direction.dot(&comps.normalv);
+                let ray = Ray::new(point, sample.direction).with_time(comps.time);
+                if cos > 0.0 && sample.pdf > 0.0 && !world.occluded(&ray, Float::INFINITY) {
+                    let weight = power_heuristic(sample.pdf, cos / PI * diffuse_chance);
+                    let f = material.brdf(sample.direction, comps.eyev, comps.normalv);
+                    sum = sum + f * sample.radiance * (cos * weight / sample.pdf);
+                }
+            }
+        }
+        sum
+    }
+}
+
+impl Integrator for PathTracer {
+    fn radiance<'a>(&self, world: &'a World, ray: &Ray, xs: &mut Intersections<'a>, rng: &mut Rng) -> Color {
+        let mut ray = *ray;
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut radiance = Color::black();
+        // The density the last diffuse bounce chose its direction with, or
+        // None after a mirror bounce or refraction, which direct sampling
+        // can't reproduce.
+        let mut bounce_pdf: Option<Float> = None;
+        for bounce in 0.. {
+            world.intersect_into(&ray, xs);
+            let hit = match xs.hit() {
+                Some(&hit) => hit,
+                None => {
+                    if let Some(ref environment) = world.environment {
+                        let weight = match bounce_pdf {
+                            Some(pdf) if environment.samples() > 0 => {
+                                power_heuristic(pdf, environment.pdf(&ray.direction))
+                            }
+                            _ => 1.0,
+                        };
+                        radiance = radiance + throughput * environment.radiance(&ray.direction) * weight;
+                    }
+                    break;
+                }
+            };
+            let comps = hit.prepare_computations(&ray, xs);
+            let material = comps.object.material();
+            // The last vertex sampled this shape directly as well.
+            let weight = match bounce_pdf {
+                Some(pdf) if material.emissive.luminance() > 0.0 => {
+                    let distance = (comps.point - ray.origin).magnitude();
+                    let cos_light = comps.eyev.dot(&comps.normalv) / comps.eyev.magnitude();
+                    let area_pdf = comps.object.surface_pdf(comps.point, comps.time);
+                    if area_pdf > 0.0 && cos_light > 0.0 {
+                        power_heuristic(pdf, area_pdf * distance * distance / cos_light)
+                    } else {
+                        1.0
+                    }
+                }
+                _ => 1.0,
+            };
+            radiance = radiance + throughput * material.emissive * weight;
+
+            // Mirror reflection and refraction add to the surface's own
+            // shading rather than taking a share of it, as in
+            // `World::shade_hit`. Each branch is picked in proportion to its
+            // weight and weighted up by the total.
+            let reflective = material.reflective.clamp(0.0, 1.0);
+            let refracted = comps.refracted_direction().filter(|_| material.transparency > 0.0);
+            let transparency = if refracted.is_some() { material.transparency.clamp(0.0, 1.0) } else { 0.0 };
+            let (mirror, refract) = if reflective > 0.0 && transparency > 0.0 {
+                let reflectance = comps.schlick();
+                (reflective * reflectance, transparency * (1.0 - reflectance))
+            } else {
+                (reflective, transparency)
+            };
+            let total = 1.0 + mirror + refract;
+            let last = bounce == self.depth;
+            let diffuse_chance = if last { 0.0 } else { 1.0 / total };
+            radiance = radiance + throughput * self.direct(world, &comps, diffuse_chance, rng);
+            if last {
+                break;
+            }
+
+            let choice = rng.next_float() * total;
+            let (origin, direction) = match refracted {
+                _ if choice < mirror => {
+                    bounce_pdf = None;
+                    throughput = throughput * total;
+                    (comps.over_point, comps.reflectv)
+                }
+                Some(direction) if choice < mirror + refract => {
+                    bounce_pdf = None;
+                    throughput = throughput * total;
+                    (comps.under_point, direction)
+                }
+                _ => {
+                    let direction = rng.cosine_hemisphere(&comps.normalv);
+                    let cos = direction.dot(&comps.normalv);
+                    if cos <= 0.0 {
+                        break;
+                    }
+                    let pdf = cos / PI * diffuse_chance;
+                    bounce_pdf = Some(pdf);
+                    throughput = throughput * material.brdf(direction, comps.eyev, comps.normalv) * (cos / pdf);
+                    (comps.over_point, direction)
+                }
+            };
+            ray = Ray::new(origin, direction).with_time(comps.time);
+
+            if bounce + 1 >= ROULETTE_DEPTH {
+                let survival = throughput.luminance().min(0.95);
+                if survival <= 0.0 || rng.next_float() >= survival {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival);
+            }
+        }
+        radiance
+    }
+}
+
+// Veach's weight for a sample drawn with density `a` where another strategy
+// would have drawn it with density `b`.
+fn power_heuristic(a: Float, b: Float) -> Float {
+    let (a2, b2) = (a * a, b * b);
+    if a2 + b2 > 0.0 { a2 / (a2 + b2) } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canvas::Canvas;
+    use environment::{Background, Environment};
+    use light::PointLight;
+    use material::Material;
+    use matrix4::Matrix4;
+    use shapes::plane::Plane;
+    use shapes::sphere::Sphere;
+    use tuple::Tuple;
+
+    fn average(integrator: &dyn Integrator, world: &World, ray: &Ray, n: usize) -> Color {
+        let mut xs = Intersections::new();
+        let mut sum = Color::black();
+        for i in 0..n {
+            sum = sum + integrator.radiance(world, ray, &mut xs, &mut Rng::new(i as u64));
+        }
+        sum * (1.0 / n as Float)
+    }
+
+    // The inside of a sphere that glows and reflects half the light reaching it.
+    fn furnace() -> World {
+        let mut w = World::new();
+        let mut s = Sphere::new();
+        s.set_material(Material {
+            ambient: 0.0,
+            diffuse: 0.5,
+            specular: 0.0,
+            emissive: Color::new(0.5, 0.5, 0.5),
+            ..Material::new()
+        });
+        w.objects.push(Box::new(s));
+        w
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(IntegratorKind::from_name("Path"), Some(IntegratorKind::Path));
+        assert_eq!(IntegratorKind::from_name("whitted"), Some(IntegratorKind::Whitted));
+        assert_eq!(IntegratorKind::from_name("bidirectional"), None);
+    }
+
+    #[test]
+    fn whitted_matches_world_trace() {
+        let mut w = World::new();
+        w.objects.push(Box::new(Sphere::new()));
+        w.lights.push(Box::new(PointLight::new(Tuple::point(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0))));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.1, 1.0));
+        let whitted = IntegratorKind::Whitted.build(5);
+        assert_eq!(whitted.radiance(&w, &r, &mut Intersections::new(), &mut Rng::new(0)), w.color_at(&r, 5));
+    }
+
+    #[test]
+    fn furnace_before_roulette() {
+        // Every bounce gathers half as much glow as the one before, and the
+        // last vertex samples the glow directly like it would a light.
+        let w = furnace();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.3, 0.4, 1.0));
+        let c = average(&PathTracer { depth: 2 }, &w, &r, 1000).luminance();
+        assert!((c - 0.9375).abs() < 0.005, "{}", c);
+    }
+
+    #[test]
+    fn furnace_converges() {
+        // With enough bounces the glow adds up to 0.5 / (1 - 0.5).
+        let w = furnace();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        let c = average(&PathTracer { depth: 100 }, &w, &r, 4000).luminance();
+        assert!((c - 1.0).abs() < 0.05, "{}", c);
+    }
+
+    #[test]
+    fn environment_counted_once() {
+        // A floor under a uniform white sky, lit both by sampling the sky
+        // and by bounce rays escaping to it.
+        let mut w = World::new();
+        let mut floor = Plane::new();
+        floor.set_material(Material { ambient: 0.0, specular: 0.0, diffuse: 0.8, ..Material::new() });
+        w.objects.push(Box::new(floor));
+        let mut image = Canvas::new(8, 4);
+        for y in 0..4 {
+            for x in 0..8 {
+                image.write_pixel(x, y, Color::new(1.0, 1.0, 1.0));
+            }
+        }
+        w.environment = Some(Environment::new(Background::Image(image)));
+        let down = Ray::new(Tuple::point(0.0, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        for &depth in [0, 1, 4].iter() {
+            let c = average(&PathTracer { depth }, &w, &down, 4000).luminance();
+            assert!((c - 0.8).abs() < 0.03, "depth {}: {}", depth, c);
+        }
+    }
+
+    #[test]
+    fn color_bleeding() {
+        // A white floor beside a red wall, lit from above. Light bounced off
+        // the wall tints the floor next to it, which Whitted shading misses.
+        let mut w = World::new();
+        let mut floor = Plane::new();
+        floor.set_material(Material { ambient: 0.0, specular: 0.0, ..Material::new() });
+        w.objects.push(Box::new(floor));
+        let mut wall = Plane::new();
+        wall.set_transform(Matrix4::rotation_z(PI / 2.0).translate(1.0, 0.0, 0.0));
+        wall.set_material(Material { color: Color::new(1.0, 0.0, 0.0), ambient: 0.0, specular: 0.0, ..Material::new() });
+        w.objects.push(Box::new(wall));
+        w.lights.push(Box::new(PointLight::new(Tuple::point(0.0, 3.0, 0.0), Color::new(0.3, 0.3, 0.3))));
+        let r = Ray::new(Tuple::point(0.8, 1.0, -1.0), Tuple::vector(0.0, -1.0, 1.0));
+        let redness = |c: Color| {
+            let [red, green, _] = c.to_rgb8();
+            red as i32 - green as i32
+        };
+        assert_eq!(redness(average(&Whitted { depth: 5 }, &w, &r, 1)), 0);
+        assert!(redness(average(&PathTracer { depth: 5 }, &w, &r, 500)) > 10);
+    }
+
+    #[test]
+    fn refraction_shows_the_background() {
+        // A clear glass sphere in front of a green sky, seen straight on.
+        let mut w = World::new();
+        let mut glass = Sphere::new();
+        glass.set_material(Material {
+            ambient: 0.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            transparency: 1.0,
+            refractive_index: 1.5,
+            ..Material::new()
+        });
+        w.objects.push(Box::new(glass));
+        let green = Color::new(0.0, 1.0, 0.0);
+        w.environment = Some(Environment::new(Background::Solid(green)));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let c = average(&PathTracer { depth: 5 }, &w, &r, 4000);
+        assert!((c.luminance() / green.luminance() - 1.0).abs() < 0.1, "{:?}", c);
+        assert_eq!(c.to_rgb8()[0], 0);
+    }
+
+    #[test]
+    fn emissive_shapes_sampled_directly() {
+        // A floor under a small glowing ball. The point below the ball
+        // reflects 0.8 * 16 * (0.25 / 2)^2 = 0.2.
+        let mut w = World::new();
+        let mut floor = Plane::new();
+        floor.set_material(Material { ambient: 0.0, specular: 0.0, diffuse: 0.8, ..Material::new() });
+        w.objects.push(Box::new(floor));
+        let mut ball = Sphere::new();
+        ball.set_transform(Matrix4::scaling(0.25, 0.25, 0.25).translate(0.0, 2.0, 0.0));
+        ball.set_material(Material {
+            ambient: 0.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            emissive: Color::new(16.0, 16.0, 16.0),
+            ..Material::new()
+        });
+        w.objects.push(Box::new(ball));
+        let r = Ray::new(Tuple::point(0.0, 1.0, -1.0), Tuple::vector(0.0, -1.0, 1.0));
+        let n = 4000;
+        let stats = |samples: &[Float]| {
+            let mean = samples.iter().sum::<Float>() / n as Float;
+            let variance = samples.iter().map(|s| (s - mean) * (s - mean)).sum::<Float>() / n as Float;
+            (mean, variance)
+        };
+        let mut xs = Intersections::new();
+        let path: Vec<Float> = (0..n)
+            .map(|i| PathTracer { depth: 1 }.radiance(&w, &r, &mut xs, &mut Rng::new(i as u64)).luminance())
+            .collect();
+        // The same floor point lit only by bounce rays that find the ball.
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let bsdf: Vec<Float> = (0..n)
+            .map(|i| {
+                let bounce = Ray::new(Tuple::point(0.0, util::EPSILON, 0.0), Rng::new(i as u64).cosine_hemisphere(&up));
+                if w.intersect(&bounce).hit().is_some() { 0.8 * 16.0 } else { 0.0 }
+            })
+            .collect();
+        let (path_mean, path_variance) = stats(&path);
+        let (bsdf_mean, bsdf_variance) = stats(&bsdf);
+        assert!((path_mean - 0.2).abs() < 0.01, "{}", path_mean);
+        assert!((bsdf_mean - 0.2).abs() < 0.08, "{}", bsdf_mean);
+        assert!(path_variance * 10.0 < bsdf_variance, "{} vs {}", path_variance, bsdf_variance);
+    }
+}
@@ -1,11 +1,412 @@
+use super::bounds::Bounds;
 use super::ray::Ray;
 use super::material::Material;
+use super::rng::Rng;
+use super::tuple::Tuple;
+use super::util;
+use super::util::Float;
+use std::ops;
+use std::slice;
 
-pub trait Intersectable {
-    fn intersect(&self, ray: &Ray) -> Vec<Intersection>;
+// Shapes are shared between render threads.
+pub trait Intersectable: Send + Sync {
+    // Adds this shape's intersections with `ray` that lie in tmin..tmax to `xs`.
+    fn intersect_into<'a>(&'a self, ray: &Ray, tmin: Float, tmax: Float, xs: &mut Intersections<'a>);
+    // Whether the shape crosses the ray anywhere in 0..tmax. Answers shadow
+    // queries without collecting or sorting intersections.
+    fn occluded(&self, ray: &Ray, tmax: Float) -> bool;
+    // The normal at `point` on the shape as it was at `time`.
+    fn normal_at_time(&self, point: Tuple, time: Float) -> Tuple;
+    // A world-space box holding the shape throughout its motion.
+    fn bounds(&self) -> Bounds;
+    fn material(&self) -> &Material;
+
+    // A point spread evenly over the shape's surface as it was at `time`, so
+    // emissive shapes can be sampled like lights. None for shapes without a
+    // finite area, which paths only find by chance.
+    fn sample_surface(&self, _time: Float, _rng: &mut Rng) -> Option<SurfaceSample> {
+        None
+    }
+
+    // The density `sample_surface` picks `point` with, per unit of area.
+    fn surface_pdf(&self, _point: Tuple, _time: Float) -> Float {
+        0.0
+    }
+
+    fn normal_at(&self, point: Tuple) -> Tuple {
+        self.normal_at_time(point, 0.0)
+    }
+
+    // Every intersection along the whole line, including behind the origin.
+    fn intersect(&self, ray: &Ray) -> Intersections<'_> where Self: Sized {
+        let mut xs = Intersections::new();
+        self.intersect_into(ray, Float::NEG_INFINITY, Float::INFINITY, &mut xs);
+        xs.sort();
+        xs
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceSample {
+    pub point: Tuple,
+    pub normal: Tuple,
+    // Probability density of `point`, per unit of world-space area.
+    pub pdf: Float,
+}
+
+#[derive(Clone, Copy)]
+pub struct Intersection<'a> {
+    pub t: Float,
+    pub object: &'a dyn Intersectable,
+}
+
+impl<'a> Intersection<'a> {
+    pub fn new(t: Float, object: &'a dyn Intersectable) -> Intersection<'a> {
+        Intersection { t, object }
+    }
+
+    // Everything shading needs to know about this intersection. `xs` is the
+    // full set of intersections along `ray`, used to find the refractive
+    // indices on either side of the surface.
+    pub fn prepare_computations(&self, ray: &Ray, xs: &Intersections<'a>) -> Computations<'a> {
+        let point = ray.position(self.t);
+        let eyev = -ray.direction;
+        let mut normalv = self.object.normal_at_time(point, ray.time);
+        let inside = normalv.dot(&eyev) < 0.0;
+        if inside {
+            normalv = -normalv;
+        }
+        let (n1, n2) = self.refractive_indices(xs);
+        Computations {
+            t: self.t,
+            object: self.object,
+            point,
+            eyev,
+            normalv,
+            inside,
+            over_point: point + normalv * util::EPSILON,
+            under_point: point - normalv * util::EPSILON,
+            reflectv: ray.direction.reflect(&normalv),
+            n1,
+            n2,
+            time: ray.time,
+        }
+    }
+
+    // The media the ray leaves and enters at this intersection, worked out
+    // from the intersections before it.
+    fn refractive_indices(&self, xs: &Intersections<'a>) -> (Float, Float) {
+        match xs.iter().position(|x| x.t == self.t && same_object(x.object, self.object)) {
+            Some(i) => (medium(&xs.xs[..i]), medium(&xs.xs[..=i])),
+            None => (1.0, 1.0),
+        }
+    }
+}
+
+// The refractive index the ray is in after crossing `passed`: that of the
+// object it entered most recently and hasn't left since. A ray is inside an
+// object after crossing its surface an odd number of times.
+fn medium(passed: &[Intersection]) -> Float {
+    for (i, x) in passed.iter().enumerate().rev() {
+        let same = |other: &&Intersection| same_object(other.object, x.object);
+        // Only the last crossing of each object says whether the ray is in it.
+        if passed[i + 1..].iter().any(|other| same(&other)) {
+            continue;
+        }
+        if passed.iter().filter(same).count() % 2 == 1 {
+            return x.object.material().refractive_index;
+        }
+    }
+    1.0
+}
+
+fn same_object(a: &dyn Intersectable, b: &dyn Intersectable) -> bool {
+    a as *const dyn Intersectable as *const u8 == b as *const dyn Intersectable as *const u8
+}
+
+pub struct Computations<'a> {
+    pub t: Float,
+    pub object: &'a dyn Intersectable,
+    pub point: Tuple,
+    pub eyev: Tuple,
+    pub normalv: Tuple,
+    pub inside: bool,
+    // Just above and just below the surface, so shadow and refraction rays
+    // don't hit the surface they start from.
+    pub over_point: Tuple,
+    pub under_point: Tuple,
+    pub reflectv: Tuple,
+    pub n1: Float,
+    pub n2: Float,
+    // When the ray was cast; rays spawned from here share it.
+    pub time: Float,
+}
+
+impl<'a> Computations<'a> {
+    // The direction a ray passing into the surface carries on in, bent by
+    // Snell's law, or None if it's totally internally reflected.
+    pub fn refracted_direction(&self) -> Option<Tuple> {
+        let n_ratio = self.n1 / self.n2;
+        let cos_i = self.eyev.dot(&self.normalv);
+        let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
+        if sin2_t > 1.0 {
+            return None;
+        }
+        let cos_t = (1.0 - sin2_t).sqrt();
+        Some(self.normalv * (n_ratio * cos_i - cos_t) - self.eyev * n_ratio)
+    }
+
+    // Schlick's approximation of the fraction of light reflected rather
+    // than refracted.
+    pub fn schlick(&self) -> Float {
+        let mut cos = self.eyev.dot(&self.normalv);
+        if self.n1 > self.n2 {
+            let n_ratio = self.n1 / self.n2;
+            let sin2_t = n_ratio * n_ratio * (1.0 - cos * cos);
+            if sin2_t > 1.0 {
+                return 1.0;
+            }
+            cos = (1.0 - sin2_t).sqrt();
+        }
+        let r0 = ((self.n1 - self.n2) / (self.n1 + self.n2)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+    }
+}
+
+// Intersections along a ray. Shapes push theirs in any order and `sort`
+// puts them in order of t once the ray's are all in; `hit` and shading rely
+// on that order. Clearing keeps the allocation, so one collection can be
+// reused for every ray a thread traces.
+#[derive(Clone, Default)]
+pub struct Intersections<'a> {
+    xs: Vec<Intersection<'a>>,
+}
+
+impl<'a> Intersections<'a> {
+    pub fn new() -> Intersections<'a> {
+        Intersections { xs: Vec::new() }
+    }
+
+    pub fn push(&mut self, x: Intersection<'a>) {
+        self.xs.push(x);
+    }
+
+    // Each shape's intersections arrive as a short sorted run, which the
+    // stable sort picks up and merges.
+    pub fn sort(&mut self) {
+        self.xs.sort_by(|a, b| a.t.total_cmp(&b.t));
+    }
+
+    pub fn merge(&mut self, other: &Intersections<'a>) {
+        self.xs.extend_from_slice(&other.xs);
+        self.sort();
+    }
+
+    // The visible intersection: the one with the lowest non-negative t.
+    pub fn hit(&self) -> Option<&Intersection<'a>> {
+        self.xs.get(self.xs.partition_point(|x| x.t < 0.0))
+    }
+
+    pub fn clear(&mut self) {
+        self.xs.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.xs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.xs.is_empty()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, Intersection<'a>> {
+        self.xs.iter()
+    }
+}
+
+impl<'a> ops::Index<usize> for Intersections<'a> {
+    type Output = Intersection<'a>;
+
+    fn index(&self, i: usize) -> &Intersection<'a> {
+        &self.xs[i]
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'b Intersections<'a> {
+    type Item = &'b Intersection<'a>;
+    type IntoIter = slice::Iter<'b, Intersection<'a>>;
+
+    fn into_iter(self) -> slice::Iter<'b, Intersection<'a>> {
+        self.xs.iter()
+    }
 }
 
-pub struct Intersection {
-    pub mat: Material,
-    pub t: f64,
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use material::Material;
+    use matrix4::Matrix4;
+    use shapes::plane::Plane;
+    use shapes::sphere::Sphere;
+    use world::World;
+
+    fn ts(xs: &Intersections) -> Vec<Float> {
+        xs.iter().map(|x| x.t).collect()
+    }
+
+    #[test]
+    fn sort_by_t() {
+        let s = Sphere::new();
+        let mut xs = Intersections::new();
+        for &t in [5.0, -3.0, 7.0, 2.0].iter() {
+            xs.push(Intersection::new(t, &s));
+        }
+        assert_eq!(ts(&xs), vec![5.0, -3.0, 7.0, 2.0]);
+        xs.sort();
+        assert_eq!(ts(&xs), vec![-3.0, 2.0, 5.0, 7.0]);
+        assert_eq!(xs[1].t, 2.0);
+        // A degenerate transform can produce NaN, which sorts last.
+        xs.push(Intersection::new(Float::NAN, &s));
+        xs.push(Intersection::new(1.0, &s));
+        xs.sort();
+        assert_eq!(xs[1].t, 1.0);
+        assert!(xs[5].t.is_nan());
+    }
+
+    #[test]
+    fn hit() {
+        let s = Sphere::new();
+        let mut xs = Intersections::new();
+        assert!(xs.hit().is_none());
+        xs.push(Intersection::new(-1.0, &s));
+        xs.push(Intersection::new(-2.0, &s));
+        assert!(xs.hit().is_none());
+        xs.push(Intersection::new(4.0, &s));
+        xs.push(Intersection::new(0.5, &s));
+        xs.sort();
+        assert_eq!(xs.hit().map(|x| x.t), Some(0.5));
+    }
+
+    #[test]
+    fn merge() {
+        let s = Sphere::new();
+        let mut a = Intersections::new();
+        let mut b = Intersections::new();
+        for &t in [1.0, 4.0, 6.0].iter() {
+            a.push(Intersection::new(t, &s));
+        }
+        for &t in [-2.0, 5.0].iter() {
+            b.push(Intersection::new(t, &s));
+        }
+        a.merge(&b);
+        assert_eq!(ts(&a), vec![-2.0, 1.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn clear_keeps_capacity() {
+        let s = Sphere::new();
+        let mut xs = Intersections::new();
+        xs.push(Intersection::new(1.0, &s));
+        let capacity = xs.xs.capacity();
+        xs.clear();
+        assert!(xs.is_empty());
+        assert_eq!(xs.xs.capacity(), capacity);
+    }
+
+    #[test]
+    fn prepare_computations_outside() {
+        let s = Sphere::new();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = s.intersect(&r);
+        let comps = xs[0].prepare_computations(&r, &xs);
+        assert_eq!(comps.t, 4.0);
+        assert!(same_object(comps.object, &s));
+        assert_eq!(comps.point, Tuple::point(0.0, 0.0, -1.0));
+        assert_eq!(comps.eyev, Tuple::vector(0.0, 0.0, -1.0));
+        assert_eq!(comps.normalv, Tuple::vector(0.0, 0.0, -1.0));
+        assert!(!comps.inside);
+    }
+
+    #[test]
+    fn prepare_computations_inside() {
+        let s = Sphere::new();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = s.intersect(&r);
+        let comps = xs.hit().unwrap().prepare_computations(&r, &xs);
+        assert_eq!(comps.point, Tuple::point(0.0, 0.0, 1.0));
+        assert_eq!(comps.eyev, Tuple::vector(0.0, 0.0, -1.0));
+        assert!(comps.inside);
+        assert_eq!(comps.normalv, Tuple::vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn offset_points() {
+        let mut s = Sphere::new();
+        s.set_transform(Matrix4::translation(0.0, 0.0, 1.0));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = s.intersect(&r);
+        let comps = xs[0].prepare_computations(&r, &xs);
+        assert!(comps.over_point.z() < -util::EPSILON / 2.0);
+        assert!(comps.point.z() > comps.over_point.z());
+        assert!(comps.under_point.z() > util::EPSILON / 2.0);
+        assert!(comps.point.z() < comps.under_point.z());
+    }
+
+    #[test]
+    fn reflect_vector() {
+        let p = Plane::new();
+        let k = Float::sqrt(2.0) / 2.0;
+        let r = Ray::new(Tuple::point(0.0, 1.0, -1.0), Tuple::vector(0.0, -k, k));
+        let xs = p.intersect(&r);
+        let comps = xs[0].prepare_computations(&r, &xs);
+        assert_eq!(comps.reflectv, Tuple::vector(0.0, k, k));
+    }
+
+    fn glass_sphere(transform: Matrix4, refractive_index: Float) -> Sphere {
+        let mut s = Sphere::new();
+        s.set_transform(transform);
+        let mut m = Material::new();
+        m.transparency = 1.0;
+        m.refractive_index = refractive_index;
+        s.set_material(m);
+        s
+    }
+
+    #[test]
+    fn refractive_indices() {
+        let mut w = World::new();
+        w.objects.push(Box::new(glass_sphere(Matrix4::scaling(2.0, 2.0, 2.0), 1.5)));
+        w.objects.push(Box::new(glass_sphere(Matrix4::translation(0.0, 0.0, -0.25), 2.0)));
+        w.objects.push(Box::new(glass_sphere(Matrix4::translation(0.0, 0.0, 0.25), 2.5)));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -4.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = w.intersect(&r);
+        let indices: Vec<(Float, Float)> = xs.iter().map(|x| {
+            let comps = x.prepare_computations(&r, &xs);
+            (comps.n1, comps.n2)
+        }).collect();
+        assert_eq!(indices, vec![(1.0, 1.5), (1.5, 2.0), (2.0, 2.5), (2.5, 2.5), (2.5, 1.5), (1.5, 1.0)]);
+    }
+
+    #[test]
+    fn schlick() {
+        let k = Float::sqrt(2.0) / 2.0;
+        let mut w = World::new();
+        w.objects.push(Box::new(glass_sphere(Matrix4::identity(), 1.5)));
+        let reflectance = |r: &Ray, i: usize| {
+            let xs = w.intersect(r);
+            let comps = xs[i].prepare_computations(r, &xs);
+            (comps.schlick(), comps.refracted_direction())
+        };
+        // Total internal reflection.
+        let (r, refracted) = reflectance(&Ray::new(Tuple::point(0.0, 0.0, k), Tuple::vector(0.0, 1.0, 0.0)), 1);
+        assert_eq!(r, 1.0);
+        assert!(refracted.is_none());
+        // Head on, straight through.
+        let (r, refracted) = reflectance(&Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0)), 1);
+        assert!(util::approx_eq(r, 0.04));
+        assert_eq!(refracted, Some(Tuple::vector(0.0, 1.0, 0.0)));
+        // Grazing.
+        let (r, _) = reflectance(&Ray::new(Tuple::point(0.0, 0.99, -2.0), Tuple::vector(0.0, 0.0, 1.0)), 0);
+        assert!((r - 0.48873).abs() < 1e-4, "{}", r);
+    }
+}
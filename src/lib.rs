@@ -1,9 +1,26 @@
+pub mod bounds;
+pub mod camera;
 pub mod canvas;
 pub mod color;
+pub mod environment;
+pub mod hdr;
+pub mod integrator;
 pub mod intersection;
+pub mod light;
 pub mod material;
 pub mod matrix;
+pub mod matrix4;
+pub mod png;
+pub mod point3;
+pub mod quaternion;
 pub mod ray;
+pub mod rng;
+pub mod sampling;
+pub mod scene;
 pub mod shapes;
+pub mod sky;
+pub mod stereo;
 pub mod tuple;
-pub mod util;
\ No newline at end of file
+pub mod util;
+pub mod vector3;
+pub mod world;
\ No newline at end of file
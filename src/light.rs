@@ -0,0 +1,383 @@
+use super::color::Color;
+use super::rng::{orthonormal_basis, Rng};
+use super::tuple::Tuple;
+use super::util::consts;
+use super::util::Float;
+
+// The way from a shaded point to one sample of a light: a unit vector
+// toward it and how far a shadow ray has to travel to get there.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightSample {
+    pub direction: Tuple,
+    pub distance: Float,
+}
+
+impl LightSample {
+    fn toward(point: Tuple, position: Tuple) -> LightSample {
+        let v = position - point;
+        LightSample { direction: v.normalize(), distance: v.magnitude() }
+    }
+}
+
+// How a light dims with distance. `InverseSquare` treats the intensity as
+// what arrives one unit away, so a light of 100 gives 1 at ten units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Attenuation {
+    None,
+    InverseSquare,
+}
+
+impl Attenuation {
+    pub fn from_name(name: &str) -> Option<Attenuation> {
+        match name {
+            "none" => Some(Attenuation::None),
+            "inverse-square" => Some(Attenuation::InverseSquare),
+            _ => None,
+        }
+    }
+
+    pub fn apply(self, intensity: Color, distance: Float) -> Color {
+        match self {
+            Attenuation::None => intensity,
+            Attenuation::InverseSquare => intensity * (1.0 / (distance * distance)),
+        }
+    }
+}
+
+// Lights are shaded by averaging over a set of samples: one for most lights,
+// one per cell for an area light, several across the disc of a wide sun.
+pub trait Light: Send + Sync {
+    fn samples(&self) -> usize;
+    // Sample `i` of the light as seen from `point`, where 0 <= i < samples().
+    fn sample_direction(&self, i: usize, point: Tuple, rng: &mut Rng) -> LightSample;
+    // The light arriving at `point`, ignoring anything in the way.
+    fn intensity_at(&self, point: Tuple) -> Color;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointLight {
+    pub position: Tuple,
+    pub intensity: Color,
+    pub attenuation: Attenuation,
+}
+
+impl PointLight {
+    pub fn new(position: Tuple, intensity: Color) -> PointLight {
+        PointLight { position, intensity, attenuation: Attenuation::None }
+    }
+}
+
+impl Light for PointLight {
+    fn samples(&self) -> usize {
+        1
+    }
+
+    fn sample_direction(&self, _i: usize, point: Tuple, _rng: &mut Rng) -> LightSample {
+        LightSample::toward(point, self.position)
+    }
+
+    fn intensity_at(&self, point: Tuple) -> Color {
+        self.attenuation.apply(self.intensity, (point - self.position).magnitude())
+    }
+}
+
+// A point light restricted to a cone around `direction`. Points within
+// `angle - falloff` of the axis are fully lit; beyond that the light fades
+// smoothly to nothing at `angle`. Both angles are in radians.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpotLight {
+    pub position: Tuple,
+    pub direction: Tuple,
+    pub angle: Float,
+    pub falloff: Float,
+    pub intensity: Color,
+    pub attenuation: Attenuation,
+}
+
+impl SpotLight {
+    pub fn new(position: Tuple, direction: Tuple, angle: Float, falloff: Float, intensity: Color) -> SpotLight {
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            angle,
+            falloff,
+            intensity,
+            attenuation: Attenuation::None,
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn samples(&self) -> usize {
+        1
+    }
+
+    fn sample_direction(&self, _i: usize, point: Tuple, _rng: &mut Rng) -> LightSample {
+        LightSample::toward(point, self.position)
+    }
+
+    fn intensity_at(&self, point: Tuple) -> Color {
+        let v = point - self.position;
+        let intensity = self.attenuation.apply(self.intensity, v.magnitude());
+        let cos = v.normalize().dot(&self.direction);
+        let (cos_outer, cos_inner) = (self.angle.cos(), (self.angle - self.falloff).max(0.0).cos());
+        if cos <= cos_outer {
+            Color::black()
+        } else if cos >= cos_inner {
+            intensity
+        } else {
+            let t = (cos - cos_outer) / (cos_inner - cos_outer);
+            intensity * (t * t * (3.0 - 2.0 * t))
+        }
+    }
+}
+
+// A light infinitely far away shining along `direction`, like the sun.
+// Every shadow ray is parallel and never reaches the light. Given an angular
+// diameter it becomes a disc in the sky instead, and its samples spread
+// across the disc soften shadow edges the way the real sun does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirectionalLight {
+    pub direction: Tuple,
+    pub intensity: Color,
+    pub angular_diameter: Float,
+    pub samples: usize,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Tuple, intensity: Color) -> DirectionalLight {
+        DirectionalLight { direction: direction.normalize(), intensity, angular_diameter: 0.0, samples: 1 }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn samples(&self) -> usize {
+        self.samples
+    }
+
+    fn sample_direction(&self, i: usize, _point: Tuple, rng: &mut Rng) -> LightSample {
+        let toward = -self.direction;
+        if self.angular_diameter <= 0.0 {
+            return LightSample { direction: toward, distance: Float::INFINITY };
+        }
+        // Uniform over the cone, with each sample confined to its own band
+        // of rings so that a few of them still cover the whole disc.
+        let cos_max = (self.angular_diameter / 2.0).cos();
+        let u = (i as Float + rng.next_float()) / self.samples as Float;
+        let cos_theta = 1.0 - u * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * consts::PI * rng.next_float();
+        let (tangent, bitangent) = orthonormal_basis(&toward);
+        let direction = tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + toward * cos_theta;
+        LightSample { direction: direction.normalize(), distance: Float::INFINITY }
+    }
+
+    fn intensity_at(&self, _point: Tuple) -> Color {
+        self.intensity
+    }
+}
+
+// A rectangle from `corner` spanning `full_uvec` and `full_vvec`, divided
+// into usteps x vsteps cells with one sample in each. Without jitter the
+// samples sit at the cell centers, which shows up as banding in penumbrae.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AreaLight {
+    pub corner: Tuple,
+    pub uvec: Tuple,
+    pub usteps: usize,
+    pub vvec: Tuple,
+    pub vsteps: usize,
+    pub intensity: Color,
+    pub jitter: bool,
+    pub attenuation: Attenuation,
+}
+
+impl AreaLight {
+    pub fn new(
+        corner: Tuple,
+        full_uvec: Tuple,
+        usteps: usize,
+        full_vvec: Tuple,
+        vsteps: usize,
+        intensity: Color,
+    ) -> AreaLight {
+        AreaLight {
+            corner,
+            uvec: full_uvec / usteps as Float,
+            usteps,
+            vvec: full_vvec / vsteps as Float,
+            vsteps,
+            intensity,
+            jitter: true,
+            attenuation: Attenuation::None,
+        }
+    }
+
+    pub fn point_on_light(&self, u: Float, v: Float) -> Tuple {
+        self.corner + self.uvec * u + self.vvec * v
+    }
+
+    // The point for sample `i`, inside cell (i % usteps, i / usteps).
+    pub fn sample_position(&self, i: usize, rng: &mut Rng) -> Tuple {
+        let (ju, jv) = if self.jitter { (rng.next_float(), rng.next_float()) } else { (0.5, 0.5) };
+        self.point_on_light((i % self.usteps) as Float + ju, (i / self.usteps) as Float + jv)
+    }
+}
+
+impl Light for AreaLight {
+    fn samples(&self) -> usize {
+        self.usteps * self.vsteps
+    }
+
+    fn sample_direction(&self, i: usize, point: Tuple, rng: &mut Rng) -> LightSample {
+        LightSample::toward(point, self.sample_position(i, rng))
+    }
+
+    // Attenuated by the distance to the middle of the rectangle.
+    fn intensity_at(&self, point: Tuple) -> Color {
+        let center = self.point_on_light(self.usteps as Float / 2.0, self.vsteps as Float / 2.0);
+        self.attenuation.apply(self.intensity, (point - center).magnitude())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::consts;
+
+    #[test]
+    fn create_point_light() {
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let intensity = Color::new(1.0, 1.0, 1.0);
+        let light = PointLight::new(position, intensity);
+        assert_eq!(light.position, position);
+        assert_eq!(light.intensity, intensity);
+        assert_eq!(light.samples(), 1);
+        let sample = light.sample_direction(0, Tuple::point(0.0, 0.0, -4.0), &mut Rng::new(0));
+        assert_eq!(sample, LightSample { direction: Tuple::vector(0.0, 0.0, 1.0), distance: 4.0 });
+    }
+
+    #[test]
+    fn inverse_square_attenuation() {
+        let mut light = PointLight::new(Tuple::point(0.0, 0.0, 0.0), Color::new(100.0, 100.0, 100.0));
+        assert_eq!(light.intensity_at(Tuple::point(0.0, 10.0, 0.0)), Color::new(100.0, 100.0, 100.0));
+        light.attenuation = Attenuation::InverseSquare;
+        assert_eq!(light.intensity_at(Tuple::point(0.0, 10.0, 0.0)), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(light.intensity_at(Tuple::point(0.0, 0.0, -20.0)), Color::new(0.25, 0.25, 0.25));
+
+        let mut spot = SpotLight::new(
+            Tuple::point(0.0, 5.0, 0.0),
+            Tuple::vector(0.0, -1.0, 0.0),
+            0.5,
+            0.0,
+            Color::new(100.0, 100.0, 100.0),
+        );
+        spot.attenuation = Attenuation::InverseSquare;
+        assert_eq!(spot.intensity_at(Tuple::point(0.0, 0.0, 0.0)), Color::new(4.0, 4.0, 4.0));
+
+        let mut area = AreaLight::new(
+            Tuple::point(-1.0, 4.0, -1.0),
+            Tuple::vector(2.0, 0.0, 0.0),
+            2,
+            Tuple::vector(0.0, 0.0, 2.0),
+            2,
+            Color::new(16.0, 16.0, 16.0),
+        );
+        area.attenuation = Attenuation::InverseSquare;
+        assert_eq!(area.intensity_at(Tuple::point(0.0, 0.0, 0.0)), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(Attenuation::from_name("inverse-square"), Some(Attenuation::InverseSquare));
+        assert_eq!(Attenuation::from_name("linear"), None);
+    }
+
+    #[test]
+    fn spot_light_cone() {
+        let light = SpotLight::new(
+            Tuple::point(0.0, 10.0, 0.0),
+            Tuple::vector(0.0, -2.0, 0.0),
+            consts::FRAC_PI_4,
+            consts::FRAC_PI_4 / 3.0,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        assert_eq!(light.direction, Tuple::vector(0.0, -1.0, 0.0));
+        // Straight below and just inside the inner cone: full strength.
+        assert_eq!(light.intensity_at(Tuple::point(0.0, 0.0, 0.0)), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(light.intensity_at(Tuple::point(5.0, 0.0, 0.0)), Color::new(1.0, 1.0, 1.0));
+        // Past the outer cone and behind the light: nothing.
+        assert_eq!(light.intensity_at(Tuple::point(10.5, 0.0, 0.0)), Color::black());
+        assert_eq!(light.intensity_at(Tuple::point(0.0, 20.0, 0.0)), Color::black());
+        // Inside the falloff band the light fades off smoothly.
+        assert_eq!(light.intensity_at(Tuple::point(7.0, 0.0, 0.0)), Color::new(0.79096, 0.79096, 0.79096));
+        let sample = light.sample_direction(0, Tuple::point(0.0, 0.0, 0.0), &mut Rng::new(0));
+        assert_eq!(sample, LightSample { direction: Tuple::vector(0.0, 1.0, 0.0), distance: 10.0 });
+    }
+
+    #[test]
+    fn directional_light() {
+        let light = DirectionalLight::new(Tuple::vector(0.0, -3.0, 0.0), Color::new(1.0, 0.9, 0.8));
+        let mut rng = Rng::new(0);
+        for &point in [Tuple::point(0.0, 0.0, 0.0), Tuple::point(100.0, -5.0, 3.0)].iter() {
+            let sample = light.sample_direction(0, point, &mut rng);
+            assert_eq!(sample.direction, Tuple::vector(0.0, 1.0, 0.0));
+            assert_eq!(sample.distance, Float::INFINITY);
+            assert_eq!(light.intensity_at(point), Color::new(1.0, 0.9, 0.8));
+        }
+    }
+
+    #[test]
+    fn directional_light_disc() {
+        let mut light = DirectionalLight::new(Tuple::vector(0.0, -1.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        light.angular_diameter = 0.2;
+        light.samples = 8;
+        assert_eq!(light.samples(), 8);
+        let mut rng = Rng::new(3);
+        let mut widest: Float = 0.0;
+        for i in 0..8 {
+            let sample = light.sample_direction(i, Tuple::point(0.0, 0.0, 0.0), &mut rng);
+            let angle = sample.direction.dot(&Tuple::vector(0.0, 1.0, 0.0)).min(1.0).acos();
+            assert!(angle <= 0.1 + 1e-4 && (sample.direction.magnitude() - 1.0).abs() < 1e-4);
+            widest = widest.max(angle);
+        }
+        // The last sample lies in the outermost band of the disc.
+        assert!(widest > 0.09);
+    }
+
+    #[test]
+    fn create_area_light() {
+        let light = AreaLight::new(
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(2.0, 0.0, 0.0),
+            4,
+            Tuple::vector(0.0, 0.0, 1.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        assert_eq!(light.uvec, Tuple::vector(0.5, 0.0, 0.0));
+        assert_eq!(light.vvec, Tuple::vector(0.0, 0.0, 0.5));
+        assert_eq!(light.samples(), 8);
+    }
+
+    #[test]
+    fn sample_positions() {
+        let mut light = AreaLight::new(
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(2.0, 0.0, 0.0),
+            4,
+            Tuple::vector(0.0, 0.0, 1.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        light.jitter = false;
+        let mut rng = Rng::new(0);
+        assert_eq!(light.sample_position(0, &mut rng), Tuple::point(0.25, 0.0, 0.25));
+        assert_eq!(light.sample_position(1, &mut rng), Tuple::point(0.75, 0.0, 0.25));
+        assert_eq!(light.sample_position(2, &mut rng), Tuple::point(1.25, 0.0, 0.25));
+        assert_eq!(light.sample_position(4, &mut rng), Tuple::point(0.25, 0.0, 0.75));
+        assert_eq!(light.sample_position(7, &mut rng), Tuple::point(1.75, 0.0, 0.75));
+        light.jitter = true;
+        for i in 0..light.samples() {
+            let p = light.sample_position(i, &mut rng);
+            let (u, v) = ((i % 4) as Float * 0.5, (i / 4) as Float * 0.5);
+            assert!(p.x() >= u && p.x() < u + 0.5 && p.z() >= v && p.z() < v + 0.5);
+        }
+    }
+}
@@ -1,3 +1,313 @@
+extern crate kptracer;
+
+use kptracer::camera::RenderOptions;
+use kptracer::integrator::IntegratorKind;
+use kptracer::sampling::Pattern;
+use kptracer::scene::{BackgroundDescription, Scene};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::thread;
+use std::time::Instant;
+
+const USAGE: &str = "Usage: kptracer [OPTIONS] <SCENE>
+
+Renders a scene file to an image.
+
+Options:
+  -o, --output <PATH>     Image to write (default: the scene name with the format's extension)
+  -f, --format <FORMAT>   ppm or png (default: from the output extension, otherwise ppm)
+      --width <N>         Override the camera's width in pixels
+      --height <N>        Override the camera's height in pixels
+  -j, --threads <N>       Worker threads (default: available cores)
+  -s, --samples <N>       Samples per pixel (default: 1)
+  -p, --pattern <NAME>    Where samples fall within a pixel: regular, jittered, stratified,
+                          halton or sobol (default: regular; regular and jittered round
+                          the sample count down to a square grid)
+  -d, --depth <N>         Maximum reflection depth, or bounces when path tracing (default: 5)
+  -i, --integrator <NAME> whitted, for Phong shading with mirror reflections, or path, for
+                          path tracing with indirect light (default: whitted)
+      --frames <A>..<B>   Render frames A to B of an animated scene, numbering each image.
+                          A run of '#' in the output path is replaced by the zero-padded
+                          frame number; otherwise it goes before the extension
+  -h, --help              Print this message";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ppm,
+    Png,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Format> {
+        match name.to_ascii_lowercase().as_str() {
+            "ppm" => Some(Format::Ppm),
+            "png" => Some(Format::Png),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match *self {
+            Format::Ppm => "ppm",
+            Format::Png => "png",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    scene: String,
+    output: String,
+    format: Format,
+    width: Option<usize>,
+    height: Option<usize>,
+    frames: Option<(usize, usize)>,
+    render: RenderOptions,
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Help,
+    Render(Options),
+}
+
+fn parse_count(flag: &str, value: Option<&String>) -> Result<usize, String> {
+    let value = value.ok_or_else(|| format!("{} needs a value", flag))?;
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("{} expects a positive whole number, got '{}'", flag, value)),
+    }
+}
+
+fn parse_frames(value: Option<&String>) -> Result<(usize, usize), String> {
+    let value = value.ok_or("--frames needs a value")?;
+    let range = match value.find("..") {
+        Some(i) => (value[..i].parse::<usize>(), value[i + 2..].parse::<usize>()),
+        None => (value.parse::<usize>(), value.parse::<usize>()),
+    };
+    match range {
+        (Ok(first), Ok(last)) if first <= last => Ok((first, last)),
+        _ => Err(format!("--frames expects a range like 1..48, got '{}'", value)),
+    }
+}
+
+// `out-###.png` becomes `out-007.png` for frame 7, and `out.png` becomes
+// `out.0007.png`.
+fn frame_path(output: &str, frame: usize) -> String {
+    match output.find('#') {
+        Some(start) => {
+            let width = output[start..].chars().take_while(|&c| c == '#').count();
+            format!("{}{:0width$}{}", &output[..start], frame, &output[start + width..], width = width)
+        }
+        None => {
+            let path = Path::new(output);
+            let numbered = match path.extension() {
+                Some(ext) => path.with_extension(format!("{:04}.{}", frame, ext.to_string_lossy())),
+                None => path.with_extension(format!("{:04}", frame)),
+            };
+            numbered.to_string_lossy().into_owned()
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    let mut scene = None;
+    let mut output = None;
+    let mut format = None;
+    let mut width = None;
+    let mut height = None;
+    let mut frames = None;
+    let mut render = RenderOptions {
+        threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        ..RenderOptions::default()
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-o" | "--output" => output = Some(args.next().ok_or("--output needs a value")?.clone()),
+            "-f" | "--format" => {
+                let name = args.next().ok_or("--format needs a value")?;
+                format = Some(Format::from_name(name).ok_or_else(|| format!("unknown format '{}'", name))?);
+            }
+            "--width" => width = Some(parse_count(arg, args.next())?),
+            "--height" => height = Some(parse_count(arg, args.next())?),
+            "-j" | "--threads" => render.threads = parse_count(arg, args.next())?,
+            "-s" | "--samples" => render.samples = parse_count(arg, args.next())?,
+            "-p" | "--pattern" => {
+                let name = args.next().ok_or("--pattern needs a value")?;
+                render.pattern = Pattern::from_name(name).ok_or_else(|| format!("unknown sampling pattern '{}'", name))?;
+            }
+            "-d" | "--depth" => {
+                let value = args.next().ok_or("--depth needs a value")?;
+                render.depth = value.parse().map_err(|_| format!("--depth expects a whole number, got '{}'", value))?;
+            }
+            "-i" | "--integrator" => {
+                let name = args.next().ok_or("--integrator needs a value")?;
+                render.integrator =
+                    IntegratorKind::from_name(name).ok_or_else(|| format!("unknown integrator '{}'", name))?;
+            }
+            "--frames" => frames = Some(parse_frames(args.next())?),
+            flag if flag.starts_with('-') && flag.len() > 1 => return Err(format!("unknown option '{}'", flag)),
+            path => {
+                if scene.is_some() {
+                    return Err(format!("unexpected argument '{}'", path));
+                }
+                scene = Some(path.to_string());
+            }
+        }
+    }
+    let scene = scene.ok_or("no scene file given")?;
+    let format = format
+        .or_else(|| {
+            let ext = Path::new(output.as_ref()?).extension()?;
+            Format::from_name(ext.to_str()?)
+        })
+        .unwrap_or(Format::Ppm);
+    let output = output.unwrap_or_else(|| {
+        Path::new(&scene).with_extension(format.extension()).to_string_lossy().into_owned()
+    });
+    Ok(Command::Render(Options { scene, output, format, width, height, frames, render }))
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let source = fs::read_to_string(&options.scene).map_err(|e| format!("{}: {}", options.scene, e))?;
+    match options.frames {
+        Some((first, last)) => {
+            for frame in first..=last {
+                eprintln!("Frame {} of {}..{}", frame, first, last);
+                render_frame(options, &source, frame, &frame_path(&options.output, frame))?;
+            }
+            Ok(())
+        }
+        None => render_frame(options, &source, 0, &options.output),
+    }
+}
+
+fn render_frame(options: &Options, source: &str, frame: usize, output: &str) -> Result<(), String> {
+    let mut scene = Scene::parse_frame(source, frame as f64).map_err(|e| format!("{}: {}", options.scene, e))?;
+    if let Some(width) = options.width {
+        scene.camera.width = width;
+    }
+    if let Some(height) = options.height {
+        scene.camera.height = height;
+    }
+    // Images are found next to the scene file.
+    if let Some(BackgroundDescription::Image(ref mut path)) = scene.environment.as_mut().map(|e| &mut e.background) {
+        if let Some(dir) = Path::new(&options.scene).parent() {
+            *path = dir.join(&*path).to_string_lossy().into_owned();
+        }
+    }
+    let camera = scene.camera.to_camera();
+    let world = scene.world()?;
+
+    let start = Instant::now();
+    let progress = |done: usize, total: usize| {
+        eprint!("\rRendering: {:3}% ({}/{} tiles)", done * 100 / total, done, total);
+    };
+    let canvas = match scene.camera.stereo {
+        Some(rig) => rig.render_with_progress(&camera, &world, &options.render, &progress),
+        None => camera.render_with_progress(&world, &options.render, &progress),
+    };
+    eprintln!("\rRendered {}x{} in {:.2}s{:20}", canvas.width(), canvas.height(), start.elapsed().as_secs_f64(), "");
+
+    let bytes = match options.format {
+        Format::Ppm => canvas.to_ppm_string().into_bytes(),
+        Format::Png => canvas.to_png(),
+    };
+    fs::write(output, bytes).map_err(|e| format!("{}: {}", output, e))
+}
+
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match parse_args(&args) {
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Ok(Command::Render(options)) => run(&options),
+        Err(e) => Err(format!("{}\n\n{}", e, USAGE)),
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        parse_args(&args)
+    }
+
+    fn options(args: &[&str]) -> Options {
+        match parse(args) {
+            Ok(Command::Render(options)) => options,
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn defaults() {
+        let o = options(&["scenes/spheres.yml"]);
+        assert_eq!(o.scene, "scenes/spheres.yml");
+        assert_eq!(o.output, "scenes/spheres.ppm");
+        assert_eq!(o.format, Format::Ppm);
+        assert_eq!(o.width, None);
+        assert_eq!(o.frames, None);
+        assert_eq!(o.render.samples, 1);
+        assert_eq!(o.render.depth, 5);
+        assert_eq!(o.render.integrator, IntegratorKind::Whitted);
+        assert!(o.render.threads >= 1);
+    }
+
+    #[test]
+    fn all_options() {
+        let o = options(&[
+            "-o", "out.png", "--width", "640", "--height", "480", "-j", "3", "-s", "16", "-p", "halton", "-d", "0",
+            "-i", "path", "scene.yml",
+        ]);
+        assert_eq!(o.output, "out.png");
+        assert_eq!(o.format, Format::Png);
+        assert_eq!((o.width, o.height), (Some(640), Some(480)));
+        let expected = RenderOptions { samples: 16, pattern: Pattern::Halton, depth: 0, threads: 3, integrator: IntegratorKind::Path };
+        assert_eq!(o.render, expected);
+    }
+
+    #[test]
+    fn explicit_format() {
+        let o = options(&["scene.yml", "--format", "png"]);
+        assert_eq!(o.format, Format::Png);
+        assert_eq!(o.output, "scene.png");
+        let o = options(&["scene.yml", "-f", "PPM", "-o", "image.png"]);
+        assert_eq!(o.format, Format::Ppm);
+    }
+
+    #[test]
+    fn frame_ranges() {
+        assert_eq!(options(&["a.yml", "--frames", "1..48"]).frames, Some((1, 48)));
+        assert_eq!(options(&["a.yml", "--frames", "7"]).frames, Some((7, 7)));
+        assert_eq!(parse(&["a.yml", "--frames", "9..2"]), Err("--frames expects a range like 1..48, got '9..2'".to_string()));
+        assert_eq!(frame_path("out/turntable.png", 7), "out/turntable.0007.png");
+        assert_eq!(frame_path("out/turntable", 12), "out/turntable.0012");
+        assert_eq!(frame_path("shot-##.ppm", 7), "shot-07.ppm");
+        assert_eq!(frame_path("shot-##.ppm", 123), "shot-123.ppm");
+    }
+
+    #[test]
+    fn help_and_errors() {
+        assert_eq!(parse(&["--help", "scene.yml"]), Ok(Command::Help));
+        assert_eq!(parse(&[]), Err("no scene file given".to_string()));
+        assert_eq!(parse(&["a.yml", "b.yml"]), Err("unexpected argument 'b.yml'".to_string()));
+        assert_eq!(parse(&["-x", "a.yml"]), Err("unknown option '-x'".to_string()));
+        assert_eq!(parse(&["a.yml", "--width"]), Err("--width needs a value".to_string()));
+        assert_eq!(parse(&["a.yml", "-j", "0"]), Err("-j expects a positive whole number, got '0'".to_string()));
+        assert_eq!(parse(&["a.yml", "-f", "gif"]), Err("unknown format 'gif'".to_string()));
+        assert_eq!(parse(&["a.yml", "-p", "poisson"]), Err("unknown sampling pattern 'poisson'".to_string()));
+    }
 }
@@ -1,2 +1,219 @@
-#[derive(Clone, Copy)]
-pub struct Material { }
\ No newline at end of file
+use super::color::Color;
+use super::light::Light;
+use super::rng::Rng;
+use super::tuple::Tuple;
+use super::util::consts::PI;
+use super::util::Float;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Material {
+    pub color: Color,
+    pub ambient: Float,
+    pub diffuse: Float,
+    pub specular: Float,
+    pub shininess: Float,
+    pub reflective: Float,
+    pub transparency: Float,
+    pub refractive_index: Float,
+    // Light the surface gives off by itself.
+    pub emissive: Color,
+}
+
+impl Material {
+    pub fn new() -> Material {
+        Material {
+            color: Color::new(1.0, 1.0, 1.0),
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.9,
+            shininess: 200.0,
+            reflective: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            emissive: Color::black(),
+        }
+    }
+
+    // Phong shading for a single light. Diffuse and specular are averaged
+    // over the light's samples and scaled by `visibility`, the fraction of
+    // the light that reaches the point.
+    pub fn lighting(
+        &self,
+        light: &dyn Light,
+        point: Tuple,
+        eyev: Tuple,
+        normalv: Tuple,
+        visibility: Float,
+        rng: &mut Rng,
+    ) -> Color {
+        let intensity = light.intensity_at(point);
+        let effective_color = self.color * intensity;
+        let ambient = effective_color * self.ambient;
+        if visibility <= 0.0 {
+            return ambient;
+        }
+        let mut sum = Color::black();
+        for i in 0..light.samples() {
+            let lightv = light.sample_direction(i, point, rng).direction;
+            let light_dot_normal = lightv.dot(&normalv);
+            if light_dot_normal < 0.0 {
+                continue;
+            }
+            sum = sum + effective_color * self.diffuse * light_dot_normal;
+            let reflect_dot_eye = (-lightv).reflect(&normalv).dot(&eyev);
+            if reflect_dot_eye > 0.0 {
+                sum = sum + intensity * self.specular * reflect_dot_eye.powf(self.shininess);
+            }
+        }
+        ambient + sum * (visibility / light.samples() as Float)
+    }
+
+    // How much of the light arriving along `lightv` leaves towards `eyev`,
+    // per steradian: Lambertian diffuse plus a normalized Phong lobe. Used
+    // where light comes from everywhere rather than a few lights, so that a
+    // surface never reflects more than it receives.
+    pub fn brdf(&self, lightv: Tuple, eyev: Tuple, normalv: Tuple) -> Color {
+        let diffuse = self.color * (self.diffuse / PI);
+        let reflect_dot_eye = (-lightv).reflect(&normalv).dot(&eyev);
+        if reflect_dot_eye <= 0.0 {
+            return diffuse;
+        }
+        let specular = self.specular * (self.shininess + 2.0) / (2.0 * PI) * reflect_dot_eye.powf(self.shininess);
+        diffuse + Color::new(specular, specular, specular)
+    }
+}
+
+impl Default for Material {
+    fn default() -> Material {
+        Material::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use light::{AreaLight, PointLight};
+
+    #[test]
+    fn default_material() {
+        let m = Material::new();
+        assert_eq!(m.color, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(m.ambient, 0.1);
+        assert_eq!(m.diffuse, 0.9);
+        assert_eq!(m.specular, 0.9);
+        assert_eq!(m.shininess, 200.0);
+        assert_eq!(m.reflective, 0.0);
+        assert_eq!(m.transparency, 0.0);
+        assert_eq!(m.refractive_index, 1.0);
+        assert_eq!(m.emissive, Color::black());
+    }
+
+    fn light_at(x: Float, y: Float, z: Float) -> PointLight {
+        PointLight::new(Tuple::point(x, y, z), Color::new(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn lighting_eye_between_light_and_surface() {
+        let m = Material::new();
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let result = m.lighting(&light_at(0.0, 0.0, -10.0), position, eyev, normalv, 1.0, &mut Rng::new(0));
+        assert_eq!(result, Color::new(1.9, 1.9, 1.9));
+    }
+
+    #[test]
+    fn lighting_eye_offset_45_degrees() {
+        let m = Material::new();
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let k = Float::sqrt(2.0) / 2.0;
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let result = m.lighting(&light_at(0.0, 0.0, -10.0), position, Tuple::vector(0.0, k, -k), normalv, 1.0, &mut Rng::new(0));
+        assert_eq!(result, Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn lighting_light_offset_45_degrees() {
+        let m = Material::new();
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let k = Float::sqrt(2.0) / 2.0;
+        let light = light_at(0.0, 10.0, -10.0);
+        let result = m.lighting(&light, position, Tuple::vector(0.0, 0.0, -1.0), normalv, 1.0, &mut Rng::new(0));
+        assert_eq!(result, Color::new(0.7364, 0.7364, 0.7364));
+        let result = m.lighting(&light, position, Tuple::vector(0.0, -k, -k), normalv, 1.0, &mut Rng::new(0));
+        assert_eq!(result, Color::new(1.6364, 1.6364, 1.6364));
+    }
+
+    #[test]
+    fn lighting_light_behind_surface() {
+        let m = Material::new();
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let result = m.lighting(&light_at(0.0, 0.0, 10.0), position, eyev, normalv, 1.0, &mut Rng::new(0));
+        assert_eq!(result, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn lighting_in_shadow() {
+        let m = Material::new();
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let result = m.lighting(&light_at(0.0, 0.0, -10.0), position, eyev, normalv, 0.0, &mut Rng::new(0));
+        assert_eq!(result, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn lighting_scaled_by_visibility() {
+        let m = Material::new();
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let result = m.lighting(&light_at(0.0, 0.0, -10.0), position, eyev, normalv, 0.5, &mut Rng::new(0));
+        assert_eq!(result, Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn brdf() {
+        let m = Material { color: Color::new(1.0, 0.5, 0.0), specular: 0.5, shininess: 10.0, ..Material::new() };
+        let normalv = Tuple::vector(0.0, 1.0, 0.0);
+        let k = Float::sqrt(2.0) / 2.0;
+        let lightv = Tuple::vector(0.0, k, -k);
+        let diffuse = Color::new(0.9, 0.45, 0.0) * (1.0 / PI);
+        assert_eq!(m.brdf(lightv, Tuple::vector(0.0, 0.0, -1.0), normalv), diffuse);
+        // Looking straight down the mirror direction adds the full lobe.
+        let peak = 0.5 * 12.0 / (2.0 * PI);
+        assert_eq!(m.brdf(lightv, Tuple::vector(0.0, k, k), normalv), diffuse + Color::new(peak, peak, peak));
+    }
+
+    #[test]
+    fn lighting_samples_area_light() {
+        let mut light = AreaLight::new(
+            Tuple::point(-0.5, -0.5, -5.0),
+            Tuple::vector(1.0, 0.0, 0.0),
+            2,
+            Tuple::vector(0.0, 1.0, 0.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        light.jitter = false;
+        let mut m = Material::new();
+        m.ambient = 0.1;
+        m.diffuse = 0.9;
+        m.specular = 0.0;
+        let eye = Tuple::point(0.0, 0.0, -5.0);
+        let k = Float::sqrt(2.0) / 2.0;
+        let cases = [
+            (Tuple::point(0.0, 0.0, -1.0), Color::new(0.9965, 0.9965, 0.9965)),
+            (Tuple::point(0.0, k, -k), Color::new(0.62318, 0.62318, 0.62318)),
+        ];
+        for &(point, expected) in cases.iter() {
+            let eyev = (eye - point).normalize();
+            let normalv = Tuple::vector(point.x(), point.y(), point.z());
+            let result = m.lighting(&light, point, eyev, normalv, 1.0, &mut Rng::new(0));
+            assert_eq!(result, expected);
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use super::{ tuple::Tuple, util };
 use std::ops;
+use super::util::Float;
 
 #[derive(Debug, Clone)]
 pub struct Matrix {
     rows: usize,
     cols: usize,
-    values: Vec<f64>,
+    values: Vec<Float>,
 }
 
 impl Matrix {
@@ -17,7 +18,7 @@ impl Matrix {
         }
     }
 
-    pub fn new_with_values(rows: usize, values: Vec<f64>) -> Matrix {
+    pub fn new_with_values(rows: usize, values: Vec<Float>) -> Matrix {
         if values.len() % rows != 0 {
             panic!("{} is not divisible by {}", values.len(), rows);
         }
@@ -42,7 +43,7 @@ impl Matrix {
         Matrix::new_with_values(rows, values)
     }
 
-    pub fn translation(x: f64, y: f64, z: f64) -> Matrix {
+    pub fn translation(x: Float, y: Float, z: Float) -> Matrix {
         let mut m = Matrix::identity(4);
         m.write_value(0, 3, x);
         m.write_value(1, 3, y);
@@ -50,7 +51,7 @@ impl Matrix {
         m
     }
 
-    pub fn scaling(x: f64, y: f64, z: f64) -> Matrix {
+    pub fn scaling(x: Float, y: Float, z: Float) -> Matrix {
         let mut m = Matrix::identity(4);
         m.write_value(0, 0, x);
         m.write_value(1, 1, y);
@@ -58,7 +59,7 @@ impl Matrix {
         m
     }
 
-    pub fn rotation_x(rad: f64) -> Matrix {
+    pub fn rotation_x(rad: Float) -> Matrix {
         let mut m = Matrix::identity(4);
         m.write_value(1, 1, rad.cos());
         m.write_value(1, 2, -(rad.sin()));
@@ -67,7 +68,7 @@ impl Matrix {
         m
     }
 
-    pub fn rotation_y(rad: f64) -> Matrix {
+    pub fn rotation_y(rad: Float) -> Matrix {
         let mut m = Matrix::identity(4);
         m.write_value(0, 0, rad.cos());
         m.write_value(0, 2, rad.sin());
@@ -76,7 +77,7 @@ impl Matrix {
         m
     }
 
-    pub fn rotation_z(rad: f64) -> Matrix {
+    pub fn rotation_z(rad: Float) -> Matrix {
         let mut m = Matrix::identity(4);
         m.write_value(0, 0, rad.cos());
         m.write_value(0, 1, -(rad.sin()));
@@ -85,7 +86,7 @@ impl Matrix {
         m
     }
 
-    pub fn shearing(xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Matrix {
+    pub fn shearing(xy: Float, xz: Float, yx: Float, yz: Float, zx: Float, zy: Float) -> Matrix {
         let mut m = Matrix::identity(4);
         m.write_value(0, 1, xy);
         m.write_value(0, 2, xz);
@@ -96,11 +97,62 @@ impl Matrix {
         m
     }
 
-    pub fn write_value(&mut self, row: usize, col: usize, v: f64) {
+    pub fn rotation(axis: Tuple, rad: Float) -> Matrix {
+        let a = axis.normalize();
+        let (x, y, z) = (a.x(), a.y(), a.z());
+        let (sin, cos) = rad.sin_cos();
+        let t = 1.0 - cos;
+        Matrix::new_with_values(4, vec![
+            t * x * x + cos, t * x * y - sin * z, t * x * z + sin * y, 0.0,
+            t * x * y + sin * z, t * y * y + cos, t * y * z - sin * x, 0.0,
+            t * x * z - sin * y, t * y * z + sin * x, t * z * z + cos, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ])
+    }
+
+    // Chainable transforms, applied in reading order:
+    // `Matrix::identity(4).rotate_x(a).translate(x, y, z)` rotates first.
+    pub fn translate(self, x: Float, y: Float, z: Float) -> Matrix {
+        Matrix::translation(x, y, z) * self
+    }
+
+    pub fn scale(self, x: Float, y: Float, z: Float) -> Matrix {
+        Matrix::scaling(x, y, z) * self
+    }
+
+    pub fn rotate_x(self, rad: Float) -> Matrix {
+        Matrix::rotation_x(rad) * self
+    }
+
+    pub fn rotate_y(self, rad: Float) -> Matrix {
+        Matrix::rotation_y(rad) * self
+    }
+
+    pub fn rotate_z(self, rad: Float) -> Matrix {
+        Matrix::rotation_z(rad) * self
+    }
+
+    pub fn rotate(self, axis: Tuple, rad: Float) -> Matrix {
+        Matrix::rotation(axis, rad) * self
+    }
+
+    pub fn shear(self, xy: Float, xz: Float, yx: Float, yz: Float, zx: Float, zy: Float) -> Matrix {
+        Matrix::shearing(xy, xz, yx, yz, zx, zy) * self
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn write_value(&mut self, row: usize, col: usize, v: Float) {
         self.values[row * self.cols + col] = v;
     }
 
-    pub fn value_at(&self, row: usize, col: usize) -> f64 {
+    pub fn value_at(&self, row: usize, col: usize) -> Float {
         self.values[row * self.cols + col]
     }
 
@@ -114,16 +166,8 @@ impl Matrix {
         Matrix::new_with_values(self.rows, values)
     }
 
-    pub fn determinant(&self) -> f64 {
-        if self.rows == 2 && self.cols == 2 {
-            (self.value_at(0, 0) * self.value_at(1, 1)) - (self.value_at(0, 1) * self.value_at(1, 0))
-        } else {
-            let mut sum = 0.0;
-            for col in 0..self.cols {
-                sum += self.value_at(0, col) * self.cofactor(0, col);
-            }
-            sum
-        }
+    pub fn determinant(&self) -> Float {
+        self.lu().determinant()
     }
 
     pub fn submatrix(&self, rrow: usize, rcol: usize) -> Matrix {
@@ -138,35 +182,119 @@ impl Matrix {
         Matrix::new_with_values(self.rows - 1, values)
     }
 
-    pub fn minor(&self, row: usize, col: usize) -> f64 {
+    pub fn minor(&self, row: usize, col: usize) -> Float {
         self.submatrix(row, col).determinant()
     }
 
-    pub fn cofactor(&self, row: usize, col: usize) -> f64 {
+    pub fn cofactor(&self, row: usize, col: usize) -> Float {
         let factor = if (row + col) % 2 == 0 { 1.0 } else { -1.0 };
         factor * self.minor(row, col)
     }
 
     pub fn invertible(&self) -> bool {
-        self.determinant() != 0.0
+        !self.lu().is_singular()
     }
 
     pub fn inverse(&self) -> Matrix {
-        let mut values = Vec::new();
-        for row in 0..self.rows {
-            for col in 0..self.cols {
-                values.push(self.cofactor(row, col));
+        self.try_inverse().expect("This matrix is not invertible!")
+    }
+
+    pub fn try_inverse(&self) -> Option<Matrix> {
+        self.lu().solve(&Matrix::identity(self.rows))
+    }
+
+    // Solves self * x = b for x, one column of x per column of b.
+    pub fn solve(&self, b: &Matrix) -> Option<Matrix> {
+        self.lu().solve(b)
+    }
+
+    // LU decomposition with partial pivoting.
+    pub fn lu(&self) -> Lu {
+        if self.rows != self.cols {
+            panic!("Only square matrices can be decomposed!");
+        }
+        let n = self.rows;
+        let mut factors = self.clone();
+        let mut pivots: Vec<usize> = (0..n).collect();
+        let mut sign = 1.0;
+        let mut singular = false;
+        // Pivots this small relative to the largest entry are treated as zero.
+        let largest = self.values.iter().fold(0.0, |m: Float, v| m.max(v.abs()));
+        let tolerance = largest * n as Float * Float::EPSILON;
+        for k in 0..n {
+            let p = (k..n)
+                .max_by(|&a, &b| factors.value_at(a, k).abs().total_cmp(&factors.value_at(b, k).abs()))
+                .unwrap();
+            if p != k {
+                for col in 0..n {
+                    factors.values.swap(k * n + col, p * n + col);
+                }
+                pivots.swap(k, p);
+                sign = -sign;
+            }
+            let pivot = factors.value_at(k, k);
+            if pivot.abs() <= tolerance || !pivot.is_finite() {
+                singular = true;
+                continue;
+            }
+            for row in k + 1..n {
+                let factor = factors.value_at(row, k) / pivot;
+                factors.write_value(row, k, factor);
+                for col in k + 1..n {
+                    let v = factors.value_at(row, col) - factor * factors.value_at(k, col);
+                    factors.write_value(row, col, v);
+                }
             }
         }
-        let tc = Matrix::new_with_values(self.rows, values).transpose();
-        let det = self.determinant();
-        let mut i_values = Vec::new();
-        for row in 0..tc.rows {
-            for col in 0..tc.cols {
-                i_values.push(tc.value_at(row, col) / det);
+        Lu { factors, pivots, sign, singular }
+    }
+}
+
+// The row-permuted matrix equals L * U. Both live in `factors`: U on and
+// above the diagonal, L below it with its unit diagonal left implicit.
+#[derive(Debug, Clone)]
+pub struct Lu {
+    factors: Matrix,
+    pivots: Vec<usize>,
+    sign: Float,
+    singular: bool,
+}
+
+impl Lu {
+    pub fn is_singular(&self) -> bool {
+        self.singular
+    }
+
+    pub fn determinant(&self) -> Float {
+        (0..self.factors.rows).fold(self.sign, |det, i| det * self.factors.value_at(i, i))
+    }
+
+    pub fn solve(&self, b: &Matrix) -> Option<Matrix> {
+        let n = self.factors.rows;
+        if b.rows != n {
+            panic!("This system cannot be solved with a {}x{} right-hand side!", b.rows, b.cols);
+        }
+        if self.singular {
+            return None;
+        }
+        let mut x = Matrix::new(n, b.cols);
+        for col in 0..b.cols {
+            for row in 0..n {
+                let mut sum = b.value_at(self.pivots[row], col);
+                for i in 0..row {
+                    sum -= self.factors.value_at(row, i) * x.value_at(i, col);
+                }
+                x.write_value(row, col, sum);
+            }
+            for row in (0..n).rev() {
+                let mut sum = x.value_at(row, col);
+                for i in row + 1..n {
+                    sum -= self.factors.value_at(row, i) * x.value_at(i, col);
+                }
+                x.write_value(row, col, sum / self.factors.value_at(row, row));
             }
         }
-        Matrix::new_with_values(tc.rows, i_values)
+        Some(x)
     }
 }
 
@@ -232,10 +360,10 @@ impl ops::Mul<Tuple> for Matrix {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::f64::consts::PI;
+    use util::consts::PI;
 
-    fn root_2() -> f64 {
-        (2.0 as f64).sqrt()
+    fn root_2() -> Float {
+        Float::sqrt(2.0)
     }
 
     #[test]
@@ -343,18 +471,18 @@ mod tests {
     #[test]
     fn determinant() {
         let m2 = Matrix::new_with_values(2, vec![1.0, 5.0, -3.0, 2.0]);
-        assert_eq!(m2.determinant(), 17.0);
+        assert!(util::approx_eq(m2.determinant(), 17.0));
         let m3 = Matrix::new_with_values(3, vec![1.0, 2.0, 6.0, -5.0, 8.0, -4.0, 2.0, 6.0, 4.0]);
-        assert_eq!(m3.cofactor(0, 0), 56.0);
-        assert_eq!(m3.cofactor(0, 1), 12.0);
-        assert_eq!(m3.cofactor(0, 2), -46.0);
-        assert_eq!(m3.determinant(), -196.0);
+        assert!(util::approx_eq(m3.cofactor(0, 0), 56.0));
+        assert!(util::approx_eq(m3.cofactor(0, 1), 12.0));
+        assert!(util::approx_eq(m3.cofactor(0, 2), -46.0));
+        assert!(util::approx_eq(m3.determinant(), -196.0));
         let m4 = Matrix::new_with_values(4, vec![-2.0, -8.0, 3.0, 5.0, -3.0, 1.0, 7.0, 3.0, 1.0, 2.0, -9.0, 6.0, -6.0, 7.0, 7.0, -9.0]);
-        assert_eq!(m4.cofactor(0, 0), 690.0);
-        assert_eq!(m4.cofactor(0, 1), 447.0);
-        assert_eq!(m4.cofactor(0, 2), 210.0);
-        assert_eq!(m4.cofactor(0, 3), 51.0);
-        assert_eq!(m4.determinant(), -4071.0); 
+        assert!(util::approx_eq(m4.cofactor(0, 0), 690.0));
+        assert!(util::approx_eq(m4.cofactor(0, 1), 447.0));
+        assert!(util::approx_eq(m4.cofactor(0, 2), 210.0));
+        assert!(util::approx_eq(m4.cofactor(0, 3), 51.0));
+        assert!(util::approx_eq(m4.determinant(), -4071.0)); 
     }
 
     #[test]
@@ -370,17 +498,17 @@ mod tests {
     #[test]
     fn minor() {
         let m1 = Matrix::new_with_values(3, vec![3.0, 5.0, 0.0, 2.0, -1.0, -7.0, 6.0, -1.0, 5.0]);
-        assert_eq!(m1.submatrix(1, 0).determinant(), 25.0);
-        assert_eq!(m1.minor(1, 0), 25.0);
+        assert!(util::approx_eq(m1.submatrix(1, 0).determinant(), 25.0));
+        assert!(util::approx_eq(m1.minor(1, 0), 25.0));
     }
 
     #[test]
     fn cofactor() {
         let m1 = Matrix::new_with_values(3, vec![3.0, 5.0, 0.0, 2.0, -1.0, -7.0, 6.0, -1.0, 5.0]);
-        assert_eq!(m1.minor(0, 0), -12.0);
-        assert_eq!(m1.cofactor(0, 0), -12.0);
-        assert_eq!(m1.minor(1, 0), 25.0);
-        assert_eq!(m1.cofactor(1, 0), -25.0);
+        assert!(util::approx_eq(m1.minor(0, 0), -12.0));
+        assert!(util::approx_eq(m1.cofactor(0, 0), -12.0));
+        assert!(util::approx_eq(m1.minor(1, 0), 25.0));
+        assert!(util::approx_eq(m1.cofactor(1, 0), -25.0));
     }
 
     #[test]
@@ -395,10 +523,10 @@ mod tests {
     fn inverse() {
         let m1 = Matrix::new_with_values(4, vec![-5.0, 2.0, 6.0, -8.0, 1.0, -5.0, 1.0, 8.0, 7.0, 7.0, -6.0, -7.0, 1.0, -3.0, 7.0, 4.0]);
         let mi = m1.inverse();
-        assert_eq!(m1.determinant(), 532.0);
-        assert_eq!(m1.cofactor(2, 3), -160.0);
+        assert!(util::approx_eq(m1.determinant(), 532.0));
+        assert!(util::approx_eq(m1.cofactor(2, 3), -160.0));
         assert_eq!(util::approx_eq(mi.value_at(3, 2), -160.0 / 532.0), true);
-        assert_eq!(m1.cofactor(3, 2), 105.0);
+        assert!(util::approx_eq(m1.cofactor(3, 2), 105.0));
         assert_eq!(util::approx_eq(mi.value_at(2, 3), 105.0 / 532.0), true);
         assert_eq!(mi, Matrix::new_with_values(4, vec![0.21805, 0.45113, 0.24060, -0.04511, -0.80827, -1.45677, -0.44361, 0.52068, -0.07895, -0.22368, -0.05263, 0.19737, -0.52256, -0.81391, -0.30075, 0.30639]));
         let mi = Matrix::new_with_values(4, vec![8.0, -5.0, 9.0, 2.0, 7.0, 5.0, 6.0, 1.0, -6.0, 0.0, 9.0, 6.0, -3.0, 0.0, -9.0, -4.0]).inverse();
@@ -412,6 +540,56 @@ mod tests {
         assert_eq!(m2 * m3 * m3i, m2c);
     }
 
+    #[test]
+    fn try_inverse() {
+        let singular = Matrix::new_with_values(4, vec![-4.0, 2.0, -2.0, -3.0, 9.0, 6.0, 2.0, 6.0, 0.0, -5.0, 1.0, -5.0, 0.0, 0.0, 0.0, 0.0]);
+        assert!(singular.try_inverse().is_none());
+        let rank_deficient = Matrix::new_with_values(3, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]);
+        assert!(!rank_deficient.invertible());
+        assert!(rank_deficient.try_inverse().is_none());
+        // A zero on the diagonal needs a row swap, which flips the determinant's sign.
+        let swapped = Matrix::new_with_values(2, vec![0.0, 1.0, 1.0, 0.0]);
+        assert!(util::approx_eq(swapped.determinant(), -1.0));
+        let nan = Matrix::new_with_values(2, vec![Float::NAN, 1.0, 1.0, 0.0]);
+        assert!(nan.try_inverse().is_none());
+        assert_eq!(swapped.try_inverse(), Some(swapped.clone()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn inverse_panics_when_singular() {
+        Matrix::new_with_values(2, vec![1.0, 2.0, 2.0, 4.0]).inverse();
+    }
+
+    #[test]
+    fn solve() {
+        let a = Matrix::new_with_values(3, vec![2.0, 1.0, -1.0, -3.0, -1.0, 2.0, -2.0, 1.0, 2.0]);
+        let b = Matrix::new_with_values(3, vec![8.0, -11.0, -3.0]);
+        assert_eq!(a.solve(&b), Some(Matrix::new_with_values(3, vec![2.0, 3.0, -1.0])));
+        let bs = Matrix::new_with_values(3, vec![8.0, 1.0, -11.0, 0.0, -3.0, 0.0]);
+        let xs = a.solve(&bs).unwrap();
+        assert_eq!(a.clone() * xs, bs);
+        let singular = Matrix::new_with_values(2, vec![1.0, 2.0, 2.0, 4.0]);
+        assert_eq!(singular.solve(&Matrix::new_with_values(2, vec![1.0, 2.0])), None);
+    }
+
+    #[test]
+    fn large_determinant() {
+        // Cofactor expansion of a 12x12 matrix would take hundreds of millions of steps.
+        let n = 12;
+        let mut m = Matrix::identity(n);
+        for i in 0..n {
+            m.write_value(i, i, 2.0);
+            if i + 1 < n {
+                m.write_value(i, i + 1, 1.0);
+                m.write_value(i + 1, i, 1.0);
+            }
+        }
+        // The tridiagonal [1 2 1] matrix has determinant n + 1.
+        assert!(util::approx_eq(m.determinant(), 13.0));
+        assert_eq!(m.clone() * m.inverse(), Matrix::identity(n));
+    }
+
     #[test]
     fn translation_points() {
         let p = Tuple::point(-3.0, 4.0, 5.0);
@@ -586,4 +764,14 @@ mod tests {
         let t = tr * sc * ro; // transfomations must be applied in reverse order
         assert_eq!(t * p, Tuple::point(15.0, 0.0, 7.0));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn fluent_transformations() {
+        let p = Tuple::point(1.0, 0.0, 1.0);
+        let t = Matrix::identity(4).rotate_x(PI / 2.0).scale(5.0, 5.0, 5.0).translate(10.0, 5.0, 7.0);
+        assert_eq!(t * p, Tuple::point(15.0, 0.0, 7.0));
+        let t = Matrix::identity(4).shear(0.0, 0.0, 0.0, 0.0, 0.0, 1.0).rotate_y(PI / 2.0);
+        assert_eq!(t * Tuple::point(2.0, 3.0, 4.0), Tuple::point(7.0, 3.0, -2.0));
+        assert_eq!(Matrix::rotation(Tuple::vector(0.0, 0.0, 3.0), PI / 2.0), Matrix::rotation_z(PI / 2.0));
+    }
+}
@@ -0,0 +1,686 @@
+use super::{ matrix::Matrix, point3::Point3, quaternion::Quaternion, tuple::Tuple, util, vector3::Vector3 };
+use std::convert::TryFrom;
+use std::ops;
+use super::util::Float;
+
+// Fixed-size matrices for transforms. Unlike `Matrix` these live on the stack
+// and are `Copy`, so multiplying them never allocates. `Matrix3` and `Matrix2`
+// exist for the cofactor expansion behind `Matrix4::inverse`.
+
+#[derive(Debug, Clone, Copy)]
+pub struct Matrix4([[Float; 4]; 4]);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Matrix3([[Float; 3]; 3]);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Matrix2([[Float; 2]; 2]);
+
+impl Matrix4 {
+    pub fn new(values: [[Float; 4]; 4]) -> Matrix4 {
+        Matrix4(values)
+    }
+
+    pub fn identity() -> Matrix4 {
+        Matrix4([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn translation(x: Float, y: Float, z: Float) -> Matrix4 {
+        let mut m = Matrix4::identity();
+        m.0[0][3] = x;
+        m.0[1][3] = y;
+        m.0[2][3] = z;
+        m
+    }
+
+    pub fn scaling(x: Float, y: Float, z: Float) -> Matrix4 {
+        let mut m = Matrix4::identity();
+        m.0[0][0] = x;
+        m.0[1][1] = y;
+        m.0[2][2] = z;
+        m
+    }
+
+    pub fn rotation_x(rad: Float) -> Matrix4 {
+        let (sin, cos) = rad.sin_cos();
+        let mut m = Matrix4::identity();
+        m.0[1][1] = cos;
+        m.0[1][2] = -sin;
+        m.0[2][1] = sin;
+        m.0[2][2] = cos;
+        m
+    }
+
+    pub fn rotation_y(rad: Float) -> Matrix4 {
+        let (sin, cos) = rad.sin_cos();
+        let mut m = Matrix4::identity();
+        m.0[0][0] = cos;
+        m.0[0][2] = sin;
+        m.0[2][0] = -sin;
+        m.0[2][2] = cos;
+        m
+    }
+
+    pub fn rotation_z(rad: Float) -> Matrix4 {
+        let (sin, cos) = rad.sin_cos();
+        let mut m = Matrix4::identity();
+        m.0[0][0] = cos;
+        m.0[0][1] = -sin;
+        m.0[1][0] = sin;
+        m.0[1][1] = cos;
+        m
+    }
+
+    pub fn shearing(xy: Float, xz: Float, yx: Float, yz: Float, zx: Float, zy: Float) -> Matrix4 {
+        let mut m = Matrix4::identity();
+        m.0[0][1] = xy;
+        m.0[0][2] = xz;
+        m.0[1][0] = yx;
+        m.0[1][2] = yz;
+        m.0[2][0] = zx;
+        m.0[2][1] = zy;
+        m
+    }
+
+    // Rotation by `rad` about `axis`, following the right-hand rule like the
+    // single-axis rotations above.
+    pub fn rotation(axis: Tuple, rad: Float) -> Matrix4 {
+        let a = axis.normalize();
+        let (x, y, z) = (a.x(), a.y(), a.z());
+        let (sin, cos) = rad.sin_cos();
+        let t = 1.0 - cos;
+        Matrix4([
+            [t * x * x + cos, t * x * y - sin * z, t * x * z + sin * y, 0.0],
+            [t * x * y + sin * z, t * y * y + cos, t * y * z - sin * x, 0.0],
+            [t * x * z - sin * y, t * y * z + sin * x, t * z * z + cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn view_transform(from: Tuple, to: Tuple, up: Tuple) -> Matrix4 {
+        let forward = (to - from).normalize();
+        let left = forward.cross(&up.normalize());
+        let true_up = left.cross(&forward);
+        let orientation = Matrix4([
+            [left.x(), left.y(), left.z(), 0.0],
+            [true_up.x(), true_up.y(), true_up.z(), 0.0],
+            [-forward.x(), -forward.y(), -forward.z(), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        orientation * Matrix4::translation(-from.x(), -from.y(), -from.z())
+    }
+
+    // Chainable versions of the constructors above. Each one applies its
+    // transform after the ones already in the chain, so
+    // `Matrix4::identity().rotate_x(a).translate(x, y, z)` rotates first.
+    pub fn translate(self, x: Float, y: Float, z: Float) -> Matrix4 {
+        Matrix4::translation(x, y, z) * self
+    }
+
+    pub fn scale(self, x: Float, y: Float, z: Float) -> Matrix4 {
+        Matrix4::scaling(x, y, z) * self
+    }
+
+    pub fn rotate_x(self, rad: Float) -> Matrix4 {
+        Matrix4::rotation_x(rad) * self
+    }
+
+    pub fn rotate_y(self, rad: Float) -> Matrix4 {
+        Matrix4::rotation_y(rad) * self
+    }
+
+    pub fn rotate_z(self, rad: Float) -> Matrix4 {
+        Matrix4::rotation_z(rad) * self
+    }
+
+    pub fn rotate(self, axis: Tuple, rad: Float) -> Matrix4 {
+        Matrix4::rotation(axis, rad) * self
+    }
+
+    pub fn shear(self, xy: Float, xz: Float, yx: Float, yz: Float, zx: Float, zy: Float) -> Matrix4 {
+        Matrix4::shearing(xy, xz, yx, yz, zx, zy) * self
+    }
+
+    pub fn value_at(&self, row: usize, col: usize) -> Float {
+        self.0[row][col]
+    }
+
+    pub fn write_value(&mut self, row: usize, col: usize, v: Float) {
+        self.0[row][col] = v;
+    }
+
+    // Element-wise blend from `self` at t = 0 to `other` at t = 1. Fine for
+    // translation and scaling, but blending two rotations this way squashes
+    // whatever they turn, down to nothing for a half turn; `decompose` first
+    // to interpolate those.
+    pub fn lerp(&self, other: &Matrix4, t: Float) -> Matrix4 {
+        let mut m = *self;
+        for row in 0..4 {
+            for col in 0..4 {
+                m.0[row][col] += (other.0[row][col] - self.0[row][col]) * t;
+            }
+        }
+        m
+    }
+
+    pub fn transpose(&self) -> Matrix4 {
+        let mut m = Matrix4([[0.0; 4]; 4]);
+        for row in 0..4 {
+            for col in 0..4 {
+                m.0[col][row] = self.0[row][col];
+            }
+        }
+        m
+    }
+
+    pub fn submatrix(&self, rrow: usize, rcol: usize) -> Matrix3 {
+        let mut m = Matrix3([[0.0; 3]; 3]);
+        for (i, row) in (0..4).filter(|&r| r != rrow).enumerate() {
+            for (j, col) in (0..4).filter(|&c| c != rcol).enumerate() {
+                m.0[i][j] = self.0[row][col];
+            }
+        }
+        m
+    }
+
+    pub fn minor(&self, row: usize, col: usize) -> Float {
+        self.submatrix(row, col).determinant()
+    }
+
+    pub fn cofactor(&self, row: usize, col: usize) -> Float {
+        let factor = if (row + col) % 2 == 0 { 1.0 } else { -1.0 };
+        factor * self.minor(row, col)
+    }
+
+    pub fn determinant(&self) -> Float {
+        (0..4).map(|col| self.0[0][col] * self.cofactor(0, col)).sum()
+    }
+
+    pub fn invertible(&self) -> bool {
+        !self.is_singular(self.determinant())
+    }
+
+    pub fn inverse(&self) -> Matrix4 {
+        self.try_inverse().expect("This matrix is not invertible!")
+    }
+
+    pub fn try_inverse(&self) -> Option<Matrix4> {
+        let det = self.determinant();
+        if self.is_singular(det) {
+            return None;
+        }
+        let mut m = Matrix4([[0.0; 4]; 4]);
+        for row in 0..4 {
+            for col in 0..4 {
+                // transposed on the way in
+                m.0[col][row] = self.cofactor(row, col) / det;
+            }
+        }
+        Some(m)
+    }
+
+    // Splits an affine transform into translation, rotation and scale, by
+    // polar decomposition of its upper 3x3.
+    pub fn decompose(&self) -> Decomposed {
+        let translation = Tuple::vector(self.0[0][3], self.0[1][3], self.0[2][3]);
+        let mut linear = *self;
+        for row in 0..3 {
+            linear.0[row][3] = 0.0;
+        }
+        // Averaging with the inverse transpose converges on the rotation
+        // nearest the matrix.
+        let mut rotation = linear;
+        for _ in 0..100 {
+            let inverse = match rotation.try_inverse() {
+                Some(inverse) => inverse.transpose(),
+                None => break,
+            };
+            let mut next = rotation;
+            let mut change: Float = 0.0;
+            for row in 0..3 {
+                for col in 0..3 {
+                    next.0[row][col] = (rotation.0[row][col] + inverse.0[row][col]) / 2.0;
+                    change = change.max((next.0[row][col] - rotation.0[row][col]).abs());
+                }
+            }
+            rotation = next;
+            if change < util::EPSILON {
+                break;
+            }
+        }
+        // A mirror image isn't a rotation. Flipping every axis makes it one,
+        // and the scale takes the flip instead.
+        if rotation.determinant() < 0.0 {
+            for row in 0..3 {
+                for col in 0..3 {
+                    rotation.0[row][col] = -rotation.0[row][col];
+                }
+            }
+        }
+        Decomposed {
+            translation,
+            rotation: Quaternion::from_matrix(&rotation),
+            scale: rotation.transpose() * linear,
+        }
+    }
+
+    // Determinants this small next to the largest entry are treated as zero,
+    // the same tolerance `Matrix::lu` gives each pivot. An affine transform's
+    // determinant is that of its upper 3x3, so its translation is left out:
+    // a small object far from the origin is still invertible. NaN and
+    // infinite determinants count as singular too.
+    fn is_singular(&self, det: Float) -> bool {
+        let n = if self.0[3] == [0.0, 0.0, 0.0, 1.0] { 3 } else { 4 };
+        let largest = self.0[..n].iter().flat_map(|row| &row[..n]).fold(0.0, |m: Float, v| m.max(v.abs()));
+        !det.is_finite() || det.abs() <= largest.powi(n as i32) * n as Float * Float::EPSILON
+    }
+}
+
+// An affine transform as translation * rotation * scale, where the scale
+// also holds any shear. Moving objects blend these parts separately so they
+// keep their shape as they turn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decomposed {
+    pub translation: Tuple,
+    pub rotation: Quaternion,
+    pub scale: Matrix4,
+}
+
+impl Decomposed {
+    pub fn lerp(&self, other: &Decomposed, t: Float) -> Decomposed {
+        Decomposed {
+            translation: self.translation + (other.translation - self.translation) * t,
+            rotation: self.rotation.slerp(&other.rotation, t),
+            scale: self.scale.lerp(&other.scale, t),
+        }
+    }
+
+    pub fn to_matrix(&self) -> Matrix4 {
+        let t = self.translation;
+        Matrix4::translation(t.x(), t.y(), t.z()) * self.rotation.to_matrix() * self.scale
+    }
+}
+
+impl Matrix3 {
+    pub fn new(values: [[Float; 3]; 3]) -> Matrix3 {
+        Matrix3(values)
+    }
+
+    pub fn value_at(&self, row: usize, col: usize) -> Float {
+        self.0[row][col]
+    }
+
+    pub fn submatrix(&self, rrow: usize, rcol: usize) -> Matrix2 {
+        let mut m = Matrix2([[0.0; 2]; 2]);
+        for (i, row) in (0..3).filter(|&r| r != rrow).enumerate() {
+            for (j, col) in (0..3).filter(|&c| c != rcol).enumerate() {
+                m.0[i][j] = self.0[row][col];
+            }
+        }
+        m
+    }
+
+    pub fn minor(&self, row: usize, col: usize) -> Float {
+        self.submatrix(row, col).determinant()
+    }
+
+    pub fn cofactor(&self, row: usize, col: usize) -> Float {
+        let factor = if (row + col) % 2 == 0 { 1.0 } else { -1.0 };
+        factor * self.minor(row, col)
+    }
+
+    pub fn determinant(&self) -> Float {
+        (0..3).map(|col| self.0[0][col] * self.cofactor(0, col)).sum()
+    }
+}
+
+impl Matrix2 {
+    pub fn new(values: [[Float; 2]; 2]) -> Matrix2 {
+        Matrix2(values)
+    }
+
+    pub fn value_at(&self, row: usize, col: usize) -> Float {
+        self.0[row][col]
+    }
+
+    pub fn determinant(&self) -> Float {
+        (self.0[0][0] * self.0[1][1]) - (self.0[0][1] * self.0[1][0])
+    }
+}
+
+impl Default for Matrix4 {
+    fn default() -> Matrix4 {
+        Matrix4::identity()
+    }
+}
+
+impl PartialEq<Matrix4> for Matrix4 {
+    fn eq(&self, other: &Matrix4) -> bool {
+        self.0.iter().flatten().zip(other.0.iter().flatten()).all(|(&a, &b)| util::approx_eq(a, b))
+    }
+}
+
+impl PartialEq<Matrix3> for Matrix3 {
+    fn eq(&self, other: &Matrix3) -> bool {
+        self.0.iter().flatten().zip(other.0.iter().flatten()).all(|(&a, &b)| util::approx_eq(a, b))
+    }
+}
+
+impl PartialEq<Matrix2> for Matrix2 {
+    fn eq(&self, other: &Matrix2) -> bool {
+        self.0.iter().flatten().zip(other.0.iter().flatten()).all(|(&a, &b)| util::approx_eq(a, b))
+    }
+}
+
+impl<'b> ops::Mul<&'b Matrix4> for &Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, other: &'b Matrix4) -> Matrix4 {
+        let mut m = Matrix4([[0.0; 4]; 4]);
+        for row in 0..4 {
+            for col in 0..4 {
+                m.0[row][col] = (0..4).map(|i| self.0[row][i] * other.0[i][col]).sum();
+            }
+        }
+        m
+    }
+}
+
+impl ops::Mul<Matrix4> for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, other: Matrix4) -> Matrix4 {
+        &self * &other
+    }
+}
+
+impl ops::Mul<Tuple> for &Matrix4 {
+    type Output = Tuple;
+
+    fn mul(self, t: Tuple) -> Tuple {
+        let row = |r: usize| {
+            self.0[r][0] * t.x() + self.0[r][1] * t.y() + self.0[r][2] * t.z() + self.0[r][3] * t.w()
+        };
+        Tuple::new(row(0), row(1), row(2), row(3))
+    }
+}
+
+impl ops::Mul<Tuple> for Matrix4 {
+    type Output = Tuple;
+
+    fn mul(self, t: Tuple) -> Tuple {
+        &self * t
+    }
+}
+
+// Transforms are affine, so the bottom row is ignored: points always pick
+// up the translation column and vectors never do.
+impl ops::Mul<Point3> for &Matrix4 {
+    type Output = Point3;
+
+    fn mul(self, p: Point3) -> Point3 {
+        let row = |r: usize| self.0[r][0] * p.x() + self.0[r][1] * p.y() + self.0[r][2] * p.z() + self.0[r][3];
+        Point3::new(row(0), row(1), row(2))
+    }
+}
+
+impl ops::Mul<Point3> for Matrix4 {
+    type Output = Point3;
+
+    fn mul(self, p: Point3) -> Point3 {
+        &self * p
+    }
+}
+
+impl ops::Mul<Vector3> for &Matrix4 {
+    type Output = Vector3;
+
+    fn mul(self, v: Vector3) -> Vector3 {
+        let row = |r: usize| self.0[r][0] * v.x() + self.0[r][1] * v.y() + self.0[r][2] * v.z();
+        Vector3::new(row(0), row(1), row(2))
+    }
+}
+
+impl ops::Mul<Vector3> for Matrix4 {
+    type Output = Vector3;
+
+    fn mul(self, v: Vector3) -> Vector3 {
+        &self * v
+    }
+}
+
+impl From<Matrix4> for Matrix {
+    fn from(m: Matrix4) -> Matrix {
+        Matrix::new_with_values(4, m.0.iter().flatten().cloned().collect())
+    }
+}
+
+impl<'a> TryFrom<&'a Matrix> for Matrix4 {
+    type Error = String;
+
+    fn try_from(m: &'a Matrix) -> Result<Matrix4, String> {
+        if m.rows() != 4 || m.cols() != 4 {
+            return Err(format!("a {}x{} matrix is not 4x4", m.rows(), m.cols()));
+        }
+        let mut values = [[0.0; 4]; 4];
+        for (row, values) in values.iter_mut().enumerate() {
+            for (col, value) in values.iter_mut().enumerate() {
+                *value = m.value_at(row, col);
+            }
+        }
+        Ok(Matrix4(values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::consts::PI;
+
+    #[test]
+    fn create_matrices() {
+        let m = Matrix4::new([[1.0, 2.0, 3.0, 4.0], [5.5, 6.5, 7.5, 8.5], [9.0, 10.0, 11.0, 12.0], [13.5, 14.5, 15.5, 16.5]]);
+        assert_eq!(m.value_at(0, 3), 4.0);
+        assert_eq!(m.value_at(1, 2), 7.5);
+        assert_eq!(m.value_at(3, 0), 13.5);
+        let m = Matrix3::new([[-3.0, 5.0, 0.0], [1.0, -2.0, -7.0], [0.0, 1.0, 1.0]]);
+        assert_eq!(m.value_at(1, 2), -7.0);
+        let m = Matrix2::new([[-3.0, 5.0], [1.0, -2.0]]);
+        assert_eq!(m.value_at(1, 0), 1.0);
+    }
+
+    #[test]
+    fn equality() {
+        let m1 = Matrix4::new([[1.0, 2.0, 3.0, 4.0], [5.0, 6.0, 7.0, 8.0], [9.0, 8.0, 7.0, 6.0], [5.0, 4.0, 3.0, 2.0]]);
+        let m2 = m1;
+        let m3 = Matrix4::new([[2.0, 3.0, 4.0, 5.0], [6.0, 7.0, 8.0, 9.0], [8.0, 7.0, 6.0, 5.0], [4.0, 3.0, 2.0, 1.0]]);
+        assert_eq!(m1, m2);
+        assert_ne!(m1, m3);
+    }
+
+    #[test]
+    fn multiply() {
+        let m1 = Matrix4::new([[1.0, 2.0, 3.0, 4.0], [5.0, 6.0, 7.0, 8.0], [9.0, 8.0, 7.0, 6.0], [5.0, 4.0, 3.0, 2.0]]);
+        let m2 = Matrix4::new([[-2.0, 1.0, 2.0, 3.0], [3.0, 2.0, 1.0, -1.0], [4.0, 3.0, 6.0, 5.0], [1.0, 2.0, 7.0, 8.0]]);
+        let m3 = Matrix4::new([[20.0, 22.0, 50.0, 48.0], [44.0, 54.0, 114.0, 108.0], [40.0, 58.0, 110.0, 102.0], [16.0, 26.0, 46.0, 42.0]]);
+        assert_eq!(ops::Mul::mul(&m1, &m2), m3);
+        assert_eq!(m1 * m2, m3);
+        assert_eq!(m1 * Matrix4::identity(), m1);
+    }
+
+    #[test]
+    fn multiply_tuple() {
+        let m = Matrix4::new([[1.0, 2.0, 3.0, 4.0], [2.0, 4.0, 4.0, 2.0], [8.0, 6.0, 4.0, 1.0], [0.0, 0.0, 0.0, 1.0]]);
+        let t = Tuple::new(1.0, 2.0, 3.0, 1.0);
+        assert_eq!(ops::Mul::mul(&m, t), Tuple::new(18.0, 24.0, 33.0, 1.0));
+        assert_eq!(m * t, Tuple::new(18.0, 24.0, 33.0, 1.0));
+    }
+
+    #[test]
+    fn multiply_points_and_vectors() {
+        let t = Matrix4::identity().scale(2.0, 3.0, 4.0).translate(5.0, -3.0, 2.0);
+        assert_eq!(t * Point3::new(-4.0, 6.0, 8.0), Point3::new(-3.0, 15.0, 34.0));
+        assert_eq!(t * Vector3::new(-4.0, 6.0, 8.0), Vector3::new(-8.0, 18.0, 32.0));
+        let p = Point3::new(1.0, 2.0, 3.0);
+        assert_eq!(Tuple::from(t * p), t * Tuple::from(p));
+    }
+
+    #[test]
+    fn transpose() {
+        let m = Matrix4::new([[0.0, 9.0, 3.0, 0.0], [9.0, 8.0, 0.0, 8.0], [1.0, 8.0, 5.0, 3.0], [0.0, 0.0, 5.0, 8.0]]);
+        let t = Matrix4::new([[0.0, 9.0, 1.0, 0.0], [9.0, 8.0, 8.0, 0.0], [3.0, 0.0, 5.0, 5.0], [0.0, 8.0, 3.0, 8.0]]);
+        assert_eq!(m.transpose(), t);
+        assert_eq!(Matrix4::identity().transpose(), Matrix4::identity());
+    }
+
+    #[test]
+    fn submatrices() {
+        let m = Matrix3::new([[1.0, 5.0, 0.0], [-3.0, 2.0, 7.0], [0.0, 6.0, -3.0]]);
+        assert_eq!(m.submatrix(0, 2), Matrix2::new([[-3.0, 2.0], [0.0, 6.0]]));
+        let m = Matrix4::new([[-6.0, 1.0, 1.0, 6.0], [-8.0, 5.0, 8.0, 6.0], [-1.0, 0.0, 8.0, 2.0], [-7.0, 1.0, -1.0, 1.0]]);
+        assert_eq!(m.submatrix(2, 1), Matrix3::new([[-6.0, 1.0, 6.0], [-8.0, 8.0, 6.0], [-7.0, -1.0, 1.0]]));
+    }
+
+    #[test]
+    fn determinant() {
+        assert_eq!(Matrix2::new([[1.0, 5.0], [-3.0, 2.0]]).determinant(), 17.0);
+        let m3 = Matrix3::new([[1.0, 2.0, 6.0], [-5.0, 8.0, -4.0], [2.0, 6.0, 4.0]]);
+        assert_eq!(m3.minor(0, 0), 56.0);
+        assert_eq!(m3.cofactor(0, 1), 12.0);
+        assert_eq!(m3.determinant(), -196.0);
+        let m4 = Matrix4::new([[-2.0, -8.0, 3.0, 5.0], [-3.0, 1.0, 7.0, 3.0], [1.0, 2.0, -9.0, 6.0], [-6.0, 7.0, 7.0, -9.0]]);
+        assert_eq!(m4.cofactor(0, 0), 690.0);
+        assert_eq!(m4.cofactor(0, 3), 51.0);
+        assert_eq!(m4.determinant(), -4071.0);
+    }
+
+    #[test]
+    fn inverse() {
+        let m = Matrix4::new([[-5.0, 2.0, 6.0, -8.0], [1.0, -5.0, 1.0, 8.0], [7.0, 7.0, -6.0, -7.0], [1.0, -3.0, 7.0, 4.0]]);
+        assert!(m.invertible());
+        assert_eq!(m.inverse(), Matrix4::new([
+            [0.21805, 0.45113, 0.24060, -0.04511],
+            [-0.80827, -1.45677, -0.44361, 0.52068],
+            [-0.07895, -0.22368, -0.05263, 0.19737],
+            [-0.52256, -0.81391, -0.30075, 0.30639],
+        ]));
+        let a = Matrix4::new([[3.0, -9.0, 7.0, 3.0], [3.0, -8.0, 2.0, -9.0], [-4.0, 4.0, 4.0, 1.0], [-6.0, 5.0, -1.0, 1.0]]);
+        let b = Matrix4::new([[8.0, 2.0, 2.0, 2.0], [3.0, -1.0, 7.0, 0.0], [7.0, 0.0, 5.0, 4.0], [6.0, -2.0, 0.0, 5.0]]);
+        assert_eq!(a * b * b.inverse(), a);
+        let singular = Matrix4::new([[-4.0, 2.0, -2.0, -3.0], [9.0, 6.0, 2.0, 6.0], [0.0, -5.0, 1.0, -5.0], [0.0, 0.0, 0.0, 0.0]]);
+        assert!(!singular.invertible());
+        assert_eq!(singular.try_inverse(), None);
+        assert_eq!(Matrix4::scaling(0.0, 1.0, 1.0).try_inverse(), None);
+        assert_eq!(Matrix4::scaling(1e-20, 1.0, 1.0).try_inverse(), None);
+        assert!(Matrix4::scaling(1e-3, 1.0, 1.0).invertible());
+        // Moving a small object far away doesn't make it singular.
+        let far = Matrix4::identity().scale(0.1, 0.1, 0.1).translate(5000.0, 0.0, 0.0);
+        assert_eq!(far.try_inverse().unwrap() * Tuple::point(5000.0, 0.1, 0.0), Tuple::point(0.0, 1.0, 0.0));
+        assert!(Matrix4::identity().scale(0.01, 0.01, 0.01).translate(1000.0, 0.0, 0.0).invertible());
+        let nan = Matrix4::view_transform(Tuple::point(0.0, 0.0, 0.0), Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+        assert!(!nan.invertible());
+        assert_eq!(nan.try_inverse(), None);
+    }
+
+    #[test]
+    fn transformations() {
+        let p = Tuple::point(-3.0, 4.0, 5.0);
+        assert_eq!(Matrix4::translation(5.0, -3.0, 2.0) * p, Tuple::point(2.0, 1.0, 7.0));
+        assert_eq!(Matrix4::translation(5.0, -3.0, 2.0).inverse() * p, Tuple::point(-8.0, 7.0, 3.0));
+        assert_eq!(Matrix4::translation(5.0, -3.0, 2.0) * Tuple::vector(-3.0, 4.0, 5.0), Tuple::vector(-3.0, 4.0, 5.0));
+        assert_eq!(Matrix4::scaling(2.0, 3.0, 4.0) * Tuple::point(-4.0, 6.0, 8.0), Tuple::point(-8.0, 18.0, 32.0));
+        let k = Float::sqrt(2.0) / 2.0;
+        assert_eq!(Matrix4::rotation_x(PI / 4.0) * Tuple::point(0.0, 1.0, 0.0), Tuple::point(0.0, k, k));
+        assert_eq!(Matrix4::rotation_y(PI / 4.0) * Tuple::point(0.0, 0.0, 1.0), Tuple::point(k, 0.0, k));
+        assert_eq!(Matrix4::rotation_z(PI / 4.0) * Tuple::point(0.0, 1.0, 0.0), Tuple::point(-k, k, 0.0));
+        assert_eq!(Matrix4::shearing(0.0, 0.0, 0.0, 0.0, 0.0, 1.0) * Tuple::point(2.0, 3.0, 4.0), Tuple::point(2.0, 3.0, 7.0));
+        let t = Matrix4::translation(10.0, 5.0, 7.0) * Matrix4::scaling(5.0, 5.0, 5.0) * Matrix4::rotation_x(PI / 2.0);
+        assert_eq!(t * Tuple::point(1.0, 0.0, 1.0), Tuple::point(15.0, 0.0, 7.0));
+    }
+
+    #[test]
+    fn fluent_transforms() {
+        let t = Matrix4::identity().rotate_x(PI / 2.0).scale(5.0, 5.0, 5.0).translate(10.0, 5.0, 7.0);
+        assert_eq!(t, Matrix4::translation(10.0, 5.0, 7.0) * Matrix4::scaling(5.0, 5.0, 5.0) * Matrix4::rotation_x(PI / 2.0));
+        assert_eq!(t * Tuple::point(1.0, 0.0, 1.0), Tuple::point(15.0, 0.0, 7.0));
+        let t = Matrix4::identity().shear(1.0, 0.0, 0.0, 0.0, 0.0, 0.0).rotate_z(PI / 2.0);
+        assert_eq!(t * Tuple::point(2.0, 3.0, 4.0), Tuple::point(-3.0, 5.0, 4.0));
+    }
+
+    #[test]
+    fn lerp() {
+        let a = Matrix4::translation(0.0, 0.0, 0.0);
+        let b = Matrix4::scaling(3.0, 1.0, 1.0).translate(4.0, -2.0, 0.0);
+        assert_eq!(a.lerp(&b, 0.0), a);
+        assert_eq!(a.lerp(&b, 1.0), b);
+        assert_eq!(a.lerp(&b, 0.5), Matrix4::scaling(2.0, 1.0, 1.0).translate(2.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn decompose() {
+        let m = Matrix4::identity().scale(2.0, 1.0, 0.5).rotate_y(0.7).translate(1.0, -2.0, 3.0);
+        let d = m.decompose();
+        assert_eq!(d.translation, Tuple::vector(1.0, -2.0, 3.0));
+        assert_eq!(d.rotation.to_matrix(), Matrix4::rotation_y(0.7));
+        assert_eq!(d.scale, Matrix4::scaling(2.0, 1.0, 0.5));
+        assert_eq!(d.to_matrix(), m);
+        let sheared = Matrix4::shearing(1.0, 0.0, 0.0, 0.5, 0.0, 0.0).rotate_z(-1.2).translate(0.0, 4.0, 0.0);
+        assert_eq!(sheared.decompose().to_matrix(), sheared);
+        // The mirroring goes into the scale.
+        let mirror = Matrix4::scaling(-1.0, 1.0, 1.0).decompose();
+        assert_eq!(mirror.rotation.to_matrix(), Matrix4::rotation_x(PI));
+        assert_eq!(mirror.scale, Matrix4::scaling(-1.0, -1.0, -1.0));
+    }
+
+    #[test]
+    fn rotation_about_axis() {
+        let angle = 0.7;
+        assert_eq!(Matrix4::rotation(Tuple::vector(1.0, 0.0, 0.0), angle), Matrix4::rotation_x(angle));
+        assert_eq!(Matrix4::rotation(Tuple::vector(0.0, 2.0, 0.0), angle), Matrix4::rotation_y(angle));
+        assert_eq!(Matrix4::rotation(Tuple::vector(0.0, 0.0, -1.0), angle), Matrix4::rotation_z(-angle));
+        // A third of a turn about the diagonal cycles the axes.
+        let t = Matrix4::identity().rotate(Tuple::vector(1.0, 1.0, 1.0), 2.0 * PI / 3.0);
+        assert_eq!(t * Tuple::point(1.0, 0.0, 0.0), Tuple::point(0.0, 1.0, 0.0));
+        assert_eq!(t * Tuple::vector(0.0, 0.0, 1.0), Tuple::vector(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn matches_general_matrix() {
+        let rotations = [
+            (Matrix4::rotation_x(0.3), Matrix::rotation_x(0.3)),
+            (Matrix4::rotation_y(0.3), Matrix::rotation_y(0.3)),
+            (Matrix4::rotation_z(0.3), Matrix::rotation_z(0.3)),
+        ];
+        for (fixed, general) in rotations.iter() {
+            assert_eq!(Matrix::from(*fixed), *general);
+            assert_eq!(Matrix::from(fixed.inverse()), general.inverse());
+        }
+        let m = Matrix::translation(1.0, 2.0, 3.0);
+        assert_eq!(Matrix4::try_from(&m), Ok(Matrix4::translation(1.0, 2.0, 3.0)));
+        assert!(Matrix4::try_from(&Matrix::identity(3)).is_err());
+    }
+
+    #[test]
+    fn view_transform() {
+        let from = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        assert_eq!(Matrix4::view_transform(from, Tuple::point(0.0, 0.0, -1.0), up), Matrix4::identity());
+        assert_eq!(Matrix4::view_transform(from, Tuple::point(0.0, 0.0, 1.0), up), Matrix4::scaling(-1.0, 1.0, -1.0));
+        let from = Tuple::point(0.0, 0.0, 8.0);
+        assert_eq!(Matrix4::view_transform(from, Tuple::point(0.0, 0.0, 0.0), up), Matrix4::translation(0.0, 0.0, -8.0));
+        let from = Tuple::point(1.0, 3.0, 2.0);
+        let to = Tuple::point(4.0, -2.0, 8.0);
+        let up = Tuple::vector(1.0, 1.0, 0.0);
+        assert_eq!(Matrix4::view_transform(from, to, up), Matrix4::new([
+            [-0.50709, 0.50709, 0.67612, -2.36643],
+            [0.76772, 0.60609, 0.12122, -2.82843],
+            [-0.35857, 0.59761, -0.71714, 0.00000],
+            [0.00000, 0.00000, 0.00000, 1.00000],
+        ]));
+    }
+}
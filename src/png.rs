@@ -0,0 +1,109 @@
+// A minimal PNG encoder for 8-bit RGB images. Image data is stored in
+// uncompressed deflate blocks, which keeps the encoder tiny at the cost of
+// file size.
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+const MAX_STORED_BLOCK: usize = 65535;
+
+pub fn encode(width: usize, height: usize, rgb: &[u8]) -> Vec<u8> {
+    assert_eq!(rgb.len(), width * height * 3, "pixel data does not match the image size");
+    let mut header = Vec::new();
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, truecolor, default compression, filter and interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut scanlines = Vec::with_capacity(height * (width * 3 + 1));
+    for row in rgb.chunks(width * 3) {
+        scanlines.push(0);
+        scanlines.extend_from_slice(row);
+    }
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = if blocks.peek().is_none() { 1 } else { 0 };
+        let len = block.len() as u16;
+        out.push(last);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn encode_small_image() {
+        let png = encode(2, 1, &[255, 0, 0, 0, 0, 255]);
+        assert_eq!(&png[..8], &SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..20], &[0, 0, 0, 2]);
+        assert_eq!(&png[20..24], &[0, 0, 0, 1]);
+        assert_eq!(&png[png.len() - 12..], &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]);
+        // IDAT: zlib header, one final stored block holding both filtered scanline bytes
+        let idat = &png[33..];
+        assert_eq!(&idat[4..8], b"IDAT");
+        assert_eq!(&idat[8..15], &[0x78, 0x01, 1, 7, 0, 0xf8, 0xff]);
+        assert_eq!(&idat[15..22], &[0, 255, 0, 0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn split_large_images_into_blocks() {
+        let stored = zlib_stored(&vec![7; MAX_STORED_BLOCK + 10]);
+        assert_eq!(stored[2], 0);
+        assert_eq!(stored[2 + 5 + MAX_STORED_BLOCK], 1);
+        assert_eq!(stored.len(), 2 + 5 + MAX_STORED_BLOCK + 5 + 10 + 4);
+    }
+}
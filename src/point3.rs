@@ -0,0 +1,104 @@
+use super::tuple::Tuple;
+use super::util;
+use super::vector3::Vector3;
+use std::convert::TryFrom;
+use std::ops;
+use super::util::Float;
+
+// A position in space. Points can be offset by vectors and subtracted from
+// each other, but not added, scaled or crossed.
+#[derive(Debug, Clone, Copy)]
+pub struct Point3(Float, Float, Float);
+
+impl Point3 {
+    pub fn new(x: Float, y: Float, z: Float) -> Point3 {
+        Point3(x, y, z)
+    }
+
+    pub fn origin() -> Point3 {
+        Point3(0.0, 0.0, 0.0)
+    }
+
+    pub fn x(&self) -> Float {
+        self.0
+    }
+
+    pub fn y(&self) -> Float {
+        self.1
+    }
+
+    pub fn z(&self) -> Float {
+        self.2
+    }
+}
+
+impl PartialEq<Point3> for Point3 {
+    fn eq(&self, other: &Point3) -> bool {
+        util::approx_eq(self.0, other.0) && util::approx_eq(self.1, other.1) && util::approx_eq(self.2, other.2)
+    }
+}
+
+impl ops::Add<Vector3> for Point3 {
+    type Output = Point3;
+
+    fn add(self, v: Vector3) -> Point3 {
+        Point3(self.0 + v.x(), self.1 + v.y(), self.2 + v.z())
+    }
+}
+
+impl ops::Sub<Vector3> for Point3 {
+    type Output = Point3;
+
+    fn sub(self, v: Vector3) -> Point3 {
+        Point3(self.0 - v.x(), self.1 - v.y(), self.2 - v.z())
+    }
+}
+
+impl ops::Sub<Point3> for Point3 {
+    type Output = Vector3;
+
+    fn sub(self, other: Point3) -> Vector3 {
+        Vector3::new(self.0 - other.0, self.1 - other.1, self.2 - other.2)
+    }
+}
+
+impl From<Point3> for Tuple {
+    fn from(p: Point3) -> Tuple {
+        Tuple::point(p.0, p.1, p.2)
+    }
+}
+
+impl TryFrom<Tuple> for Point3 {
+    type Error = String;
+
+    fn try_from(t: Tuple) -> Result<Point3, String> {
+        if !util::approx_eq(t.w(), 1.0) {
+            return Err(format!("expected a point, got a tuple with w = {}", t.w()));
+        }
+        Ok(Point3(t.x(), t.y(), t.z()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        let p = Point3::new(3.0, 2.0, 1.0);
+        let q = Point3::new(5.0, 6.0, 7.0);
+        let v = Vector3::new(5.0, 6.0, 7.0);
+        assert_eq!(p - q, Vector3::new(-2.0, -4.0, -6.0));
+        assert_eq!(p - v, Point3::new(-2.0, -4.0, -6.0));
+        assert_eq!(p + v, Point3::new(8.0, 8.0, 8.0));
+        assert_eq!(q + (p - q), p);
+    }
+
+    #[test]
+    fn tuple_conversions() {
+        let p = Point3::new(4.3, -4.2, 3.1);
+        assert_eq!(Tuple::from(p), Tuple::point(4.3, -4.2, 3.1));
+        assert_eq!(Point3::try_from(Tuple::point(4.3, -4.2, 3.1)), Ok(p));
+        assert!(Point3::try_from(Tuple::vector(4.3, -4.2, 3.1)).is_err());
+    }
+}
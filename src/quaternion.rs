@@ -0,0 +1,138 @@
+use super::matrix4::Matrix4;
+use super::util;
+use super::util::Float;
+
+// A unit quaternion w + xi + yj + zk, standing for a rotation. Blending two
+// of them with `slerp` turns at a steady rate about a single axis, which
+// blending rotation matrices doesn't.
+#[derive(Debug, Clone, Copy)]
+pub struct Quaternion {
+    pub w: Float,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+impl Quaternion {
+    pub fn new(w: Float, x: Float, y: Float, z: Float) -> Quaternion {
+        Quaternion { w, x, y, z }
+    }
+
+    pub fn identity() -> Quaternion {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    // The rotation in the upper 3x3 of `m`, which must be a pure rotation.
+    pub fn from_matrix(m: &Matrix4) -> Quaternion {
+        let v = |row, col| m.value_at(row, col);
+        let trace = v(0, 0) + v(1, 1) + v(2, 2);
+        // Divide by the largest component to keep the result accurate.
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Quaternion::new(s / 4.0, (v(2, 1) - v(1, 2)) / s, (v(0, 2) - v(2, 0)) / s, (v(1, 0) - v(0, 1)) / s)
+        } else if v(0, 0) > v(1, 1) && v(0, 0) > v(2, 2) {
+            let s = (1.0 + v(0, 0) - v(1, 1) - v(2, 2)).sqrt() * 2.0;
+            Quaternion::new((v(2, 1) - v(1, 2)) / s, s / 4.0, (v(0, 1) + v(1, 0)) / s, (v(0, 2) + v(2, 0)) / s)
+        } else if v(1, 1) > v(2, 2) {
+            let s = (1.0 + v(1, 1) - v(0, 0) - v(2, 2)).sqrt() * 2.0;
+            Quaternion::new((v(0, 2) - v(2, 0)) / s, (v(0, 1) + v(1, 0)) / s, s / 4.0, (v(1, 2) + v(2, 1)) / s)
+        } else {
+            let s = (1.0 + v(2, 2) - v(0, 0) - v(1, 1)).sqrt() * 2.0;
+            Quaternion::new((v(1, 0) - v(0, 1)) / s, (v(0, 2) + v(2, 0)) / s, (v(1, 2) + v(2, 1)) / s, s / 4.0)
+        };
+        q.normalize()
+    }
+
+    pub fn to_matrix(&self) -> Matrix4 {
+        let &Quaternion { w, x, y, z } = self;
+        Matrix4::new([
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y), 0.0],
+            [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x), 0.0],
+            [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> Float {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn normalize(&self) -> Quaternion {
+        let length = self.dot(self).sqrt();
+        Quaternion::new(self.w / length, self.x / length, self.y / length, self.z / length)
+    }
+
+    // Spherical interpolation from `self` at t = 0 to `other` at t = 1, the
+    // short way round.
+    pub fn slerp(&self, other: &Quaternion, t: Float) -> Quaternion {
+        let mut cos = self.dot(other);
+        // q and -q are the same rotation; pick the one nearer `self`.
+        let other = if cos < 0.0 {
+            cos = -cos;
+            Quaternion::new(-other.w, -other.x, -other.y, -other.z)
+        } else {
+            *other
+        };
+        let (a, b) = if cos > 1.0 - util::EPSILON {
+            // Nearly the same rotation, where a straight blend is as good.
+            (1.0 - t, t)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+        Quaternion::new(
+            self.w * a + other.w * b,
+            self.x * a + other.x * b,
+            self.y * a + other.y * b,
+            self.z * a + other.z * b,
+        )
+        .normalize()
+    }
+}
+
+// Approximate, and q equals -q since both give the same rotation.
+impl PartialEq<Quaternion> for Quaternion {
+    fn eq(&self, other: &Quaternion) -> bool {
+        util::approx_eq(self.dot(other).abs(), 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tuple::Tuple;
+    use util::consts::PI;
+
+    #[test]
+    fn matrix_round_trip() {
+        let rotations = [
+            Matrix4::identity(),
+            Matrix4::rotation_x(0.3),
+            Matrix4::rotation_y(PI),
+            Matrix4::rotation_z(-2.0),
+            Matrix4::rotation(Tuple::vector(1.0, 2.0, -1.0), 2.5),
+        ];
+        for m in rotations.iter() {
+            assert_eq!(Quaternion::from_matrix(m).to_matrix(), *m);
+        }
+        let q = Quaternion::from_matrix(&Matrix4::rotation_y(PI / 3.0));
+        let half = (PI / 6.0).sin_cos();
+        assert_eq!(q, Quaternion::new(half.1, 0.0, half.0, 0.0));
+        assert_eq!(q, Quaternion::new(-half.1, 0.0, -half.0, 0.0));
+    }
+
+    #[test]
+    fn slerp() {
+        let a = Quaternion::identity();
+        let b = Quaternion::from_matrix(&Matrix4::rotation_z(PI / 2.0));
+        assert_eq!(a.slerp(&b, 0.0), a);
+        assert_eq!(a.slerp(&b, 1.0), b);
+        assert_eq!(a.slerp(&b, 0.5).to_matrix(), Matrix4::rotation_z(PI / 4.0));
+        // A half turn has two short ways round; either keeps the object whole.
+        let half_turn = Quaternion::from_matrix(&Matrix4::rotation_y(PI));
+        let m = a.slerp(&half_turn, 0.5).to_matrix();
+        assert_eq!(m * Tuple::vector(0.0, 1.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+        assert!(util::approx_eq((m * Tuple::vector(1.0, 0.0, 0.0)).magnitude(), 1.0));
+    }
+}
@@ -1,19 +1,34 @@
+use super::matrix4::Matrix4;
 use super::tuple::Tuple;
+use super::util::Float;
 
+// `time` says when during the shutter interval the ray was cast, so moving
+// objects can be intersected where they were at that moment.
 #[derive(Clone, Copy)]
 pub struct Ray {
     pub origin: Tuple,
     pub direction: Tuple,
+    pub time: Float,
 }
 
 impl Ray {
     pub fn new(origin: Tuple, direction: Tuple) -> Ray {
-        Ray { origin, direction }
+        Ray { origin, direction, time: 0.0 }
     }
 
-    pub fn position(&self, t: f64) -> Tuple {
+    pub fn with_time(self, time: Float) -> Ray {
+        Ray { time, ..self }
+    }
+
+    pub fn position(&self, t: Float) -> Tuple {
         self.origin + (self.direction * t)
     }
+
+    // The direction isn't renormalized, so t values stay comparable between
+    // the original and transformed rays.
+    pub fn transform(&self, m: &Matrix4) -> Ray {
+        Ray { origin: m * self.origin, direction: m * self.direction, time: self.time }
+    }
 }
 
 #[cfg(test)]
@@ -27,6 +42,8 @@ mod tests {
         let r = Ray::new(p, v);
         assert_eq!(r.origin, p);
         assert_eq!(r.direction, v);
+        assert_eq!(r.time, 0.0);
+        assert_eq!(r.with_time(0.25).time, 0.25);
     }
 
     #[test]
@@ -37,4 +54,22 @@ mod tests {
         assert_eq!(r.position(-1.0), Tuple::point(1.0, 3.0, 4.0));
         assert_eq!(r.position(2.5), Tuple::point(4.5, 3.0, 4.0));
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn translate_ray() {
+        let r = Ray::new(Tuple::point(1.0, 2.0, 3.0), Tuple::vector(0.0, 1.0, 0.0));
+        let r2 = r.transform(&Matrix4::translation(3.0, 4.0, 5.0));
+        assert_eq!(r2.origin, Tuple::point(4.0, 6.0, 8.0));
+        assert_eq!(r2.direction, Tuple::vector(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    pub fn scale_ray() {
+        let r = Ray::new(Tuple::point(1.0, 2.0, 3.0), Tuple::vector(0.0, 1.0, 0.0));
+        let r2 = r.transform(&Matrix4::scaling(2.0, 3.0, 4.0));
+        assert_eq!(r2.origin, Tuple::point(2.0, 6.0, 12.0));
+        assert_eq!(r2.direction, Tuple::vector(0.0, 3.0, 0.0));
+        assert_eq!(r.origin, Tuple::point(1.0, 2.0, 3.0));
+        assert_eq!(r.with_time(0.5).transform(&Matrix4::scaling(2.0, 3.0, 4.0)).time, 0.5);
+    }
+}
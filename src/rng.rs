@@ -0,0 +1,208 @@
+use super::tuple::Tuple;
+use super::util::consts::PI;
+use super::util::Float;
+
+// The largest Float below 1.0, so random values stay in [0, 1) even when
+// rounding to f32.
+const ONE_MINUS_EPSILON: Float = 1.0 - Float::EPSILON / 2.0;
+
+// PCG32 (O'Neill, "PCG: A Family of Simple Fast Space-Efficient Statistically
+// Good Algorithms for Random Number Generation"). Small, fast and fully
+// deterministic: a generator seeded for a given pixel and sample produces the
+// same numbers no matter which thread draws them.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+    inc: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng::with_stream(seed, 0)
+    }
+
+    // Generators with different streams are independent even with the same seed.
+    pub fn with_stream(seed: u64, stream: u64) -> Rng {
+        let mut rng = Rng { state: 0, inc: (stream << 1) | 1 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    // A generator for one sample of one pixel.
+    pub fn for_sample(px: usize, py: usize, sample: usize) -> Rng {
+        Rng::with_stream(mix((px as u64) << 32 | py as u64), sample as u64)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        (self.next_u32() as u64) << 32 | self.next_u32() as u64
+    }
+
+    // Uniform in [0, 1).
+    pub fn next_float(&mut self) -> Float {
+        let x = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        (x as Float).min(ONE_MINUS_EPSILON)
+    }
+
+    // Uniform in 0..n, without modulo bias.
+    pub fn below(&mut self, n: u32) -> u32 {
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let x = self.next_u32();
+            if x >= threshold {
+                return x % n;
+            }
+        }
+    }
+
+    // A point on the unit disk in the xy plane, as a vector from its center.
+    pub fn in_unit_disk(&mut self) -> Tuple {
+        // Shirley and Chiu's concentric mapping keeps strata from the square intact.
+        let u = 2.0 * self.next_float() - 1.0;
+        let v = 2.0 * self.next_float() - 1.0;
+        if u == 0.0 && v == 0.0 {
+            return Tuple::vector(0.0, 0.0, 0.0);
+        }
+        let (r, theta) = if u.abs() > v.abs() {
+            (u, PI / 4.0 * (v / u))
+        } else {
+            (v, PI / 2.0 - PI / 4.0 * (u / v))
+        };
+        Tuple::vector(r * theta.cos(), r * theta.sin(), 0.0)
+    }
+
+    // A uniformly distributed unit vector.
+    pub fn on_unit_sphere(&mut self) -> Tuple {
+        let z = 1.0 - 2.0 * self.next_float();
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * self.next_float();
+        Tuple::vector(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    // A unit vector in the hemisphere around `normal`, with density
+    // proportional to the cosine of its angle to the normal.
+    pub fn cosine_hemisphere(&mut self, normal: &Tuple) -> Tuple {
+        let d = self.in_unit_disk();
+        let z = (1.0 - d.x() * d.x() - d.y() * d.y()).max(0.0).sqrt();
+        let (tangent, bitangent) = orthonormal_basis(normal);
+        (tangent * d.x() + bitangent * d.y() + *normal * z).normalize()
+    }
+
+    // A uniformly distributed point inside the triangle abc.
+    pub fn in_triangle(&mut self, a: Tuple, b: Tuple, c: Tuple) -> Tuple {
+        let r1 = self.next_float().sqrt();
+        let r2 = self.next_float();
+        a + (b - a) * (r1 * (1.0 - r2)) + (c - a) * (r1 * r2)
+    }
+}
+
+// Two unit vectors perpendicular to `n` and to each other (Duff et al.,
+// "Building an Orthonormal Basis, Revisited").
+pub fn orthonormal_basis(n: &Tuple) -> (Tuple, Tuple) {
+    let sign = if n.z() >= 0.0 { 1.0 } else { -1.0 };
+    let a = -1.0 / (sign + n.z());
+    let b = n.x() * n.y() * a;
+    (
+        Tuple::vector(1.0 + sign * n.x() * n.x() * a, sign * b, -sign * n.x()),
+        Tuple::vector(b, sign + n.y() * n.y() * a, -n.y()),
+    )
+}
+
+// SplitMix64's finalizer, used to turn structured seeds like pixel
+// coordinates into well-spread ones.
+pub fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_reference_pcg32() {
+        let mut rng = Rng::with_stream(42, 54);
+        let xs: Vec<u32> = (0..6).map(|_| rng.next_u32()).collect();
+        assert_eq!(xs, vec![0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e]);
+    }
+
+    #[test]
+    fn seeding() {
+        let draw = |mut rng: Rng| (0..4).map(|_| rng.next_u32()).collect::<Vec<_>>();
+        assert_eq!(draw(Rng::for_sample(3, 4, 5)), draw(Rng::for_sample(3, 4, 5)));
+        assert_ne!(draw(Rng::for_sample(3, 4, 5)), draw(Rng::for_sample(3, 4, 6)));
+        assert_ne!(draw(Rng::for_sample(3, 4, 5)), draw(Rng::for_sample(4, 3, 5)));
+        assert_ne!(draw(Rng::new(1)), draw(Rng::new(2)));
+    }
+
+    #[test]
+    fn floats() {
+        let mut rng = Rng::new(7);
+        let n = 10000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let x = rng.next_float();
+            assert!((0.0..1.0).contains(&x));
+            sum += x;
+        }
+        assert!((sum / n as Float - 0.5).abs() < 0.01);
+        assert!((0..1000).all(|_| rng.below(3) < 3));
+    }
+
+    #[test]
+    fn disk_and_sphere() {
+        let mut rng = Rng::new(11);
+        for _ in 0..1000 {
+            let d = rng.in_unit_disk();
+            assert!(d.is_vector() && d.z() == 0.0 && d.magnitude() <= 1.0 + 1e-6);
+            let s = rng.on_unit_sphere();
+            assert!(s.is_vector() && (s.magnitude() - 1.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn cosine_hemisphere() {
+        let mut rng = Rng::new(13);
+        let normal = Tuple::vector(1.0, 2.0, -2.0).normalize();
+        let n = 20000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let v = rng.cosine_hemisphere(&normal);
+            let cos = v.dot(&normal);
+            assert!(cos >= -1e-4 && (v.magnitude() - 1.0).abs() < 1e-4);
+            sum += cos;
+        }
+        // The mean cosine of a cosine-weighted hemisphere is 2/3.
+        assert!((sum / n as Float - 2.0 / 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn basis_is_orthonormal() {
+        for n in [Tuple::vector(0.0, 0.0, 1.0), Tuple::vector(0.0, 0.0, -1.0), Tuple::vector(1.0, -3.0, 2.0).normalize()].iter() {
+            let (t, b) = orthonormal_basis(n);
+            assert!(t.dot(n).abs() < 1e-4 && b.dot(n).abs() < 1e-4 && t.dot(&b).abs() < 1e-4);
+            assert!((t.magnitude() - 1.0).abs() < 1e-4 && (b.magnitude() - 1.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn triangle() {
+        let mut rng = Rng::new(17);
+        let (a, b, c) = (Tuple::point(0.0, 0.0, 0.0), Tuple::point(2.0, 0.0, 0.0), Tuple::point(0.0, 2.0, 0.0));
+        for _ in 0..1000 {
+            let p = rng.in_triangle(a, b, c);
+            assert!(p.is_point());
+            assert!(p.x() >= 0.0 && p.y() >= 0.0 && p.x() + p.y() <= 2.0 + 1e-6 && p.z() == 0.0);
+        }
+    }
+}
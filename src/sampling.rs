@@ -0,0 +1,179 @@
+use super::rng::Rng;
+use super::util::Float;
+
+// Where within a pixel the camera shoots its rays. Every pattern is
+// deterministic: the same pixel always gets the same sample positions, so
+// re-rendering a scene reproduces it bit for bit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pattern {
+    // Centers of a square grid, rounding the sample count down to a square.
+    Regular,
+    // One random point in each cell of a square grid.
+    Jittered,
+    // Latin hypercube: exactly one sample in each row and each column
+    // stratum, for any sample count.
+    Stratified,
+    // Low-discrepancy sequences, offset per pixel so neighbouring pixels
+    // don't share their sample positions.
+    Halton,
+    Sobol,
+}
+
+impl Pattern {
+    pub fn from_name(name: &str) -> Option<Pattern> {
+        match name.to_ascii_lowercase().as_str() {
+            "regular" => Some(Pattern::Regular),
+            "jittered" => Some(Pattern::Jittered),
+            "stratified" => Some(Pattern::Stratified),
+            "halton" => Some(Pattern::Halton),
+            "sobol" => Some(Pattern::Sobol),
+            _ => None,
+        }
+    }
+
+    // Replaces the contents of `out` with sample positions for pixel (px, py).
+    // Positions are in [0, 1) with (0, 0) at the pixel's top left corner.
+    pub fn fill(&self, count: usize, px: usize, py: usize, out: &mut Vec<(Float, Float)>) {
+        out.clear();
+        let count = count.max(1);
+        let mut rng = Rng::for_sample(px, py, 0);
+        match *self {
+            Pattern::Regular | Pattern::Jittered => {
+                let grid = ((count as Float).sqrt() as usize).max(1);
+                for sy in 0..grid {
+                    for sx in 0..grid {
+                        let (ju, jv) = if *self == Pattern::Regular {
+                            (0.5, 0.5)
+                        } else {
+                            (rng.next_float(), rng.next_float())
+                        };
+                        out.push(((sx as Float + ju) / grid as Float, (sy as Float + jv) / grid as Float));
+                    }
+                }
+            }
+            Pattern::Stratified => {
+                let n = count as Float;
+                let mut columns: Vec<usize> = (0..count).collect();
+                for i in (1..count).rev() {
+                    columns.swap(i, rng.below(i as u32 + 1) as usize);
+                }
+                for (row, &column) in columns.iter().enumerate() {
+                    out.push(((column as Float + rng.next_float()) / n, (row as Float + rng.next_float()) / n));
+                }
+            }
+            Pattern::Halton | Pattern::Sobol => {
+                let (du, dv) = (rng.next_float(), rng.next_float());
+                for i in 0..count as u32 {
+                    let (u, v) = if *self == Pattern::Halton {
+                        (radical_inverse(i, 2), radical_inverse(i, 3))
+                    } else {
+                        sobol(i)
+                    };
+                    out.push((wrap(u + du), wrap(v + dv)));
+                }
+            }
+        }
+    }
+}
+
+fn wrap(x: Float) -> Float {
+    let f = x.fract();
+    // Rounding can land exactly on 1.0 in f32.
+    if f >= 1.0 { 0.0 } else { f }
+}
+
+fn radical_inverse(mut i: u32, base: u32) -> Float {
+    let inv = 1.0 / base as f64;
+    let mut factor = inv;
+    let mut result = 0.0;
+    while i > 0 {
+        result += (i % base) as f64 * factor;
+        i /= base;
+        factor *= inv;
+    }
+    result as Float
+}
+
+// The first two dimensions of the Sobol sequence: the base-2 van der Corput
+// sequence and the dimension generated by the polynomial x + 1.
+fn sobol(i: u32) -> (Float, Float) {
+    let mut v = 1u32 << 31;
+    let mut y = 0u32;
+    let mut bits = i;
+    while bits > 0 {
+        if bits & 1 == 1 {
+            y ^= v;
+        }
+        v ^= v >> 1;
+        bits >>= 1;
+    }
+    let scale = 1.0 / (1u64 << 32) as f64;
+    ((i.reverse_bits() as f64 * scale) as Float, (y as f64 * scale) as Float)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Pattern; 5] = [Pattern::Regular, Pattern::Jittered, Pattern::Stratified, Pattern::Halton, Pattern::Sobol];
+
+    fn samples(pattern: Pattern, count: usize, px: usize, py: usize) -> Vec<(Float, Float)> {
+        let mut out = Vec::new();
+        pattern.fill(count, px, py, &mut out);
+        out
+    }
+
+    #[test]
+    fn regular_grid() {
+        assert_eq!(samples(Pattern::Regular, 1, 3, 4), vec![(0.5, 0.5)]);
+        assert_eq!(samples(Pattern::Regular, 5, 3, 4), vec![(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)]);
+    }
+
+    #[test]
+    fn samples_stay_in_pixel_and_are_deterministic() {
+        for &pattern in ALL.iter() {
+            for &(px, py) in [(0, 0), (7, 3), (1920, 1080)].iter() {
+                let a = samples(pattern, 16, px, py);
+                assert_eq!(a.len(), 16);
+                assert!(a.iter().all(|&(u, v)| (0.0..1.0).contains(&u) && (0.0..1.0).contains(&v)));
+                assert_eq!(a, samples(pattern, 16, px, py));
+            }
+        }
+    }
+
+    #[test]
+    fn jittered_fills_every_cell() {
+        let s = samples(Pattern::Jittered, 9, 2, 5);
+        for (i, &(u, v)) in s.iter().enumerate() {
+            assert_eq!(((u * 3.0) as usize, (v * 3.0) as usize), (i % 3, i / 3));
+        }
+        assert_ne!(s, samples(Pattern::Jittered, 9, 2, 6));
+    }
+
+    #[test]
+    fn stratified_covers_each_row_and_column() {
+        let n = 7;
+        let s = samples(Pattern::Stratified, n, 11, 13);
+        let mut columns: Vec<usize> = s.iter().map(|&(u, _)| (u * n as Float) as usize).collect();
+        let rows: Vec<usize> = s.iter().map(|&(_, v)| (v * n as Float) as usize).collect();
+        columns.sort();
+        assert_eq!(columns, (0..n).collect::<Vec<_>>());
+        assert_eq!(rows, (0..n).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn low_discrepancy_sequences() {
+        let halton: Vec<Float> = (0..4).map(|i| radical_inverse(i, 2)).collect();
+        assert_eq!(halton, vec![0.0, 0.5, 0.25, 0.75]);
+        assert!((radical_inverse(4, 3) - 4.0 / 9.0).abs() < 1e-6);
+        let sobol: Vec<(Float, Float)> = (0..4).map(sobol).collect();
+        assert_eq!(sobol, vec![(0.0, 0.0), (0.5, 0.5), (0.25, 0.75), (0.75, 0.25)]);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(Pattern::from_name("Halton"), Some(Pattern::Halton));
+        assert_eq!(Pattern::from_name("stratified"), Some(Pattern::Stratified));
+        assert_eq!(Pattern::from_name("poisson"), None);
+    }
+}
@@ -0,0 +1,215 @@
+use super::parser::{Node, ParseError, Value};
+
+// Any number or list of numbers in a scene can be replaced by keyframes:
+//
+//   from:
+//     keyframes:
+//       - [ 0, [ 0, 1.5, -5 ] ]
+//       - [ 48, [ 5, 1.5, 0 ] ]
+//     interpolation: cubic
+//
+// `resolve` swaps each of those for its value at a given frame, so the rest
+// of the loader never sees them. Before the first keyframe and after the last
+// the value holds still.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Interpolation {
+    Linear,
+    // A Catmull-Rom spline through the keyframes, which eases in and out of
+    // each one without overshooting much.
+    Cubic,
+}
+
+// Returns a copy of `node` with every keyframed value evaluated at `frame`.
+pub fn resolve(node: &Node, frame: f64) -> Result<Node, ParseError> {
+    let value = match node.value {
+        Value::Map(_) if node.get("keyframes").is_some() => return evaluate(node, frame),
+        Value::Map(ref entries) => Value::Map(
+            entries
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), resolve(v, frame)?)))
+                .collect::<Result<_, ParseError>>()?,
+        ),
+        Value::List(ref items) => {
+            Value::List(items.iter().map(|item| resolve(item, frame)).collect::<Result<_, ParseError>>()?)
+        }
+        ref other => other.clone(),
+    };
+    Ok(Node::new(value, node.line, node.col))
+}
+
+fn evaluate(node: &Node, frame: f64) -> Result<Node, ParseError> {
+    for (key, _) in node.as_map().unwrap() {
+        match key.as_str() {
+            Some("keyframes") | Some("interpolation") => {}
+            _ => return Err(key.error("keyframed values only take 'keyframes' and 'interpolation'")),
+        }
+    }
+    let interpolation = match node.get("interpolation") {
+        None => Interpolation::Linear,
+        Some(name) => match name.as_str() {
+            Some("linear") => Interpolation::Linear,
+            Some("cubic") => Interpolation::Cubic,
+            _ => return Err(name.error("interpolation must be 'linear' or 'cubic'")),
+        },
+    };
+    let keys = keyframes(node.get("keyframes").unwrap())?;
+    let i = keys.iter().take_while(|k| k.frame <= frame).count();
+    let values = if i == 0 {
+        keys[0].values.clone()
+    } else if i == keys.len() {
+        keys[i - 1].values.clone()
+    } else {
+        let (a, b) = (&keys[i - 1], &keys[i]);
+        let t = (frame - a.frame) / (b.frame - a.frame);
+        match interpolation {
+            Interpolation::Linear => a.values.iter().zip(&b.values).map(|(p, q)| p + (q - p) * t).collect(),
+            Interpolation::Cubic => {
+                let before = if i >= 2 { &keys[i - 2] } else { a };
+                let after = keys.get(i + 1).unwrap_or(b);
+                let dt = b.frame - a.frame;
+                (0..a.values.len())
+                    .map(|n| {
+                        let m0 = tangent(before, b, n) * dt;
+                        let m1 = tangent(a, after, n) * dt;
+                        hermite(a.values[n], m0, b.values[n], m1, t)
+                    })
+                    .collect()
+            }
+        }
+    };
+    let shape = &keys[0].shape;
+    let value = match *shape {
+        None => Value::Number(values[0]),
+        Some(ref items) => Value::List(
+            values.iter().zip(items).map(|(&v, item)| Node::new(Value::Number(v), item.line, item.col)).collect(),
+        ),
+    };
+    Ok(Node::new(value, node.line, node.col))
+}
+
+struct Keyframe {
+    frame: f64,
+    values: Vec<f64>,
+    // The list nodes of the first keyframe's value, or None for a number.
+    shape: Option<Vec<Node>>,
+}
+
+fn keyframes(node: &Node) -> Result<Vec<Keyframe>, ParseError> {
+    let items = match node.as_list() {
+        Some(items) if !items.is_empty() => items,
+        _ => return Err(node.error("expected a list of [ frame, value ] keyframes")),
+    };
+    let mut keys: Vec<Keyframe> = Vec::with_capacity(items.len());
+    for item in items {
+        let (frame, value) = match item.as_list() {
+            Some(pair) if pair.len() == 2 => match pair[0].as_f64() {
+                Some(frame) => (frame, &pair[1]),
+                None => return Err(pair[0].error("expected a frame number")),
+            },
+            _ => return Err(item.error("expected a [ frame, value ] keyframe")),
+        };
+        let (values, shape) = match value.value {
+            Value::Number(n) => (vec![n], None),
+            Value::List(ref list) => {
+                let values = list.iter().map(|n| n.as_f64()).collect::<Option<Vec<f64>>>();
+                match values {
+                    Some(values) => (values, Some(list.clone())),
+                    None => return Err(value.error("keyframe values must be numbers or lists of numbers")),
+                }
+            }
+            _ => return Err(value.error("keyframe values must be numbers or lists of numbers")),
+        };
+        if let Some(last) = keys.last() {
+            if frame <= last.frame {
+                return Err(item.error("keyframes must be in increasing frame order"));
+            }
+            if values.len() != last.values.len() || shape.is_some() != last.shape.is_some() {
+                return Err(value.error("every keyframe needs the same kind of value"));
+            }
+        }
+        keys.push(Keyframe { frame, values, shape });
+    }
+    Ok(keys)
+}
+
+// The slope of component `n` through the keyframes either side of a key.
+fn tangent(before: &Keyframe, after: &Keyframe, n: usize) -> f64 {
+    (after.values[n] - before.values[n]) / (after.frame - before.frame)
+}
+
+fn hermite(p0: f64, m0: f64, p1: f64, m1: f64, t: f64) -> f64 {
+    let (t2, t3) = (t * t, t * t * t);
+    (2.0 * t3 - 3.0 * t2 + 1.0) * p0 + (t3 - 2.0 * t2 + t) * m0 + (-2.0 * t3 + 3.0 * t2) * p1 + (t3 - t2) * m1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parser;
+
+    fn at(source: &str, frame: f64) -> Result<Node, ParseError> {
+        resolve(&parser::parse(source)?, frame)
+    }
+
+    fn number(node: &Node) -> f64 {
+        node.get("x").unwrap().as_f64().unwrap()
+    }
+
+    #[test]
+    fn linear() {
+        let source = "x:\n  keyframes:\n    - [ 0, 1 ]\n    - [ 10, 3 ]\n    - [ 20, -1 ]\n";
+        let cases = [(-5.0, 1.0), (0.0, 1.0), (5.0, 2.0), (10.0, 3.0), (12.5, 2.0), (20.0, -1.0), (30.0, -1.0)];
+        for &(frame, expected) in cases.iter() {
+            assert_eq!(number(&at(source, frame).unwrap()), expected);
+        }
+    }
+
+    #[test]
+    fn cubic_passes_through_keys_smoothly() {
+        let source = "x:\n  keyframes: [ [ 0, 0 ], [ 10, 10 ], [ 20, 0 ] ]\n  interpolation: cubic\n";
+        assert_eq!(number(&at(source, 0.0).unwrap()), 0.0);
+        assert_eq!(number(&at(source, 10.0).unwrap()), 10.0);
+        // Flat at the peak, so values just either side sit just below it.
+        let (before, after) = (number(&at(source, 9.0).unwrap()), number(&at(source, 11.0).unwrap()));
+        assert!((before - after).abs() < 1e-9 && before < 10.0 && before > 9.5);
+        // Straight lines stay straight.
+        let line = "x:\n  keyframes: [ [ 0, 0 ], [ 10, 10 ], [ 20, 20 ] ]\n  interpolation: cubic\n";
+        assert!((number(&at(line, 13.0).unwrap()) - 13.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn lists_and_nesting() {
+        let source = "
+- add: light
+  at:
+    keyframes:
+      - [ 0, [ 0, 10, -10 ] ]
+      - [ 4, [ 4, 10, 10 ] ]
+  transform:
+    - [ rotate-y, { keyframes: [ [ 0, 0 ], [ 4, 2 ] ] } ]
+";
+        let node = at(source, 1.0).unwrap();
+        let light = &node.as_list().unwrap()[0];
+        let at: Vec<f64> = light.get("at").unwrap().as_list().unwrap().iter().map(|n| n.as_f64().unwrap()).collect();
+        assert_eq!(at, vec![1.0, 10.0, -5.0]);
+        let step = &light.get("transform").unwrap().as_list().unwrap()[0];
+        assert_eq!(step.as_list().unwrap()[1].as_f64(), Some(0.5));
+        assert_eq!(step.as_list().unwrap()[0].as_str(), Some("rotate-y"));
+    }
+
+    #[test]
+    fn errors() {
+        let err = |source: &str| at(source, 0.0).unwrap_err();
+        let e = err("x:\n  keyframes: [ [ 5, 1 ], [ 2, 3 ] ]\n");
+        assert_eq!((e.line, e.col, e.message.as_str()), (2, 26, "keyframes must be in increasing frame order"));
+        let e = err("x:\n  keyframes: [ [ 0, 1 ], [ 2, [ 1, 2 ] ] ]\n");
+        assert_eq!(e.message, "every keyframe needs the same kind of value");
+        let e = err("x:\n  keyframes: [ [ 0, 1 ] ]\n  interpolation: bezier\n");
+        assert_eq!(e.message, "interpolation must be 'linear' or 'cubic'");
+        let e = err("x:\n  keyframes: []\n");
+        assert_eq!(e.message, "expected a list of [ frame, value ] keyframes");
+        let e = err("x:\n  keyframes: [ [ 0, red ] ]\n");
+        assert_eq!(e.message, "keyframe values must be numbers or lists of numbers");
+    }
+}
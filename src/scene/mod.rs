@@ -0,0 +1,1180 @@
+pub mod animation;
+pub mod parser;
+
+pub use self::parser::{Node, ParseError, Value};
+
+use super::camera::{Camera, Projection};
+use super::color::Color;
+use super::environment::{Background, Environment};
+use super::hdr;
+use super::intersection::Intersectable;
+use super::light::{AreaLight, Attenuation, DirectionalLight, Light, PointLight, SpotLight};
+use super::material::Material;
+use super::matrix4::Matrix4;
+use super::shapes::cube::Cube;
+use super::shapes::plane::Plane;
+use super::shapes::sphere::Sphere;
+use super::sky::{self, Sky};
+use super::stereo::{StereoLayout, StereoRig};
+use super::tuple::Tuple;
+use super::world::World;
+use std::collections::HashMap;
+use std::fs;
+use super::util::consts::PI;
+use super::util::{self, Float};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShapeKind {
+    Sphere,
+    Plane,
+    Cube,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraDescription {
+    pub width: usize,
+    pub height: usize,
+    pub field_of_view: Float,
+    pub from: Tuple,
+    pub to: Tuple,
+    pub up: Tuple,
+    pub aperture: Float,
+    pub focal_distance: Float,
+    pub shutter: (Float, Float),
+    pub projection: Projection,
+    pub stereo: Option<StereoRig>,
+}
+
+impl CameraDescription {
+    pub fn to_camera(&self) -> Camera {
+        let mut camera = Camera::new(self.width, self.height, self.field_of_view);
+        camera.set_transform(Matrix4::view_transform(self.from, self.to, self.up));
+        camera.set_lens(self.aperture, self.focal_distance);
+        camera.set_shutter(self.shutter.0, self.shutter.1);
+        camera.set_projection(self.projection);
+        camera
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectDescription {
+    pub kind: ShapeKind,
+    pub transform: Matrix4,
+    // Where the object has moved to by time 1, if it moves at all.
+    pub end_transform: Option<Matrix4>,
+    pub material: Material,
+}
+
+impl ObjectDescription {
+    pub fn to_shape(&self) -> Box<dyn Intersectable> {
+        match self.kind {
+            ShapeKind::Sphere => {
+                let mut s = Sphere::new();
+                match self.end_transform {
+                    Some(end) => s.set_motion(self.transform, end),
+                    None => s.set_transform(self.transform),
+                }
+                s.set_material(self.material);
+                Box::new(s)
+            }
+            ShapeKind::Plane => {
+                let mut p = Plane::new();
+                match self.end_transform {
+                    Some(end) => p.set_motion(self.transform, end),
+                    None => p.set_transform(self.transform),
+                }
+                p.set_material(self.material);
+                Box::new(p)
+            }
+            ShapeKind::Cube => {
+                let mut c = Cube::new();
+                match self.end_transform {
+                    Some(end) => c.set_motion(self.transform, end),
+                    None => c.set_transform(self.transform),
+                }
+                c.set_material(self.material);
+                Box::new(c)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LightDescription {
+    Point(PointLight),
+    Area(AreaLight),
+    Spot(SpotLight),
+    Directional(DirectionalLight),
+}
+
+impl LightDescription {
+    pub fn to_light(&self) -> Box<dyn Light> {
+        match *self {
+            LightDescription::Point(light) => Box::new(light),
+            LightDescription::Area(light) => Box::new(light),
+            LightDescription::Spot(light) => Box::new(light),
+            LightDescription::Directional(light) => Box::new(light),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackgroundDescription {
+    Solid(Color),
+    Gradient { zenith: Color, horizon: Color, ground: Color },
+    Sky(Sky),
+    // The path to an equirectangular Radiance HDR image.
+    Image(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvironmentDescription {
+    pub background: BackgroundDescription,
+    pub intensity: Float,
+    pub samples: usize,
+}
+
+impl EnvironmentDescription {
+    // Fails if the image can't be read.
+    pub fn to_environment(&self) -> Result<Environment, String> {
+        let background = match self.background {
+            BackgroundDescription::Solid(color) => Background::Solid(color),
+            BackgroundDescription::Gradient { zenith, horizon, ground } => Background::Gradient { zenith, horizon, ground },
+            BackgroundDescription::Sky(sky) => Background::Sky(sky),
+            BackgroundDescription::Image(ref path) => {
+                let data = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+                Background::Image(hdr::decode(&data).map_err(|e| format!("{}: {}", path, e))?)
+            }
+        };
+        let mut environment = Environment::new(background);
+        environment.set_intensity(self.intensity);
+        environment.set_samples(self.samples);
+        Ok(environment)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scene {
+    pub camera: CameraDescription,
+    pub lights: Vec<LightDescription>,
+    pub objects: Vec<ObjectDescription>,
+    pub environment: Option<EnvironmentDescription>,
+}
+
+impl Scene {
+    pub fn parse(source: &str) -> Result<Scene, ParseError> {
+        Scene::parse_frame(source, 0.0)
+    }
+
+    // The scene as it stands at `frame`, with keyframed values filled in.
+    pub fn parse_frame(source: &str, frame: f64) -> Result<Scene, ParseError> {
+        let document = animation::resolve(&parser::parse(source)?, frame)?;
+        Loader::new().load(&document)
+    }
+
+    pub fn world(&self) -> Result<World, String> {
+        Ok(World {
+            objects: self.objects.iter().map(|o| o.to_shape()).collect(),
+            lights: self.lights.iter().map(|l| l.to_light()).collect(),
+            environment: match self.environment {
+                Some(ref environment) => Some(environment.to_environment()?),
+                None => None,
+            },
+        })
+    }
+}
+
+struct Loader {
+    defines: HashMap<String, Node>,
+}
+
+impl Loader {
+    fn new() -> Loader {
+        Loader { defines: HashMap::new() }
+    }
+
+    fn load(&mut self, document: &Node) -> Result<Scene, ParseError> {
+        let items = list(document, "the scene")?;
+        let mut camera = None;
+        let mut lights = Vec::new();
+        let mut objects = Vec::new();
+        let mut environment = None;
+        for item in items {
+            if item.as_map().is_none() {
+                return Err(item.error("expected an 'add' or 'define' entry"));
+            }
+            if let Some(kind) = item.get("add") {
+                match string(kind, "add")? {
+                    "camera" => {
+                        if camera.is_some() {
+                            return Err(kind.error("the scene already has a camera"));
+                        }
+                        camera = Some(self.camera(item)?);
+                    }
+                    "environment" => {
+                        if environment.is_some() {
+                            return Err(kind.error("the scene already has an environment"));
+                        }
+                        environment = Some(self.environment(item)?);
+                    }
+                    "sky" => {
+                        if environment.is_some() {
+                            return Err(kind.error("the scene already has an environment"));
+                        }
+                        let (sky, sun) = self.sky(item)?;
+                        environment = Some(sky);
+                        lights.extend(sun);
+                    }
+                    "light" => lights.push(self.light(item)?),
+                    "area-light" => lights.push(self.area_light(item)?),
+                    "spot-light" => lights.push(self.spot_light(item)?),
+                    "directional-light" => lights.push(self.directional_light(item)?),
+                    "sphere" => objects.push(self.object(ShapeKind::Sphere, item)?),
+                    "plane" => objects.push(self.object(ShapeKind::Plane, item)?),
+                    "cube" => objects.push(self.object(ShapeKind::Cube, item)?),
+                    other => return Err(kind.error(format!("unknown item '{}'", other))),
+                }
+            } else if item.get("define").is_some() {
+                self.define(item)?;
+            } else {
+                return Err(item.error("expected an 'add' or 'define' entry"));
+            }
+        }
+        match camera {
+            Some(camera) => Ok(Scene { camera, lights, objects, environment }),
+            None => Err(document.error("the scene has no camera")),
+        }
+    }
+
+    fn define(&mut self, item: &Node) -> Result<(), ParseError> {
+        check_keys(item, &["define", "extend", "value"])?;
+        let name = string(item.get("define").unwrap(), "define")?.to_string();
+        let value = required(item, "value")?;
+        let value = match item.get("extend") {
+            Some(base) => {
+                let base_value = self.lookup(base)?;
+                match (base_value.as_map(), value.as_map()) {
+                    (Some(base_entries), Some(entries)) => {
+                        let mut merged: Vec<(Node, Node)> = base_entries
+                            .iter()
+                            .filter(|(k, _)| value.get(k.as_str().unwrap()).is_none())
+                            .cloned()
+                            .collect();
+                        merged.extend(entries.iter().cloned());
+                        Node::new(Value::Map(merged), value.line, value.col)
+                    }
+                    _ => return Err(base.error("only maps can be extended")),
+                }
+            }
+            None => value.clone(),
+        };
+        self.defines.insert(name, value);
+        Ok(())
+    }
+
+    fn lookup(&self, name: &Node) -> Result<&Node, ParseError> {
+        let s = string(name, "a name")?;
+        self.defines.get(s).ok_or_else(|| name.error(format!("'{}' is not defined", s)))
+    }
+
+    fn camera(&self, item: &Node) -> Result<CameraDescription, ParseError> {
+        check_keys(item, &["add", "width", "height", "field-of-view", "from", "to", "up", "aperture", "focal-distance", "shutter", "projection", "view-width", "stereo"])?;
+        let width = size(required(item, "width")?, "width")?;
+        let height = size(required(item, "height")?, "height")?;
+        let projection = match item.get("projection") {
+            Some(projection) => self.projection(item, projection)?,
+            None => Projection::Perspective,
+        };
+        // A fisheye can see behind itself; a perspective view narrows to
+        // nothing at 0 and blows up at pi, or anywhere within EPSILON of it.
+        let fov_node = required(item, "field-of-view")?;
+        let field_of_view = number(fov_node, "field-of-view")?;
+        if projection == Projection::Fisheye {
+            if !(field_of_view > 0.0 && field_of_view <= 2.0 * PI) {
+                return Err(fov_node.error("field-of-view must be above 0 and at most 2 pi"));
+            }
+        } else if !(field_of_view > 0.0 && field_of_view < PI - util::EPSILON) {
+            return Err(fov_node.error("field-of-view must be between 0 and pi"));
+        }
+        let from = point(required(item, "from")?)?;
+        let to = point(required(item, "to")?)?;
+        if to == from {
+            return Err(required(item, "to")?.error("the camera must look somewhere other than where it is"));
+        }
+        let up = match item.get("up") {
+            Some(up) => vector(up)?,
+            None => Tuple::vector(0.0, 1.0, 0.0),
+        };
+        if (to - from).normalize().cross(&up).magnitude() <= util::EPSILON * up.magnitude() {
+            let node = item.get("up").unwrap_or(item);
+            return Err(node.error("up must not point along the direction the camera looks"));
+        }
+        // Without a focal distance, focus on the point the camera looks at.
+        let focal_distance = match item.get("focal-distance") {
+            Some(node) => match number(node, "focal-distance")? {
+                d if d > 0.0 => d,
+                _ => return Err(node.error("focal-distance must be positive")),
+            },
+            None => (to - from).magnitude(),
+        };
+        Ok(CameraDescription {
+            width,
+            height,
+            field_of_view,
+            from,
+            to,
+            up,
+            aperture: match item.get("aperture") {
+                Some(node) => match number(node, "aperture")? {
+                    a if a >= 0.0 => a,
+                    _ => return Err(node.error("aperture must not be negative")),
+                },
+                None => 0.0,
+            },
+            focal_distance,
+            shutter: match item.get("shutter") {
+                Some(shutter) => shutter_interval(shutter)?,
+                None => (0.0, 0.0),
+            },
+            projection,
+            stereo: match item.get("stereo") {
+                Some(stereo) => Some(self.stereo(stereo, (to - from).magnitude())?),
+                None => None,
+            },
+        })
+    }
+
+    // Eyes converge on the point the camera looks at unless told otherwise.
+    fn stereo(&self, node: &Node, distance: Float) -> Result<StereoRig, ParseError> {
+        if node.as_map().is_none() {
+            return Err(node.error("expected a map for stereo"));
+        }
+        check_keys(node, &["layout", "interaxial", "convergence"])?;
+        let layout = required(node, "layout")?;
+        let name = string(layout, "layout")?;
+        let layout = StereoLayout::from_name(name).ok_or_else(|| layout.error(format!("unknown stereo layout '{}'", name)))?;
+        let convergence = match node.get("convergence") {
+            Some(convergence) => match number(convergence, "convergence")? {
+                d if d > 0.0 => d,
+                _ => return Err(convergence.error("convergence must be positive")),
+            },
+            None => distance,
+        };
+        Ok(StereoRig::new(number(required(node, "interaxial")?, "interaxial")?, convergence, layout))
+    }
+
+    // Orthographic cameras use `view-width` in place of a field of view.
+    fn projection(&self, item: &Node, node: &Node) -> Result<Projection, ParseError> {
+        match string(node, "projection")? {
+            "perspective" => Ok(Projection::Perspective),
+            "orthographic" => {
+                let width = number(required(item, "view-width")?, "view-width")?;
+                if width <= 0.0 {
+                    return Err(item.get("view-width").unwrap().error("view-width must be positive"));
+                }
+                Ok(Projection::Orthographic { width })
+            }
+            "fisheye" => Ok(Projection::Fisheye),
+            "equirectangular" => Ok(Projection::Equirectangular),
+            other => Err(node.error(format!("unknown projection '{}'", other))),
+        }
+    }
+
+    // A solid `color`, a gradient from `zenith` through `horizon` to
+    // `ground`, or an HDR `image`. It lights the scene with `samples` rays
+    // per shading point.
+    fn environment(&self, item: &Node) -> Result<EnvironmentDescription, ParseError> {
+        check_keys(item, &["add", "color", "zenith", "horizon", "ground", "image", "intensity", "samples"])?;
+        let kinds = ["color", "zenith", "image"].iter().filter(|&&key| item.get(key).is_some()).count();
+        if kinds != 1 {
+            return Err(item.error("an environment needs exactly one of 'color', 'zenith' or 'image'"));
+        }
+        let background = if let Some(node) = item.get("color") {
+            BackgroundDescription::Solid(color(node)?)
+        } else if let Some(node) = item.get("zenith") {
+            let horizon = color(required(item, "horizon")?)?;
+            BackgroundDescription::Gradient {
+                zenith: color(node)?,
+                horizon,
+                ground: match item.get("ground") {
+                    Some(ground) => color(ground)?,
+                    None => horizon,
+                },
+            }
+        } else {
+            BackgroundDescription::Image(string(item.get("image").unwrap(), "image")?.to_string())
+        };
+        Ok(EnvironmentDescription {
+            background,
+            intensity: match item.get("intensity") {
+                Some(intensity) => number(intensity, "intensity")?,
+                None => 1.0,
+            },
+            samples: self.environment_samples(item)?,
+        })
+    }
+
+    // A daylight sky as the environment, plus a sun to match it unless
+    // `sun` is false. Angles are in radians.
+    fn sky(&self, item: &Node) -> Result<(EnvironmentDescription, Option<LightDescription>), ParseError> {
+        check_keys(item, &["add", "turbidity", "elevation", "azimuth", "intensity", "samples", "sun", "sun-diameter", "sun-samples"])?;
+        let turbidity = match item.get("turbidity") {
+            Some(node) => match number(node, "turbidity")? {
+                t if (1.7..=10.0).contains(&t) => t,
+                _ => return Err(node.error("turbidity must be between 1.7 and 10")),
+            },
+            None => 3.0,
+        };
+        let node = required(item, "elevation")?;
+        let elevation = number(node, "elevation")?;
+        if !(0.0..=PI / 2.0).contains(&elevation) {
+            return Err(node.error("the sun's elevation must be between 0 and pi/2"));
+        }
+        let azimuth = match item.get("azimuth") {
+            Some(azimuth) => number(azimuth, "azimuth")?,
+            None => 0.0,
+        };
+        let sky = Sky::new(turbidity, elevation, azimuth);
+        let intensity = match item.get("intensity") {
+            Some(intensity) => number(intensity, "intensity")?,
+            None => 1.0,
+        };
+        let environment = EnvironmentDescription {
+            background: BackgroundDescription::Sky(sky),
+            intensity,
+            samples: self.environment_samples(item)?,
+        };
+        let with_sun = match item.get("sun") {
+            Some(sun) => sun.as_bool().ok_or_else(|| sun.error("expected true or false for sun"))?,
+            None => true,
+        };
+        if !with_sun {
+            return Ok((environment, None));
+        }
+        let diameter = match item.get("sun-diameter") {
+            Some(diameter) => number(diameter, "sun-diameter")?,
+            None => sky::SUN_ANGULAR_DIAMETER,
+        };
+        let samples = match item.get("sun-samples") {
+            Some(samples) => size(samples, "sun-samples")?,
+            None => 4,
+        };
+        let mut sun = sky.sun(diameter, samples);
+        sun.intensity = sun.intensity * intensity;
+        Ok((environment, Some(LightDescription::Directional(sun))))
+    }
+
+    fn environment_samples(&self, item: &Node) -> Result<usize, ParseError> {
+        match item.get("samples") {
+            Some(node) => match node.as_f64() {
+                Some(n) if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+                _ => Err(node.error("expected a whole number for samples")),
+            },
+            None => Ok(16),
+        }
+    }
+
+    fn light(&self, item: &Node) -> Result<LightDescription, ParseError> {
+        check_keys(item, &["add", "at", "intensity", "temperature", "attenuation"])?;
+        let mut light = PointLight::new(point(required(item, "at")?)?, intensity(item)?);
+        light.attenuation = attenuation(item)?;
+        Ok(LightDescription::Point(light))
+    }
+
+    // A rectangle from `corner` along `uvec` and `vvec`, sampled once per cell.
+    fn area_light(&self, item: &Node) -> Result<LightDescription, ParseError> {
+        check_keys(item, &["add", "corner", "uvec", "usteps", "vvec", "vsteps", "intensity", "temperature", "attenuation", "jitter"])?;
+        let mut light = AreaLight::new(
+            point(required(item, "corner")?)?,
+            vector(required(item, "uvec")?)?,
+            size(required(item, "usteps")?, "usteps")?,
+            vector(required(item, "vvec")?)?,
+            size(required(item, "vsteps")?, "vsteps")?,
+            intensity(item)?,
+        );
+        light.attenuation = attenuation(item)?;
+        if let Some(jitter) = item.get("jitter") {
+            light.jitter = jitter.as_bool().ok_or_else(|| jitter.error("expected true or false for jitter"))?;
+        }
+        Ok(LightDescription::Area(light))
+    }
+
+    // Angles are in radians; `falloff` defaults to a hard-edged cone.
+    fn spot_light(&self, item: &Node) -> Result<LightDescription, ParseError> {
+        check_keys(item, &["add", "at", "direction", "angle", "falloff", "intensity", "temperature", "attenuation"])?;
+        let falloff = match item.get("falloff") {
+            Some(falloff) => number(falloff, "falloff")?,
+            None => 0.0,
+        };
+        let angle_node = required(item, "angle")?;
+        let angle = number(angle_node, "angle")?;
+        if angle <= 0.0 {
+            return Err(angle_node.error("angle must be positive"));
+        }
+        let mut light = SpotLight::new(
+            point(required(item, "at")?)?,
+            direction(required(item, "direction")?)?,
+            angle,
+            falloff,
+            intensity(item)?,
+        );
+        light.attenuation = attenuation(item)?;
+        Ok(LightDescription::Spot(light))
+    }
+
+    fn directional_light(&self, item: &Node) -> Result<LightDescription, ParseError> {
+        check_keys(item, &["add", "direction", "intensity", "temperature", "angular-diameter", "samples"])?;
+        let mut light = DirectionalLight::new(direction(required(item, "direction")?)?, intensity(item)?);
+        if let Some(diameter) = item.get("angular-diameter") {
+            light.angular_diameter = number(diameter, "angular-diameter")?;
+        }
+        if let Some(samples) = item.get("samples") {
+            light.samples = size(samples, "samples")?;
+        }
+        Ok(LightDescription::Directional(light))
+    }
+
+    fn object(&self, kind: ShapeKind, item: &Node) -> Result<ObjectDescription, ParseError> {
+        check_keys(item, &["add", "material", "transform", "end-transform"])?;
+        let material = match item.get("material") {
+            Some(material) => self.material(material)?,
+            None => Material::new(),
+        };
+        let transform = match item.get("transform") {
+            Some(transform) => self.invertible_transform(transform)?,
+            None => Matrix4::identity(),
+        };
+        let end_transform = match item.get("end-transform") {
+            Some(end) => Some(self.invertible_transform(end)?),
+            None => None,
+        };
+        Ok(ObjectDescription { kind, transform, end_transform, material })
+    }
+
+    fn invertible_transform(&self, node: &Node) -> Result<Matrix4, ParseError> {
+        let m = self.transform(node)?;
+        if !m.invertible() {
+            return Err(node.error("transform is not invertible"));
+        }
+        Ok(m)
+    }
+
+    fn material(&self, node: &Node) -> Result<Material, ParseError> {
+        let node = if node.as_str().is_some() { self.lookup(node)? } else { node };
+        let entries = match node.as_map() {
+            Some(entries) => entries,
+            None => return Err(node.error("expected a material")),
+        };
+        let mut m = Material::new();
+        for (key, value) in entries {
+            match key.as_str().unwrap() {
+                "color" => m.color = color(value)?,
+                "ambient" => m.ambient = number(value, "ambient")?,
+                "diffuse" => m.diffuse = number(value, "diffuse")?,
+                "specular" => m.specular = number(value, "specular")?,
+                "shininess" => m.shininess = number(value, "shininess")?,
+                "reflective" => m.reflective = number(value, "reflective")?,
+                "transparency" => m.transparency = number(value, "transparency")?,
+                "refractive-index" => m.refractive_index = number(value, "refractive-index")?,
+                "emissive" => m.emissive = color(value)?,
+                other => return Err(key.error(format!("unknown material property '{}'", other))),
+            }
+        }
+        Ok(m)
+    }
+
+    // Transforms are listed in the order they are applied, so each one is
+    // multiplied onto the left of the ones before it.
+    fn transform(&self, node: &Node) -> Result<Matrix4, ParseError> {
+        self.expand_transform(node, &mut Vec::new())
+    }
+
+    // `expanding` holds the defines being expanded, so one that refers back
+    // to itself is caught rather than expanded forever.
+    fn expand_transform<'a>(&'a self, node: &'a Node, expanding: &mut Vec<&'a str>) -> Result<Matrix4, ParseError> {
+        let mut m = Matrix4::identity();
+        for step in list(node, "a transform")? {
+            m = match step.as_str() {
+                Some(name) => {
+                    if expanding.contains(&name) {
+                        return Err(step.error(format!("define '{}' refers to itself", name)));
+                    }
+                    let value = self.lookup(step)?;
+                    expanding.push(name);
+                    let t = self.expand_transform(value, expanding)?;
+                    expanding.pop();
+                    t * m
+                }
+                None => operation(m, step)?,
+            };
+        }
+        Ok(m)
+    }
+}
+
+fn operation(m: Matrix4, node: &Node) -> Result<Matrix4, ParseError> {
+    let parts = list(node, "a transform operation")?;
+    if parts.is_empty() {
+        return Err(node.error("expected a transform operation"));
+    }
+    let name = string(&parts[0], "a transform operation")?;
+    let arity = match name {
+        "translate" | "scale" => 3,
+        "rotate-x" | "rotate-y" | "rotate-z" => 1,
+        "rotate" => 4,
+        "shear" => 6,
+        _ => return Err(parts[0].error(format!("unknown transform '{}'", name))),
+    };
+    if parts.len() - 1 != arity {
+        return Err(node.error(format!("'{}' takes {} arguments", name, arity)));
+    }
+    let mut args = Vec::new();
+    for part in &parts[1..] {
+        args.push(number(part, name)?);
+    }
+    Ok(match name {
+        "translate" => m.translate(args[0], args[1], args[2]),
+        "scale" => m.scale(args[0], args[1], args[2]),
+        "rotate-x" => m.rotate_x(args[0]),
+        "rotate-y" => m.rotate_y(args[0]),
+        "rotate-z" => m.rotate_z(args[0]),
+        "rotate" => m.rotate(Tuple::vector(args[0], args[1], args[2]), args[3]),
+        _ => m.shear(args[0], args[1], args[2], args[3], args[4], args[5]),
+    })
+}
+
+fn check_keys(item: &Node, allowed: &[&str]) -> Result<(), ParseError> {
+    for (key, _) in item.as_map().unwrap_or(&[]) {
+        let name = key.as_str().unwrap();
+        if !allowed.contains(&name) {
+            return Err(key.error(format!("unknown key '{}'", name)));
+        }
+    }
+    Ok(())
+}
+
+fn required<'a>(item: &'a Node, key: &str) -> Result<&'a Node, ParseError> {
+    item.get(key).ok_or_else(|| item.error(format!("missing '{}'", key)))
+}
+
+fn list<'a>(node: &'a Node, what: &str) -> Result<&'a [Node], ParseError> {
+    node.as_list().ok_or_else(|| node.error(format!("expected a list for {}", what)))
+}
+
+fn string<'a>(node: &'a Node, what: &str) -> Result<&'a str, ParseError> {
+    node.as_str().ok_or_else(|| node.error(format!("expected a string for {}", what)))
+}
+
+fn number(node: &Node, what: &str) -> Result<Float, ParseError> {
+    node.as_f64().map(|n| n as Float).ok_or_else(|| node.error(format!("expected a number for {}", what)))
+}
+
+fn size(node: &Node, what: &str) -> Result<usize, ParseError> {
+    match node.as_f64() {
+        Some(n) if n >= 1.0 && n.fract() == 0.0 => Ok(n as usize),
+        _ => Err(node.error(format!("expected a positive whole number for {}", what))),
+    }
+}
+
+// Cooler than a candle flame, a blackbody barely glows.
+const MIN_TEMPERATURE: f64 = 1000.0;
+
+// The light's `intensity`, tinted by its color `temperature` in Kelvin.
+fn intensity(item: &Node) -> Result<Color, ParseError> {
+    let intensity = match item.get("intensity") {
+        Some(intensity) => color(intensity)?,
+        None => Color::new(1.0, 1.0, 1.0),
+    };
+    match item.get("temperature") {
+        Some(kelvin) => match kelvin.as_f64() {
+            Some(k) if k >= MIN_TEMPERATURE => Ok(intensity * Color::from_kelvin(k as Float)),
+            Some(_) => Err(kelvin.error(format!("temperature must be at least {}K", MIN_TEMPERATURE))),
+            None => Err(kelvin.error("expected a temperature in Kelvin")),
+        },
+        None => Ok(intensity),
+    }
+}
+
+fn attenuation(item: &Node) -> Result<Attenuation, ParseError> {
+    match item.get("attenuation") {
+        Some(node) => {
+            let name = string(node, "attenuation")?;
+            Attenuation::from_name(name).ok_or_else(|| node.error(format!("unknown attenuation '{}'", name)))
+        }
+        None => Ok(Attenuation::None),
+    }
+}
+
+fn shutter_interval(node: &Node) -> Result<(Float, Float), ParseError> {
+    match node.as_list() {
+        Some(items) if items.len() == 2 => {
+            let (open, close) = (number(&items[0], "shutter")?, number(&items[1], "shutter")?);
+            if open > close {
+                return Err(node.error("the shutter closes before it opens"));
+            }
+            Ok((open, close))
+        }
+        _ => Err(node.error("expected [ open, close ] for shutter")),
+    }
+}
+
+fn triple(node: &Node) -> Result<(Float, Float, Float), ParseError> {
+    match node.as_list() {
+        Some(items) if items.len() == 3 => Ok((
+            number(&items[0], "x")?,
+            number(&items[1], "y")?,
+            number(&items[2], "z")?,
+        )),
+        _ => Err(node.error("expected a list of three numbers")),
+    }
+}
+
+fn point(node: &Node) -> Result<Tuple, ParseError> {
+    let (x, y, z) = triple(node)?;
+    Ok(Tuple::point(x, y, z))
+}
+
+fn vector(node: &Node) -> Result<Tuple, ParseError> {
+    let (x, y, z) = triple(node)?;
+    Ok(Tuple::vector(x, y, z))
+}
+
+// A vector that has to point somewhere, so it can be normalized.
+fn direction(node: &Node) -> Result<Tuple, ParseError> {
+    let v = vector(node)?;
+    if v.magnitude() <= util::EPSILON {
+        return Err(node.error("direction must not be zero"));
+    }
+    Ok(v)
+}
+
+fn color(node: &Node) -> Result<Color, ParseError> {
+    let (r, g, b) = triple(node)?;
+    Ok(Color::new(r, g, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util;
+    use util::consts::PI;
+
+    const CAMERA: &str = "
+- add: camera
+  width: 100
+  height: 50
+  field-of-view: 0.785
+  from: [ 0, 1.5, -5 ]
+  to: [ 0, 1, 0 ]
+  up: [ 0, 1, 0 ]
+";
+
+    fn with_camera(source: &str) -> Result<Scene, ParseError> {
+        Scene::parse(&format!("{}{}", CAMERA, source))
+    }
+
+    #[test]
+    fn parse_camera() {
+        let scene = with_camera("").unwrap();
+        assert_eq!(scene.camera.width, 100);
+        assert_eq!(scene.camera.height, 50);
+        assert_eq!(scene.camera.field_of_view, 0.785);
+        assert_eq!(scene.camera.from, Tuple::point(0.0, 1.5, -5.0));
+        assert_eq!(scene.camera.to, Tuple::point(0.0, 1.0, 0.0));
+        assert_eq!(scene.camera.up, Tuple::vector(0.0, 1.0, 0.0));
+        assert_eq!(scene.camera.aperture, 0.0);
+        assert_eq!(scene.camera.projection, Projection::Perspective);
+        assert_eq!(scene.camera.stereo, None);
+        assert!(util::approx_eq(scene.camera.focal_distance, Float::sqrt(25.25)));
+    }
+
+    #[test]
+    fn parse_camera_projection() {
+        let scene = |extra: &str| Scene::parse(&CAMERA.replace("  up:", &format!("{}  up:", extra)));
+        assert_eq!(scene("  projection: fisheye\n").unwrap().camera.projection, Projection::Fisheye);
+        assert_eq!(scene("  projection: equirectangular\n").unwrap().camera.projection, Projection::Equirectangular);
+        let camera = scene("  projection: orthographic\n  view-width: 12\n").unwrap().camera.to_camera();
+        assert_eq!(camera.projection(), Projection::Orthographic { width: 12.0 });
+        assert_eq!(scene("  projection: orthographic\n").unwrap_err().message, "missing 'view-width'");
+        assert_eq!(scene("  projection: cylindrical\n").unwrap_err().message, "unknown projection 'cylindrical'");
+    }
+
+    #[test]
+    fn parse_camera_stereo() {
+        let scene = |extra: &str| Scene::parse(&CAMERA.replace("  up:", &format!("{}  up:", extra)));
+        let stereo = scene("  stereo: { layout: anaglyph, interaxial: 0.065 }\n").unwrap().camera.stereo.unwrap();
+        assert_eq!(stereo.layout, StereoLayout::Anaglyph);
+        assert_eq!(stereo.interaxial, 0.065);
+        assert!(util::approx_eq(stereo.convergence, Float::sqrt(25.25)));
+        let stereo = scene("  stereo: { layout: over-under, interaxial: 0.1, convergence: 3 }\n").unwrap().camera.stereo;
+        assert_eq!(stereo, Some(StereoRig::new(0.1, 3.0, StereoLayout::OverUnder)));
+        let err = scene("  stereo: { layout: wiggle, interaxial: 0.1 }\n").unwrap_err();
+        assert_eq!(err.message, "unknown stereo layout 'wiggle'");
+        assert_eq!(scene("  stereo: { layout: anaglyph }\n").unwrap_err().message, "missing 'interaxial'");
+    }
+
+    #[test]
+    fn parse_camera_lens() {
+        let source = "
+- add: camera
+  width: 10
+  height: 10
+  field-of-view: 0.785
+  from: [ 0, 0, -5 ]
+  to: [ 0, 0, 0 ]
+  aperture: 0.1
+  focal-distance: 3
+  shutter: [ 0, 0.5 ]
+";
+        let camera = Scene::parse(source).unwrap().camera.to_camera();
+        assert_eq!((camera.aperture(), camera.focal_distance()), (0.1, 3.0));
+        assert_eq!(camera.shutter(), (0.0, 0.5));
+        let err = Scene::parse(&source.replace("[ 0, 0.5 ]", "[ 1, 0.5 ]")).unwrap_err();
+        assert_eq!(err.message, "the shutter closes before it opens");
+        let err = Scene::parse(&source.replace("focal-distance: 3", "focal-distance: 0")).unwrap_err();
+        assert_eq!(err.message, "focal-distance must be positive");
+        let err = Scene::parse(&source.replace("aperture: 0.1", "aperture: -0.1")).unwrap_err();
+        assert_eq!(err.message, "aperture must not be negative");
+        let err = Scene::parse(&source.replace("from: [ 0, 0, -5 ]", "from: [ 0, 0, 0 ]")).unwrap_err();
+        assert_eq!((err.line, err.col, err.message.as_str()), (7, 7, "the camera must look somewhere other than where it is"));
+        let err = Scene::parse(&source.replace("from: [ 0, 0, -5 ]", "from: [ 0, 5, 0 ]")).unwrap_err();
+        assert_eq!((err.line, err.message.as_str()), (2, "up must not point along the direction the camera looks"));
+        let err = Scene::parse(&source.replace("aperture", "up: [ 0, 0, -2 ]\n  aperture")).unwrap_err();
+        assert_eq!((err.line, err.col), (8, 7));
+        for fov in ["0", "-1", "3.14159", "10"].iter() {
+            let err = Scene::parse(&source.replace("0.785", fov)).unwrap_err();
+            assert_eq!((err.line, err.col, err.message.as_str()), (5, 18, "field-of-view must be between 0 and pi"));
+        }
+        // A fisheye may see all the way around.
+        let fisheye = source.replace("0.785", "3.5").replace("aperture", "projection: fisheye\n  aperture");
+        assert!(Scene::parse(&fisheye).is_ok());
+        let err = Scene::parse(&fisheye.replace("3.5", "7")).unwrap_err();
+        assert_eq!(err.message, "field-of-view must be above 0 and at most 2 pi");
+    }
+
+    #[test]
+    fn parse_light() {
+        let scene = with_camera("- add: light\n  at: [ -10, 10, -10 ]\n  intensity: [ 1, 0.5, 1 ]\n").unwrap();
+        assert_eq!(
+            scene.lights,
+            vec![LightDescription::Point(PointLight::new(Tuple::point(-10.0, 10.0, -10.0), Color::new(1.0, 0.5, 1.0)))]
+        );
+    }
+
+    #[test]
+    fn parse_light_temperature_and_attenuation() {
+        let scene = with_camera(
+            "
+- add: light
+  at: [ 0, 10, 0 ]
+  intensity: [ 100, 100, 100 ]
+  temperature: 3200
+  attenuation: inverse-square
+",
+        )
+        .unwrap();
+        let mut expected = PointLight::new(Tuple::point(0.0, 10.0, 0.0), Color::from_kelvin(3200.0) * 100.0);
+        expected.attenuation = Attenuation::InverseSquare;
+        assert_eq!(scene.lights, vec![LightDescription::Point(expected)]);
+
+        let err = with_camera("- add: light\n  at: [ 0, 0, 0 ]\n  attenuation: linear\n").unwrap_err();
+        assert_eq!(err.message, "unknown attenuation 'linear'");
+        let err = with_camera("- add: light\n  at: [ 0, 0, 0 ]\n  temperature: -5\n").unwrap_err();
+        assert_eq!(err.message, "temperature must be at least 1000K");
+        let err = with_camera("- add: light\n  at: [ 0, 0, 0 ]\n  temperature: 20\n").unwrap_err();
+        assert_eq!(err.message, "temperature must be at least 1000K");
+    }
+
+    #[test]
+    fn parse_spot_and_directional_lights() {
+        let scene = with_camera(
+            "
+- add: spot-light
+  at: [ 0, 10, 0 ]
+  direction: [ 0, -1, 0 ]
+  angle: 0.5
+  falloff: 0.1
+- add: directional-light
+  direction: [ 1, -1, 0 ]
+  intensity: [ 1, 0.9, 0.8 ]
+  angular-diameter: 0.05
+  samples: 3
+",
+        )
+        .unwrap();
+        let spot = SpotLight::new(
+            Tuple::point(0.0, 10.0, 0.0),
+            Tuple::vector(0.0, -1.0, 0.0),
+            0.5,
+            0.1,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        let mut sun = DirectionalLight::new(Tuple::vector(1.0, -1.0, 0.0), Color::new(1.0, 0.9, 0.8));
+        sun.angular_diameter = 0.05;
+        sun.samples = 3;
+        assert_eq!(scene.lights, vec![LightDescription::Spot(spot), LightDescription::Directional(sun)]);
+
+        let err = with_camera("- add: spot-light\n  at: [ 0, 0, 0 ]\n  direction: [ 0, -1, 0 ]\n").unwrap_err();
+        assert_eq!(err.message, "missing 'angle'");
+        let err = with_camera("- add: spot-light\n  at: [ 0, 0, 0 ]\n  direction: [ 0, 0, 0 ]\n  angle: 0.5\n").unwrap_err();
+        assert_eq!((err.line, err.col, err.message.as_str()), (11, 14, "direction must not be zero"));
+        let err = with_camera("- add: spot-light\n  at: [ 0, 0, 0 ]\n  direction: [ 0, -1, 0 ]\n  angle: -0.5\n").unwrap_err();
+        assert_eq!((err.line, err.col, err.message.as_str()), (12, 10, "angle must be positive"));
+        let err = with_camera("- add: directional-light\n  direction: [ 0, 0, 0 ]\n").unwrap_err();
+        assert_eq!((err.line, err.col, err.message.as_str()), (10, 14, "direction must not be zero"));
+    }
+
+    #[test]
+    fn parse_area_light() {
+        let scene = with_camera(
+            "
+- add: area-light
+  corner: [ -1, 2, 4 ]
+  uvec: [ 2, 0, 0 ]
+  usteps: 4
+  vvec: [ 0, 2, 0 ]
+  vsteps: 2
+  jitter: false
+",
+        )
+        .unwrap();
+        let mut expected = AreaLight::new(
+            Tuple::point(-1.0, 2.0, 4.0),
+            Tuple::vector(2.0, 0.0, 0.0),
+            4,
+            Tuple::vector(0.0, 2.0, 0.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        expected.jitter = false;
+        assert_eq!(scene.lights, vec![LightDescription::Area(expected)]);
+        assert_eq!(scene.world().unwrap().lights[0].samples(), 8);
+
+        let err = with_camera("- add: area-light\n  corner: [ 0, 0, 0 ]\n  uvec: [ 1, 0, 0 ]\n  usteps: 0\n").unwrap_err();
+        assert_eq!(err.message, "expected a positive whole number for usteps");
+    }
+
+    #[test]
+    fn parse_environment() {
+        let scene = with_camera("- add: environment\n  zenith: [ 0.2, 0.4, 1 ]\n  horizon: [ 1, 1, 1 ]\n  samples: 0\n").unwrap();
+        let white = Color::new(1.0, 1.0, 1.0);
+        let expected = EnvironmentDescription {
+            background: BackgroundDescription::Gradient { zenith: Color::new(0.2, 0.4, 1.0), horizon: white, ground: white },
+            intensity: 1.0,
+            samples: 0,
+        };
+        assert_eq!(scene.environment, Some(expected));
+        let world = scene.world().unwrap();
+        assert_eq!(world.environment.as_ref().map(|e| e.samples()), Some(0));
+
+        let scene = with_camera("- add: environment\n  color: [ 0.1, 0.1, 0.1 ]\n  intensity: 2\n").unwrap();
+        let environment = scene.environment.unwrap();
+        assert_eq!(environment.background, BackgroundDescription::Solid(Color::new(0.1, 0.1, 0.1)));
+        assert_eq!((environment.intensity, environment.samples), (2.0, 16));
+
+        let scene = with_camera("- add: environment\n  image: no-such-sky.hdr\n").unwrap();
+        assert!(scene.world().err().unwrap().starts_with("no-such-sky.hdr: "));
+        let err = with_camera("- add: environment\n  color: [ 1, 1, 1 ]\n  image: sky.hdr\n").unwrap_err();
+        assert_eq!(err.message, "an environment needs exactly one of 'color', 'zenith' or 'image'");
+        let err = with_camera("- add: environment\n  zenith: [ 1, 1, 1 ]\n").unwrap_err();
+        assert_eq!(err.message, "missing 'horizon'");
+        let err = with_camera("- add: environment\n  color: [ 0, 0, 0 ]\n- add: environment\n  color: [ 0, 0, 0 ]\n").unwrap_err();
+        assert_eq!(err.message, "the scene already has an environment");
+    }
+
+    #[test]
+    fn parse_sky() {
+        let scene = with_camera("- add: sky\n  turbidity: 4\n  elevation: 0.5\n  azimuth: 1\n  sun-samples: 6\n").unwrap();
+        let sky = Sky::new(4.0, 0.5, 1.0);
+        assert_eq!(scene.environment.unwrap().background, BackgroundDescription::Sky(sky));
+        assert_eq!(scene.lights, vec![LightDescription::Directional(sky.sun(sky::SUN_ANGULAR_DIAMETER, 6))]);
+
+        let scene = with_camera("- add: sky\n  elevation: 1\n  sun: false\n  intensity: 2\n").unwrap();
+        assert_eq!(scene.environment.unwrap().intensity, 2.0);
+        assert!(scene.lights.is_empty());
+
+        let err = with_camera("- add: sky\n  elevation: -0.1\n").unwrap_err();
+        assert_eq!(err.message, "the sun's elevation must be between 0 and pi/2");
+        let err = with_camera("- add: sky\n  elevation: 1\n  turbidity: 20\n").unwrap_err();
+        assert_eq!(err.message, "turbidity must be between 1.7 and 10");
+        let err = with_camera("- add: sky\n  elevation: 1\n- add: environment\n  color: [ 0, 0, 0 ]\n").unwrap_err();
+        assert_eq!(err.message, "the scene already has an environment");
+    }
+
+    #[test]
+    fn parse_shapes_with_materials() {
+        let scene = with_camera("
+- add: sphere
+  material:
+    color: [ 1, 0, 0 ]
+    diffuse: 0.7
+    refractive-index: 1.5
+    emissive: [ 0.5, 0.5, 0 ]
+- add: plane
+- add: cube
+").unwrap();
+        assert_eq!(scene.objects.len(), 3);
+        let sphere = &scene.objects[0];
+        assert_eq!(sphere.kind, ShapeKind::Sphere);
+        assert_eq!(sphere.material.color, Color::new(1.0, 0.0, 0.0));
+        assert_eq!(sphere.material.diffuse, 0.7);
+        assert_eq!(sphere.material.refractive_index, 1.5);
+        assert_eq!(sphere.material.emissive, Color::new(0.5, 0.5, 0.0));
+        assert_eq!(sphere.material.ambient, 0.1);
+        assert_eq!(scene.objects[1].kind, ShapeKind::Plane);
+        assert_eq!(scene.objects[1].material, Material::new());
+        assert_eq!(scene.objects[2].transform, Matrix4::identity());
+    }
+
+    #[test]
+    fn transforms_apply_in_order() {
+        let scene = with_camera("
+- add: sphere
+  transform:
+    - [ rotate-x, 1.5707963267948966 ]
+    - [ scale, 5, 5, 5 ]
+    - [ translate, 10, 5, 7 ]
+").unwrap();
+        let t = scene.objects[0].transform;
+        assert_eq!(t, Matrix4::translation(10.0, 5.0, 7.0) * Matrix4::scaling(5.0, 5.0, 5.0) * Matrix4::rotation_x(PI / 2.0));
+        assert_eq!(t * Tuple::point(1.0, 0.0, 1.0), Tuple::point(15.0, 0.0, 7.0));
+        let scene = with_camera("
+- add: cube
+  transform:
+    - [ rotate, 0, 1, 0, 1.5707963267948966 ]
+    - [ translate, 0, 0, 2 ]
+").unwrap();
+        assert_eq!(scene.objects[0].transform * Tuple::point(1.0, 0.0, 0.0), Tuple::point(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn animated_scene() {
+        let source = format!("{}{}", CAMERA, "
+- define: glow
+  value:
+    color:
+      keyframes: [ [ 0, [ 1, 0, 0 ] ], [ 10, [ 0, 0, 1 ] ] ]
+- add: light
+  at: [ 0, 10, 0 ]
+  intensity: { keyframes: [ [ 0, [ 0, 0, 0 ] ], [ 10, [ 2, 2, 2 ] ] ] }
+- add: sphere
+  material: glow
+  transform:
+    - [ translate, { keyframes: [ [ 0, 0 ], [ 10, 5 ] ], interpolation: cubic }, 0, 0 ]
+");
+        let scene = Scene::parse_frame(&source, 5.0).unwrap();
+        assert_eq!(scene.objects[0].material.color, Color::new(0.5, 0.0, 0.5));
+        assert_eq!(scene.objects[0].transform, Matrix4::translation(2.5, 0.0, 0.0));
+        match scene.lights[0] {
+            LightDescription::Point(light) => assert_eq!(light.intensity, Color::new(1.0, 1.0, 1.0)),
+            ref other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(Scene::parse(&source).unwrap(), Scene::parse_frame(&source, 0.0).unwrap());
+        assert_eq!(Scene::parse_frame(&source, 20.0).unwrap().objects[0].material.color, Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn moving_objects() {
+        let scene = with_camera("
+- add: sphere
+  transform:
+    - [ translate, -1, 0, 0 ]
+  end-transform:
+    - [ translate, 1, 0, 0 ]
+- add: plane
+").unwrap();
+        assert_eq!(scene.objects[0].end_transform, Some(Matrix4::translation(1.0, 0.0, 0.0)));
+        assert_eq!(scene.objects[1].end_transform, None);
+        let world = scene.world().unwrap();
+        let bounds = world.objects[0].bounds();
+        assert_eq!((bounds.min.x(), bounds.max.x()), (-2.0, 2.0));
+        let err = with_camera("- add: cube\n  end-transform:\n    - [ scale, 0, 1, 1 ]\n").unwrap_err();
+        assert_eq!(err.message, "transform is not invertible");
+    }
+
+    #[test]
+    fn define_and_extend() {
+        let scene = with_camera("
+- define: white
+  value:
+    color: [ 1, 1, 1 ]
+    diffuse: 0.7
+    ambient: 0.2
+- define: blue
+  extend: white
+  value:
+    color: [ 0, 0, 1 ]
+- define: standard
+  value:
+    - [ translate, 1, -1, 1 ]
+    - [ scale, 0.5, 0.5, 0.5 ]
+- add: cube
+  material: blue
+  transform:
+    - standard
+    - [ translate, 4, 0, 0 ]
+").unwrap();
+        let cube = &scene.objects[0];
+        assert_eq!(cube.material.color, Color::new(0.0, 0.0, 1.0));
+        assert_eq!(cube.material.diffuse, 0.7);
+        assert_eq!(cube.material.ambient, 0.2);
+        assert_eq!(
+            cube.transform,
+            Matrix4::translation(4.0, 0.0, 0.0) * Matrix4::scaling(0.5, 0.5, 0.5) * Matrix4::translation(1.0, -1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn build_world_and_camera() {
+        let scene = with_camera("
+- add: light
+  at: [ -10, 10, -10 ]
+- add: sphere
+  material:
+    color: [ 0.8, 1.0, 0.6 ]
+    diffuse: 0.7
+    specular: 0.2
+- add: sphere
+  transform:
+    - [ scale, 0.5, 0.5, 0.5 ]
+").unwrap();
+        let world = scene.world().unwrap();
+        assert_eq!(world.objects.len(), 2);
+        assert_eq!(world.lights.len(), 1);
+        assert_eq!(world.objects[0].material().diffuse, 0.7);
+        let camera = scene.camera.to_camera();
+        assert_eq!(camera.hsize(), 100);
+        assert_eq!(camera.vsize(), 50);
+        let ray = camera.ray_for_pixel(50, 25).unwrap();
+        assert_eq!(ray.origin, Tuple::point(0.0, 1.5, -5.0));
+        // Looking slightly down, the center ray grazes the top of the outer sphere only.
+        assert_eq!(world.intersect(&ray).len(), 2);
+    }
+
+    #[test]
+    fn report_scene_errors() {
+        let err = with_camera("- add: sphere\n  material: missing\n").unwrap_err();
+        assert_eq!((err.line, err.col, err.message.as_str()), (10, 13, "'missing' is not defined"));
+        let err = with_camera("- add: sphere\n  transform:\n    - [ rotate-x, 1, 2 ]\n").unwrap_err();
+        assert_eq!((err.line, err.col, err.message.as_str()), (11, 7, "'rotate-x' takes 1 arguments"));
+        let err = with_camera("- add: cube\n  transform:\n    - [ scale, 1, 0, 1 ]\n").unwrap_err();
+        assert_eq!((err.line, err.col, err.message.as_str()), (11, 5, "transform is not invertible"));
+        // A small object far from the origin is still invertible.
+        let scene = with_camera("- add: sphere\n  transform:\n    - [ scale, 0.1, 0.1, 0.1 ]\n    - [ translate, 5000, 0, 0 ]\n").unwrap();
+        assert!(scene.world().is_ok());
+        let err = with_camera("- add: torus\n").unwrap_err();
+        assert_eq!((err.line, err.col, err.message.as_str()), (9, 8, "unknown item 'torus'"));
+        let err = with_camera("- add: light\n  at: [ 1, 2 ]\n").unwrap_err();
+        assert_eq!((err.line, err.col), (10, 7));
+        let err = with_camera("- add: plane\n  colour: [ 1, 1, 1 ]\n").unwrap_err();
+        assert_eq!((err.line, err.col, err.message.as_str()), (10, 3, "unknown key 'colour'"));
+        let err = with_camera("- define: a\n  value: [ a ]\n- add: cube\n  transform: [ a ]\n").unwrap_err();
+        assert_eq!((err.line, err.col, err.message.as_str()), (10, 12, "define 'a' refers to itself"));
+        let err = with_camera("- define: a\n  value: [ b ]\n- define: b\n  value: [ a ]\n- add: cube\n  transform: [ a ]\n").unwrap_err();
+        assert_eq!((err.line, err.col, err.message.as_str()), (12, 12, "define 'a' refers to itself"));
+        let err = Scene::parse("- add: light\n  at: [ 0, 0, 0 ]\n").unwrap_err();
+        assert_eq!(err.message, "the scene has no camera");
+        let err = Scene::parse("- add: camera\n  width: 10\n").unwrap_err();
+        assert_eq!((err.line, err.col, err.message.as_str()), (1, 3, "missing 'height'"));
+    }
+}
@@ -0,0 +1,717 @@
+use std::error::Error;
+use std::fmt;
+
+// A hand-written parser for the subset of YAML used by scene files: block
+// lists and maps, flow lists and maps, quoted and plain scalars, and comments.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    List(Vec<Node>),
+    Map(Vec<(Node, Node)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub value: Value,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Node {
+    pub fn new(value: Value, line: usize, col: usize) -> Node {
+        Node { value, line, col }
+    }
+
+    pub fn error<S: Into<String>>(&self, message: S) -> ParseError {
+        ParseError::new(self.line, self.col, message)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self.value {
+            Value::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.value {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.value {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Node]> {
+        match self.value {
+            Value::List(ref items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&[(Node, Node)]> {
+        match self.value {
+            Value::Map(ref entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    // Looks up a key when this node is a map.
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        self.as_map()
+            .and_then(|entries| entries.iter().find(|(k, _)| k.as_str() == Some(key)))
+            .map(|(_, v)| v)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new<S: Into<String>>(line: usize, col: usize, message: S) -> ParseError {
+        ParseError { line, col, message: message.into() }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.col, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+pub fn parse(source: &str) -> Result<Node, ParseError> {
+    Parser::new(source).parse_document()
+}
+
+// How deeply flow collections may nest. Each level recurses, so without a
+// limit a run of '[' could overflow the stack.
+const MAX_FLOW_DEPTH: usize = 128;
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    col: usize,
+    // Flow collections currently open.
+    depth: usize,
+}
+
+impl Parser {
+    fn new(source: &str) -> Parser {
+        Parser { chars: source.chars().collect(), pos: 0, line: 1, col: 1, depth: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).cloned()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek();
+        if let Some(c) = c {
+            self.pos += 1;
+            if c == '\n' {
+                self.line += 1;
+                self.col = 1;
+            } else {
+                self.col += 1;
+            }
+        }
+        c
+    }
+
+    fn error<S: Into<String>>(&self, message: S) -> ParseError {
+        ParseError::new(self.line, self.col, message)
+    }
+
+    fn node(&self, value: Value, (line, col): (usize, usize)) -> Node {
+        Node::new(value, line, col)
+    }
+
+    fn mark(&self) -> (usize, usize) {
+        (self.line, self.col)
+    }
+
+    fn skip_spaces(&mut self) {
+        while let Some(' ') | Some('\t') = self.peek() {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while let Some(c) = self.peek() {
+                if c == '\n' {
+                    break;
+                }
+                self.bump();
+            }
+        }
+    }
+
+    fn at_line_end(&self) -> bool {
+        matches!(self.peek(), None | Some('\n') | Some('\r'))
+    }
+
+    // Moves to the first content character of the next non-blank line, or to
+    // the end of the input.
+    fn skip_blank(&mut self) -> Result<(), ParseError> {
+        loop {
+            while self.peek() == Some(' ') {
+                self.bump();
+            }
+            if self.peek() == Some('\t') {
+                self.skip_spaces();
+                if !self.at_line_end() && self.peek() != Some('#') {
+                    return Err(self.error("tabs are not allowed in indentation"));
+                }
+            }
+            self.skip_comment();
+            match self.peek() {
+                Some('\n') | Some('\r') => {
+                    self.bump();
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn expect_line_end(&mut self) -> Result<(), ParseError> {
+        self.skip_spaces();
+        self.skip_comment();
+        if self.at_line_end() {
+            Ok(())
+        } else {
+            Err(self.error("unexpected characters after value"))
+        }
+    }
+
+    fn at_sequence_dash(&self) -> bool {
+        self.peek() == Some('-')
+            && matches!(self.peek_at(1), None | Some(' ') | Some('\t') | Some('\n') | Some('\r'))
+    }
+
+    // Looks ahead (without consuming) for a `key:` at the current position.
+    fn at_mapping_key(&self) -> bool {
+        let mut i = self.pos;
+        match self.chars.get(i) {
+            Some(&'[') | Some(&'{') | None => return false,
+            Some(&q) if q == '"' || q == '\'' => {
+                i += 1;
+                while let Some(&c) = self.chars.get(i) {
+                    if c == '\n' {
+                        return false;
+                    }
+                    i += 1;
+                    if c == q {
+                        break;
+                    }
+                }
+                while let Some(&' ') = self.chars.get(i) {
+                    i += 1;
+                }
+                return self.chars.get(i) == Some(&':') && self.is_value_separator(i + 1);
+            }
+            _ => {}
+        }
+        while let Some(&c) = self.chars.get(i) {
+            match c {
+                '\n' | '\r' => return false,
+                '#' if i > self.pos && self.chars[i - 1] == ' ' => return false,
+                ':' if self.is_value_separator(i + 1) => return true,
+                _ => i += 1,
+            }
+        }
+        false
+    }
+
+    fn is_value_separator(&self, i: usize) -> bool {
+        matches!(self.chars.get(i), None | Some(&' ') | Some(&'\t') | Some(&'\n') | Some(&'\r'))
+    }
+
+    fn parse_document(&mut self) -> Result<Node, ParseError> {
+        self.skip_blank()?;
+        if self.peek() == Some('-') && self.peek_at(1) == Some('-') && self.peek_at(2) == Some('-') {
+            for _ in 0..3 {
+                self.bump();
+            }
+            self.expect_line_end()?;
+            self.skip_blank()?;
+        }
+        if self.peek().is_none() {
+            return Ok(Node::new(Value::Null, 1, 1));
+        }
+        let indent = self.col;
+        let node = self.parse_block(indent)?;
+        self.skip_blank()?;
+        if self.peek().is_some() {
+            return Err(self.error("unexpected content"));
+        }
+        Ok(node)
+    }
+
+    // Parses the node starting at the current position, which sits at column
+    // `indent`. On return the parser is at the end of the last line of the node
+    // or at the start of the next content line.
+    fn parse_block(&mut self, indent: usize) -> Result<Node, ParseError> {
+        if self.at_sequence_dash() {
+            self.parse_block_sequence(indent)
+        } else if self.at_mapping_key() {
+            self.parse_block_mapping(indent)
+        } else {
+            let node = self.parse_inline_value()?;
+            self.expect_line_end()?;
+            Ok(node)
+        }
+    }
+
+    fn parse_block_sequence(&mut self, indent: usize) -> Result<Node, ParseError> {
+        let start = self.mark();
+        let mut items = Vec::new();
+        loop {
+            let dash = self.mark();
+            self.bump();
+            self.skip_spaces();
+            self.skip_comment();
+            if self.at_line_end() {
+                self.skip_blank()?;
+                if self.peek().is_some() && self.col > indent {
+                    let col = self.col;
+                    items.push(self.parse_block(col)?);
+                } else {
+                    items.push(self.node(Value::Null, dash));
+                }
+            } else {
+                let col = self.col;
+                items.push(self.parse_block(col)?);
+            }
+            self.skip_blank()?;
+            if self.peek().is_none() || self.col < indent {
+                break;
+            }
+            if self.col > indent {
+                return Err(self.error("bad indentation of a list item"));
+            }
+            if !self.at_sequence_dash() {
+                break;
+            }
+        }
+        Ok(self.node(Value::List(items), start))
+    }
+
+    fn parse_block_mapping(&mut self, indent: usize) -> Result<Node, ParseError> {
+        let start = self.mark();
+        let mut entries: Vec<(Node, Node)> = Vec::new();
+        loop {
+            let key = self.parse_key()?;
+            if entries.iter().any(|(k, _)| k.value == key.value) {
+                return Err(key.error(format!("duplicate key '{}'", key.as_str().unwrap_or(""))));
+            }
+            self.skip_spaces();
+            self.skip_comment();
+            let value = if self.at_line_end() {
+                self.skip_blank()?;
+                let nested = self.peek().is_some()
+                    && (self.col > indent || (self.col == indent && self.at_sequence_dash()));
+                if nested {
+                    let col = self.col;
+                    self.parse_block(col)?
+                } else {
+                    Node::new(Value::Null, key.line, key.col)
+                }
+            } else {
+                let node = self.parse_inline_value()?;
+                self.expect_line_end()?;
+                node
+            };
+            entries.push((key, value));
+            self.skip_blank()?;
+            if self.peek().is_none() || self.col < indent {
+                break;
+            }
+            if self.col > indent || self.at_sequence_dash() {
+                return Err(self.error("bad indentation of a mapping entry"));
+            }
+        }
+        Ok(self.node(Value::Map(entries), start))
+    }
+
+    fn parse_key(&mut self) -> Result<Node, ParseError> {
+        let start = self.mark();
+        let key = match self.peek() {
+            Some('"') | Some('\'') => self.parse_quoted()?,
+            _ => {
+                let mut s = String::new();
+                loop {
+                    match self.peek() {
+                        Some(':') if self.is_value_separator(self.pos + 1) => break,
+                        None | Some('\n') | Some('\r') => return Err(self.error("expected ':' after key")),
+                        Some(c) => {
+                            s.push(c);
+                            self.bump();
+                        }
+                    }
+                }
+                self.node(Value::String(s.trim_end().to_string()), start)
+            }
+        };
+        self.skip_spaces();
+        if self.peek() != Some(':') {
+            return Err(self.error("expected ':' after key"));
+        }
+        self.bump();
+        match key.value {
+            Value::String(_) => Ok(key),
+            _ => Err(key.error("expected a string key")),
+        }
+    }
+
+    fn parse_inline_value(&mut self) -> Result<Node, ParseError> {
+        match self.peek() {
+            Some('[') => self.parse_flow_sequence(),
+            Some('{') => self.parse_flow_mapping(),
+            Some('"') | Some('\'') => self.parse_quoted(),
+            _ => self.parse_plain(false),
+        }
+    }
+
+    fn parse_flow_value(&mut self) -> Result<Node, ParseError> {
+        match self.peek() {
+            Some('[') => self.parse_flow_sequence(),
+            Some('{') => self.parse_flow_mapping(),
+            Some('"') | Some('\'') => self.parse_quoted(),
+            _ => self.parse_plain(true),
+        }
+    }
+
+    // Flow collections may span lines and contain comments.
+    fn skip_flow_space(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ') | Some('\t') | Some('\n') | Some('\r') => {
+                    self.bump();
+                }
+                Some('#') => self.skip_comment(),
+                _ => return,
+            }
+        }
+    }
+
+    // Called on opening a flow collection; `parse_flow_sequence` and
+    // `parse_flow_mapping` step back out when they close it.
+    fn enter_flow(&mut self) -> Result<(), ParseError> {
+        if self.depth == MAX_FLOW_DEPTH {
+            return Err(self.error("collections nested too deeply"));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn parse_flow_sequence(&mut self) -> Result<Node, ParseError> {
+        self.enter_flow()?;
+        let start = self.mark();
+        self.bump();
+        let mut items = Vec::new();
+        loop {
+            self.skip_flow_space();
+            match self.peek() {
+                Some(']') => {
+                    self.bump();
+                    break;
+                }
+                None => return Err(ParseError::new(start.0, start.1, "unterminated '['")),
+                _ => {}
+            }
+            items.push(self.parse_flow_value()?);
+            self.skip_flow_space();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                }
+                Some(']') => {
+                    self.bump();
+                    break;
+                }
+                None => return Err(ParseError::new(start.0, start.1, "unterminated '['")),
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+        self.depth -= 1;
+        Ok(self.node(Value::List(items), start))
+    }
+
+    fn parse_flow_mapping(&mut self) -> Result<Node, ParseError> {
+        self.enter_flow()?;
+        let start = self.mark();
+        self.bump();
+        let mut entries: Vec<(Node, Node)> = Vec::new();
+        loop {
+            self.skip_flow_space();
+            match self.peek() {
+                Some('}') => {
+                    self.bump();
+                    break;
+                }
+                None => return Err(ParseError::new(start.0, start.1, "unterminated '{'")),
+                _ => {}
+            }
+            let key = self.parse_flow_value()?;
+            if key.as_str().is_none() {
+                return Err(key.error("expected a string key"));
+            }
+            if entries.iter().any(|(k, _)| k.value == key.value) {
+                return Err(key.error(format!("duplicate key '{}'", key.as_str().unwrap_or(""))));
+            }
+            self.skip_flow_space();
+            if self.peek() != Some(':') {
+                return Err(self.error("expected ':' after key"));
+            }
+            self.bump();
+            self.skip_flow_space();
+            let value = self.parse_flow_value()?;
+            entries.push((key, value));
+            self.skip_flow_space();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                }
+                Some('}') => {
+                    self.bump();
+                    break;
+                }
+                None => return Err(ParseError::new(start.0, start.1, "unterminated '{'")),
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+        self.depth -= 1;
+        Ok(self.node(Value::Map(entries), start))
+    }
+
+    fn parse_quoted(&mut self) -> Result<Node, ParseError> {
+        let start = self.mark();
+        let quote = self.bump().unwrap();
+        let mut s = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') | Some('\r') => {
+                    return Err(ParseError::new(start.0, start.1, "unterminated string"));
+                }
+                Some(c) if c == quote => {
+                    self.bump();
+                    // '' is an escaped quote inside single-quoted strings
+                    if quote == '\'' && self.peek() == Some('\'') {
+                        self.bump();
+                        s.push('\'');
+                    } else {
+                        break;
+                    }
+                }
+                Some('\\') if quote == '"' => {
+                    self.bump();
+                    let escaped = match self.peek() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('0') => '\0',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        _ => return Err(self.error("unknown escape sequence")),
+                    };
+                    self.bump();
+                    s.push(escaped);
+                }
+                Some(c) => {
+                    self.bump();
+                    s.push(c);
+                }
+            }
+        }
+        Ok(self.node(Value::String(s), start))
+    }
+
+    fn parse_plain(&mut self, in_flow: bool) -> Result<Node, ParseError> {
+        let start = self.mark();
+        let mut s = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') | Some('\r') => break,
+                Some('#') if s.ends_with(' ') || s.ends_with('\t') => break,
+                Some(',') | Some(']') | Some('}') if in_flow => break,
+                Some(':') if self.is_value_separator(self.pos + 1) => {
+                    if in_flow {
+                        break;
+                    }
+                    return Err(self.error("mapping values are not allowed here"));
+                }
+                Some(c) => {
+                    s.push(c);
+                    self.bump();
+                }
+            }
+        }
+        let s = s.trim_end();
+        if s.is_empty() {
+            return Err(ParseError::new(start.0, start.1, "expected a value"));
+        }
+        Ok(self.node(resolve_plain(s), start))
+    }
+}
+
+fn resolve_plain(s: &str) -> Value {
+    match s {
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        "null" | "~" => return Value::Null,
+        _ => {}
+    }
+    if s.chars().any(|c| c.is_ascii_digit()) {
+        if let Ok(n) = s.parse::<f64>() {
+            return Value::Number(n);
+        }
+    }
+    Value::String(s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(node: &Node) -> Vec<&str> {
+        node.as_list().unwrap().iter().map(|n| n.as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn parse_scalars() {
+        assert_eq!(parse("42").unwrap().value, Value::Number(42.0));
+        assert_eq!(parse("-0.5").unwrap().value, Value::Number(-0.5));
+        assert_eq!(parse("1e3").unwrap().value, Value::Number(1000.0));
+        assert_eq!(parse("true").unwrap().value, Value::Bool(true));
+        assert_eq!(parse("~").unwrap().value, Value::Null);
+        assert_eq!(parse("rotate-x").unwrap().value, Value::String("rotate-x".to_string()));
+        assert_eq!(parse("\"a \\\"b\\\"\"").unwrap().value, Value::String("a \"b\"".to_string()));
+        assert_eq!(parse("'it''s'").unwrap().value, Value::String("it's".to_string()));
+        assert_eq!(parse("'42'").unwrap().value, Value::String("42".to_string()));
+        assert_eq!(parse("").unwrap().value, Value::Null);
+    }
+
+    #[test]
+    fn parse_block_list() {
+        let doc = parse("- a\n- b # comment\n\n- c\n").unwrap();
+        assert_eq!(strings(&doc), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn parse_block_map() {
+        let doc = parse("width: 100\nheight: 50\nname: test scene\n").unwrap();
+        assert_eq!(doc.get("width").unwrap().as_f64(), Some(100.0));
+        assert_eq!(doc.get("height").unwrap().as_f64(), Some(50.0));
+        assert_eq!(doc.get("name").unwrap().as_str(), Some("test scene"));
+        assert_eq!(doc.get("missing"), None);
+    }
+
+    #[test]
+    fn parse_flow_collections() {
+        let doc = parse("[ translate, 1, -2.5, [ 3 ], { a: 1, 'b': x } ]").unwrap();
+        let items = doc.as_list().unwrap();
+        assert_eq!(items.len(), 5);
+        assert_eq!(items[0].as_str(), Some("translate"));
+        assert_eq!(items[2].as_f64(), Some(-2.5));
+        assert_eq!(items[3].as_list().unwrap()[0].as_f64(), Some(3.0));
+        assert_eq!(items[4].get("b").unwrap().as_str(), Some("x"));
+    }
+
+    #[test]
+    fn parse_deeply_nested_flow_collections() {
+        let nested = format!("x: {}1{}\n", "[".repeat(128), "]".repeat(128));
+        assert!(parse(&nested).is_ok());
+        let err = parse(&format!("x: {}", "[".repeat(200000))).unwrap_err();
+        assert_eq!((err.line, err.col, err.message.as_str()), (1, 132, "collections nested too deeply"));
+        let err = parse(&format!("x: {}", "{ a: ".repeat(200))).unwrap_err();
+        assert_eq!(err.message, "collections nested too deeply");
+    }
+
+    #[test]
+    fn parse_multiline_flow_list() {
+        let doc = parse("from: [ 1,\n        2, # two\n        3 ]\nto: 4\n").unwrap();
+        assert_eq!(doc.get("from").unwrap().as_list().unwrap().len(), 3);
+        assert_eq!(doc.get("to").unwrap().as_f64(), Some(4.0));
+    }
+
+    #[test]
+    fn parse_nested_blocks() {
+        let source = "
+- add: camera
+  from: [ 0, 1.5, -5 ]
+
+- define: standard
+  value:
+    - [ scale, 0.5, 0.5, 0.5 ]
+    - [ translate, 1, 0, 0 ]
+
+- add: sphere
+  material:
+    color: [ 1, 0, 0 ]
+  transform:
+  - standard
+";
+        let doc = parse(source).unwrap();
+        let items = doc.as_list().unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].get("add").unwrap().as_str(), Some("camera"));
+        assert_eq!(items[1].get("value").unwrap().as_list().unwrap().len(), 2);
+        let material = items[2].get("material").unwrap();
+        assert_eq!(material.get("color").unwrap().as_list().unwrap().len(), 3);
+        assert_eq!(strings(items[2].get("transform").unwrap()), vec!["standard"]);
+    }
+
+    #[test]
+    fn parse_nested_lists() {
+        let doc = parse("- - a\n  - b\n- - c\n").unwrap();
+        let items = doc.as_list().unwrap();
+        assert_eq!(strings(&items[0]), vec!["a", "b"]);
+        assert_eq!(strings(&items[1]), vec!["c"]);
+    }
+
+    #[test]
+    fn node_positions() {
+        let doc = parse("- add: light\n  at: [ 1, 2, 3 ]\n").unwrap();
+        let at = doc.as_list().unwrap()[0].get("at").unwrap();
+        assert_eq!((at.line, at.col), (2, 7));
+        assert_eq!((at.as_list().unwrap()[2].line, at.as_list().unwrap()[2].col), (2, 15));
+    }
+
+    #[test]
+    fn report_error_positions() {
+        let err = parse("a: 1\n  b: 2\n").unwrap_err();
+        assert_eq!((err.line, err.col), (2, 3));
+        let err = parse("a: [ 1, 2\n").unwrap_err();
+        assert_eq!((err.line, err.col), (1, 4));
+        let err = parse("a: 1\na: 2\n").unwrap_err();
+        assert_eq!((err.line, err.col), (2, 1));
+        assert_eq!(err.message, "duplicate key 'a'");
+        let err = parse("a: \"open\n").unwrap_err();
+        assert_eq!((err.line, err.col), (1, 4));
+        let err = parse("- a\n\t- b\n").unwrap_err();
+        assert_eq!(err.line, 2);
+        assert_eq!(err.to_string(), "line 2, column 2: tabs are not allowed in indentation");
+    }
+}
@@ -0,0 +1,256 @@
+use tuple::Tuple;
+use ray::Ray;
+use matrix4::Matrix4;
+use util;
+use super::super::intersection::*;
+use super::super::material::Material;
+use super::Motion;
+use bounds::Bounds;
+use rng::Rng;
+use util::Float;
+
+// An axis-aligned cube spanning -1 to 1 on every axis.
+pub struct Cube {
+    motion: Motion,
+    mat: Material,
+}
+
+impl Cube {
+    pub fn new() -> Cube {
+        Cube {
+            motion: Motion::fixed(Matrix4::identity()),
+            mat: Material::new(),
+        }
+    }
+
+    pub fn transform(&self) -> &Matrix4 {
+        &self.motion.start
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix4) {
+        self.motion = Motion::fixed(transform);
+    }
+
+    // Moves from `start` at time 0 to `end` at time 1.
+    pub fn set_motion(&mut self, start: Matrix4, end: Matrix4) {
+        self.motion = Motion::moving(start, end);
+    }
+
+    pub fn set_material(&mut self, mat: Material) {
+        self.mat = mat;
+    }
+}
+
+impl Cube {
+    fn hits(&self, ray: &Ray) -> Option<(Float, Float)> {
+        let ray = ray.transform(&self.motion.inverse_at(ray.time)?);
+        let (xtmin, xtmax) = check_axis(ray.origin.x(), ray.direction.x());
+        let (ytmin, ytmax) = check_axis(ray.origin.y(), ray.direction.y());
+        let (ztmin, ztmax) = check_axis(ray.origin.z(), ray.direction.z());
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+        if tmin > tmax { None } else { Some((tmin, tmax)) }
+    }
+}
+
+impl Default for Cube {
+    fn default() -> Cube {
+        Cube::new()
+    }
+}
+
+fn check_axis(origin: Float, direction: Float) -> (Float, Float) {
+    let tmin_numerator = -1.0 - origin;
+    let tmax_numerator = 1.0 - origin;
+    let (tmin, tmax) = if direction.abs() >= util::EPSILON {
+        (tmin_numerator / direction, tmax_numerator / direction)
+    } else {
+        (tmin_numerator * Float::INFINITY, tmax_numerator * Float::INFINITY)
+    };
+    if tmin > tmax { (tmax, tmin) } else { (tmin, tmax) }
+}
+
+// The object-space normal of the face `p` is on, scaled by how far out it is.
+fn face_normal(p: Tuple) -> Tuple {
+    let (x, y, z) = (p.x().abs(), p.y().abs(), p.z().abs());
+    if x >= y && x >= z {
+        Tuple::vector(p.x(), 0.0, 0.0)
+    } else if y >= z {
+        Tuple::vector(0.0, p.y(), 0.0)
+    } else {
+        Tuple::vector(0.0, 0.0, p.z())
+    }
+}
+
+impl Intersectable for Cube {
+    fn intersect_into<'a>(&'a self, ray: &Ray, tmin: Float, tmax: Float, xs: &mut Intersections<'a>) {
+        if let Some((t1, t2)) = self.hits(ray) {
+            for &t in [t1, t2].iter() {
+                if (tmin..tmax).contains(&t) {
+                    xs.push(Intersection::new(t, self));
+                }
+            }
+        }
+    }
+
+    fn occluded(&self, ray: &Ray, tmax: Float) -> bool {
+        self.hits(ray).is_some_and(|(t1, t2)| (0.0..tmax).contains(&t1) || (0.0..tmax).contains(&t2))
+    }
+
+    fn normal_at_time(&self, point: Tuple, time: Float) -> Tuple {
+        let inverse = self.motion.normal_inverse_at(time);
+        super::world_normal(&inverse, face_normal(inverse * point))
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.motion.bounds(Bounds::new(Tuple::point(-1.0, -1.0, -1.0), Tuple::point(1.0, 1.0, 1.0)))
+    }
+
+    fn material(&self) -> &Material {
+        &self.mat
+    }
+
+    fn sample_surface(&self, time: Float, rng: &mut Rng) -> Option<SurfaceSample> {
+        // Every face has the same area, so pick one and then a point on it.
+        let face = rng.below(6) as usize;
+        let sign = if face < 3 { 1.0 } else { -1.0 };
+        let (u, v) = (rng.next_float() * 2.0 - 1.0, rng.next_float() * 2.0 - 1.0);
+        let (point, normal) = match face % 3 {
+            0 => (Tuple::point(sign, u, v), Tuple::vector(sign, 0.0, 0.0)),
+            1 => (Tuple::point(u, sign, v), Tuple::vector(0.0, sign, 0.0)),
+            _ => (Tuple::point(u, v, sign), Tuple::vector(0.0, 0.0, sign)),
+        };
+        super::surface_sample(&self.motion, point, normal, 24.0, time)
+    }
+
+    fn surface_pdf(&self, point: Tuple, time: Float) -> Float {
+        match self.motion.inverse_at(time) {
+            Some(inverse) => super::area_density(&inverse, face_normal(inverse * point).normalize(), 24.0),
+            None => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn intersect() {
+        let c = Cube::new();
+        let cases = [
+            (Tuple::point(5.0, 0.5, 0.0), Tuple::vector(-1.0, 0.0, 0.0), 4.0, 6.0),
+            (Tuple::point(-5.0, 0.5, 0.0), Tuple::vector(1.0, 0.0, 0.0), 4.0, 6.0),
+            (Tuple::point(0.5, 5.0, 0.0), Tuple::vector(0.0, -1.0, 0.0), 4.0, 6.0),
+            (Tuple::point(0.5, -5.0, 0.0), Tuple::vector(0.0, 1.0, 0.0), 4.0, 6.0),
+            (Tuple::point(0.5, 0.0, 5.0), Tuple::vector(0.0, 0.0, -1.0), 4.0, 6.0),
+            (Tuple::point(0.5, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 4.0, 6.0),
+            (Tuple::point(0.0, 0.5, 0.0), Tuple::vector(0.0, 0.0, 1.0), -1.0, 1.0),
+        ];
+        for &(origin, direction, t1, t2) in cases.iter() {
+            let xs = c.intersect(&Ray::new(origin, direction));
+            assert_eq!(xs.len(), 2);
+            assert_eq!(xs[0].t, t1);
+            assert_eq!(xs[1].t, t2);
+        }
+    }
+
+    #[test]
+    pub fn intersect_miss() {
+        let c = Cube::new();
+        let cases = [
+            (Tuple::point(-2.0, 0.0, 0.0), Tuple::vector(0.2673, 0.5345, 0.8018)),
+            (Tuple::point(0.0, -2.0, 0.0), Tuple::vector(0.8018, 0.2673, 0.5345)),
+            (Tuple::point(2.0, 0.0, 2.0), Tuple::vector(0.0, 0.0, -1.0)),
+            (Tuple::point(0.0, 2.0, 2.0), Tuple::vector(0.0, -1.0, 0.0)),
+        ];
+        for &(origin, direction) in cases.iter() {
+            assert_eq!(c.intersect(&Ray::new(origin, direction)).len(), 0);
+        }
+    }
+
+    #[test]
+    pub fn occluded() {
+        let c = Cube::new();
+        let r = Ray::new(Tuple::point(5.0, 0.5, 0.0), Tuple::vector(-1.0, 0.0, 0.0));
+        assert!(c.occluded(&r, 5.0));
+        assert!(!c.occluded(&r, 3.0));
+        let mut xs = Intersections::new();
+        c.intersect_into(&r, 5.0, 10.0, &mut xs);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 6.0);
+    }
+
+    #[test]
+    pub fn normal_at() {
+        let c = Cube::new();
+        assert_eq!(c.normal_at(Tuple::point(1.0, 0.5, -0.8)), Tuple::vector(1.0, 0.0, 0.0));
+        assert_eq!(c.normal_at(Tuple::point(-0.4, 0.3, -1.0)), Tuple::vector(0.0, 0.0, -1.0));
+        assert_eq!(c.normal_at(Tuple::point(0.3, -1.0, -0.7)), Tuple::vector(0.0, -1.0, 0.0));
+        assert_eq!(c.normal_at(Tuple::point(1.0, 1.0, 1.0)), Tuple::vector(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    pub fn bounds() {
+        let mut c = Cube::new();
+        c.set_motion(Matrix4::scaling(0.5, 0.5, 0.5), Matrix4::translation(0.0, 0.0, -3.0));
+        assert_eq!(c.bounds(), Bounds::new(Tuple::point(-1.0, -1.0, -4.0), Tuple::point(1.0, 1.0, 0.5)));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -10.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(c.intersect(&r)[0].t, 9.5);
+        assert_eq!(c.intersect(&r.with_time(1.0))[0].t, 6.0);
+    }
+
+    #[test]
+    pub fn half_turn() {
+        // A bar along x turning to lie along -x. Halfway it lies along z,
+        // still whole.
+        let mut c = Cube::new();
+        let start = Matrix4::scaling(3.0, 1.0, 1.0);
+        c.set_motion(start, start.rotate_y(::util::consts::PI));
+        let along_x = Ray::new(Tuple::point(-10.0, 0.0, 0.0), Tuple::vector(1.0, 0.0, 0.0));
+        let along_z = Ray::new(Tuple::point(0.0, 0.0, -10.0), Tuple::vector(0.0, 0.0, 1.0));
+        let hits = |r: Ray, near: Float, far: Float| {
+            let xs = c.intersect(&r);
+            xs.len() == 2 && util::approx_eq(xs[0].t, near) && util::approx_eq(xs[1].t, far)
+        };
+        assert!(hits(along_x.with_time(0.5), 9.0, 11.0));
+        assert!(hits(along_z.with_time(0.5), 7.0, 13.0));
+        assert!(hits(along_x.with_time(1.0), 7.0, 13.0));
+        let n = c.normal_at_time(Tuple::point(0.0, 0.0, -3.0), 0.5);
+        assert_eq!(n, Tuple::vector(0.0, 0.0, -1.0));
+        // The corners swing out to the length of the bar's half diagonal.
+        let r = Float::sqrt(11.0);
+        assert_eq!(c.bounds(), Bounds::new(Tuple::point(-r, -r, -r), Tuple::point(r, r, r)));
+    }
+
+    #[test]
+    pub fn mirrored_motion() {
+        // A mirror image is a half turn about x with every axis flipped, so
+        // the cube turns as it shrinks to nothing halfway, then grows back.
+        let mut c = Cube::new();
+        c.set_motion(Matrix4::identity(), Matrix4::scaling(-1.0, 1.0, 1.0));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -10.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(c.intersect(&r.with_time(0.5)).len(), 0);
+        assert!(!c.occluded(&r.with_time(0.5), 100.0));
+        // An eighth of a turn in, at half size, an edge faces the ray.
+        let t = c.intersect(&r.with_time(0.25))[0].t;
+        assert!(util::approx_eq(t, 10.0 - Float::sqrt(2.0) / 2.0));
+        assert_eq!(c.intersect(&r.with_time(1.0))[0].t, 9.0);
+    }
+
+    #[test]
+    pub fn sample_surface() {
+        // Stretching the cube along x doubles the area of four faces, which
+        // spreads the points on them half as densely.
+        let mut c = Cube::new();
+        c.set_transform(Matrix4::scaling(2.0, 1.0, 1.0));
+        let mut rng = Rng::new(1);
+        for _ in 0..20 {
+            let sample = c.sample_surface(0.0, &mut rng).unwrap();
+            assert_eq!(sample.normal, c.normal_at(sample.point));
+            let expected = if sample.normal.x().abs() > 0.5 { 1.0 / 24.0 } else { 1.0 / 48.0 };
+            assert!(util::approx_eq(sample.pdf, expected));
+            assert!(util::approx_eq(c.surface_pdf(sample.point, 0.0), expected));
+        }
+    }
+}
@@ -1 +1,110 @@
-pub mod sphere;
\ No newline at end of file
+pub mod cube;
+pub mod plane;
+pub mod sphere;
+
+use super::bounds::Bounds;
+use super::intersection::SurfaceSample;
+use super::matrix4::{Decomposed, Matrix4};
+use super::tuple::Tuple;
+use super::util::Float;
+
+// Shapes are defined in object space; these convert normals computed there
+// back into world space.
+fn world_normal(inverse: &Matrix4, object_normal: Tuple) -> Tuple {
+    let n = inverse.transpose() * object_normal;
+    Tuple::vector(n.x(), n.y(), n.z()).normalize()
+}
+
+// The density per unit of world-space area of points spread evenly over an
+// object-space surface of `area`, where its normal is `object_normal`.
+// Wherever the transform stretches the surface, the points spread thinner.
+fn area_density(inverse: &Matrix4, object_normal: Tuple, area: Float) -> Float {
+    let n = inverse.transpose() * object_normal;
+    let stretch = Tuple::vector(n.x(), n.y(), n.z()).magnitude() / inverse.determinant().abs();
+    1.0 / (area * stretch)
+}
+
+// Carries a point spread evenly over an object-space surface of `area`, with
+// `object_normal` there, into world space at `time`.
+fn surface_sample(motion: &Motion, point: Tuple, object_normal: Tuple, area: Float, time: Float) -> Option<SurfaceSample> {
+    let inverse = motion.inverse_at(time)?;
+    Some(SurfaceSample {
+        point: motion.transform_at(time) * point,
+        normal: world_normal(&inverse, object_normal),
+        pdf: area_density(&inverse, object_normal, area),
+    })
+}
+
+// A shape's object-to-world transform, which may move while the shutter is
+// open: `start` applies at time 0 and `end` at time 1. In between, the
+// translation, rotation and scale of each end are blended separately, so a
+// turning object stays rigid.
+#[derive(Debug, Clone, Copy)]
+struct Motion {
+    start: Matrix4,
+    inverse: Matrix4,
+    path: Option<(Decomposed, Decomposed)>,
+}
+
+impl Motion {
+    fn fixed(transform: Matrix4) -> Motion {
+        Motion { start: transform, inverse: transform.inverse(), path: None }
+    }
+
+    fn moving(start: Matrix4, end: Matrix4) -> Motion {
+        // Checked here so a bad end transform fails now rather than mid-render.
+        assert!(end.invertible(), "This matrix is not invertible!");
+        Motion { start, inverse: start.inverse(), path: Some((start.decompose(), end.decompose())) }
+    }
+
+    // None when the blend between the ends is singular, as it is partway
+    // from an object to its mirror image. The object vanishes at that time.
+    fn inverse_at(&self, time: Float) -> Option<Matrix4> {
+        match self.path {
+            Some(_) if time > 0.0 => self.transform_at(time).try_inverse(),
+            _ => Some(self.inverse),
+        }
+    }
+
+    fn transform_at(&self, time: Float) -> Matrix4 {
+        match self.path {
+            Some((start, end)) if time > 0.0 => start.lerp(&end, time.min(1.0)).to_matrix(),
+            _ => self.start,
+        }
+    }
+
+    // Rays only hit the shape at times it can be inverted, so finding the
+    // normal at a hit never needs the fallback.
+    fn normal_inverse_at(&self, time: Float) -> Matrix4 {
+        self.inverse_at(time).unwrap_or(self.inverse)
+    }
+
+    fn bounds(&self, object: Bounds) -> Bounds {
+        let start = object.transform(&self.start);
+        let (from, to) = match self.path {
+            Some(path) => path,
+            None => return start,
+        };
+        // Without turning, each corner moves in a straight line between its
+        // start and end positions, so the box around both ends holds the
+        // whole path.
+        if from.rotation == to.rotation || !object.is_finite() {
+            return start.union(&object.transform(&to.to_matrix()));
+        }
+        // Turning corners swing out along arcs. The blended scale keeps each
+        // within its furthest scaled distance from the moving origin.
+        let mut reach: Float = 0.0;
+        for i in 0..8 {
+            let pick = |bit: usize, axis: usize| if i & bit == 0 { object.min.get(axis) } else { object.max.get(axis) };
+            let corner = Tuple::vector(pick(1, 0), pick(2, 1), pick(4, 2));
+            reach = reach.max((from.scale * corner).magnitude()).max((to.scale * corner).magnitude());
+        }
+        let around = |t: Tuple| {
+            Bounds::new(
+                Tuple::point(t.x() - reach, t.y() - reach, t.z() - reach),
+                Tuple::point(t.x() + reach, t.y() + reach, t.z() + reach),
+            )
+        };
+        around(from.translation).union(&around(to.translation))
+    }
+}
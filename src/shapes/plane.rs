@@ -0,0 +1,138 @@
+use tuple::Tuple;
+use ray::Ray;
+use matrix4::Matrix4;
+use util;
+use super::super::intersection::*;
+use super::super::material::Material;
+use super::Motion;
+use bounds::Bounds;
+use util::Float;
+
+// An infinite plane through the origin, spanning x and z.
+pub struct Plane {
+    motion: Motion,
+    mat: Material,
+}
+
+impl Plane {
+    pub fn new() -> Plane {
+        Plane {
+            motion: Motion::fixed(Matrix4::identity()),
+            mat: Material::new(),
+        }
+    }
+
+    pub fn transform(&self) -> &Matrix4 {
+        &self.motion.start
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix4) {
+        self.motion = Motion::fixed(transform);
+    }
+
+    // Moves from `start` at time 0 to `end` at time 1.
+    pub fn set_motion(&mut self, start: Matrix4, end: Matrix4) {
+        self.motion = Motion::moving(start, end);
+    }
+
+    pub fn set_material(&mut self, mat: Material) {
+        self.mat = mat;
+    }
+}
+
+impl Plane {
+    fn hit(&self, ray: &Ray) -> Option<Float> {
+        let ray = ray.transform(&self.motion.inverse_at(ray.time)?);
+        if ray.direction.y().abs() < util::EPSILON {
+            None
+        } else {
+            Some(-ray.origin.y() / ray.direction.y())
+        }
+    }
+}
+
+impl Default for Plane {
+    fn default() -> Plane {
+        Plane::new()
+    }
+}
+
+impl Intersectable for Plane {
+    fn intersect_into<'a>(&'a self, ray: &Ray, tmin: Float, tmax: Float, xs: &mut Intersections<'a>) {
+        match self.hit(ray) {
+            Some(t) if (tmin..tmax).contains(&t) => xs.push(Intersection::new(t, self)),
+            _ => {}
+        }
+    }
+
+    fn occluded(&self, ray: &Ray, tmax: Float) -> bool {
+        self.hit(ray).is_some_and(|t| (0.0..tmax).contains(&t))
+    }
+
+    fn normal_at_time(&self, _point: Tuple, time: Float) -> Tuple {
+        super::world_normal(&self.motion.normal_inverse_at(time), Tuple::vector(0.0, 1.0, 0.0))
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::infinite()
+    }
+
+    fn material(&self) -> &Material {
+        &self.mat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn normal_is_constant() {
+        let p = Plane::new();
+        assert_eq!(p.normal_at(Tuple::point(0.0, 0.0, 0.0)), Tuple::vector(0.0, 1.0, 0.0));
+        assert_eq!(p.normal_at(Tuple::point(10.0, 0.0, -10.0)), Tuple::vector(0.0, 1.0, 0.0));
+        assert_eq!(p.normal_at(Tuple::point(-5.0, 0.0, 150.0)), Tuple::vector(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    pub fn occluded() {
+        let p = Plane::new();
+        let r = Ray::new(Tuple::point(0.0, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        assert!(p.occluded(&r, 2.0));
+        assert!(!p.occluded(&r, 0.5));
+        let away = Ray::new(Tuple::point(0.0, 1.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+        assert!(!p.occluded(&away, 100.0));
+    }
+
+    #[test]
+    pub fn intersect_parallel() {
+        let p = Plane::new();
+        let r = Ray::new(Tuple::point(0.0, 10.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(p.intersect(&r).len(), 0);
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(p.intersect(&r).len(), 0);
+    }
+
+    #[test]
+    pub fn intersect_from_above_and_below() {
+        let p = Plane::new();
+        let r = Ray::new(Tuple::point(0.0, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        let xs = p.intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 1.0);
+        let r = Ray::new(Tuple::point(0.0, -1.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+        let xs = p.intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 1.0);
+    }
+
+    #[test]
+    pub fn transformed_plane() {
+        let mut p = Plane::new();
+        p.set_transform(Matrix4::rotation_x(::util::consts::PI / 2.0));
+        assert_eq!(p.normal_at(Tuple::point(0.0, 0.0, 0.0)), Tuple::vector(0.0, 0.0, 1.0));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -3.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(p.intersect(&r)[0].t, 3.0);
+        assert!(!p.bounds().is_finite());
+    }
+}
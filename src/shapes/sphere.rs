@@ -1,11 +1,18 @@
 use tuple::Tuple;
 use ray::Ray;
+use matrix4::Matrix4;
 use super::super::intersection::*;
 use super::super::material::Material;
+use super::Motion;
+use bounds::Bounds;
+use rng::Rng;
+use util::consts::PI;
+use util::Float;
 
-struct Sphere {
+pub struct Sphere {
     origin: Tuple,
-    radius: f64,
+    radius: Float,
+    motion: Motion,
     mat: Material,
 }
 
@@ -14,24 +21,95 @@ impl Sphere {
         Sphere {
             origin: Tuple::point(0.0, 0.0, 0.0),
             radius: 1.0,
-            mat: Material { },
+            motion: Motion::fixed(Matrix4::identity()),
+            mat: Material::new(),
         }
     }
+
+    pub fn transform(&self) -> &Matrix4 {
+        &self.motion.start
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix4) {
+        self.motion = Motion::fixed(transform);
+    }
+
+    // Moves from `start` at time 0 to `end` at time 1.
+    pub fn set_motion(&mut self, start: Matrix4, end: Matrix4) {
+        self.motion = Motion::moving(start, end);
+    }
+
+    pub fn set_material(&mut self, mat: Material) {
+        self.mat = mat;
+    }
 }
 
-impl Intersectable for Sphere {
-    fn intersect(&self, ray: &Ray) -> Vec<Intersection> {
-        let sphere_to_ray = ray.origin - Tuple::point(0.0, 0.0, 0.0);
+impl Sphere {
+    fn area(&self) -> Float {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    fn hits(&self, ray: &Ray) -> Option<(Float, Float)> {
+        let ray = ray.transform(&self.motion.inverse_at(ray.time)?);
+        let sphere_to_ray = ray.origin - self.origin;
         let a = ray.direction.dot(&ray.direction);
         let b = (ray.direction.dot(&sphere_to_ray)) * 2.0;
-        let c = (sphere_to_ray.dot(&sphere_to_ray)) - 1.0;
+        let c = (sphere_to_ray.dot(&sphere_to_ray)) - self.radius.powi(2);
         let disciminant = b.powi(2) - 4.0 * a * c;
         if disciminant < 0.0 {
-            vec![]
+            None
         } else {
-            let t1 = (-b - disciminant.sqrt()) / (2.0 * a);
-            let t2 = (-b + disciminant.sqrt()) / (2.0 * a);
-            vec![Intersection { mat: self.mat, t: t1 }, Intersection { mat: self.mat, t: t2 }]
+            Some(((-b - disciminant.sqrt()) / (2.0 * a), (-b + disciminant.sqrt()) / (2.0 * a)))
+        }
+    }
+}
+
+impl Default for Sphere {
+    fn default() -> Sphere {
+        Sphere::new()
+    }
+}
+
+impl Intersectable for Sphere {
+    fn intersect_into<'a>(&'a self, ray: &Ray, tmin: Float, tmax: Float, xs: &mut Intersections<'a>) {
+        if let Some((t1, t2)) = self.hits(ray) {
+            for &t in [t1, t2].iter() {
+                if (tmin..tmax).contains(&t) {
+                    xs.push(Intersection::new(t, self));
+                }
+            }
+        }
+    }
+
+    fn occluded(&self, ray: &Ray, tmax: Float) -> bool {
+        self.hits(ray).is_some_and(|(t1, t2)| (0.0..tmax).contains(&t1) || (0.0..tmax).contains(&t2))
+    }
+
+    fn normal_at_time(&self, point: Tuple, time: Float) -> Tuple {
+        let inverse = self.motion.normal_inverse_at(time);
+        let object_point = inverse * point;
+        super::world_normal(&inverse, object_point - self.origin)
+    }
+
+    fn bounds(&self) -> Bounds {
+        let r = self.radius;
+        let object = Bounds::new(self.origin + Tuple::vector(-r, -r, -r), self.origin + Tuple::vector(r, r, r));
+        self.motion.bounds(object)
+    }
+
+    fn material(&self) -> &Material {
+        &self.mat
+    }
+
+    fn sample_surface(&self, time: Float, rng: &mut Rng) -> Option<SurfaceSample> {
+        let normal = rng.on_unit_sphere();
+        super::surface_sample(&self.motion, self.origin + normal * self.radius, normal, self.area(), time)
+    }
+
+    fn surface_pdf(&self, point: Tuple, time: Float) -> Float {
+        match self.motion.inverse_at(time) {
+            Some(inverse) => super::area_density(&inverse, (inverse * point - self.origin).normalize(), self.area()),
+            None => 0.0,
         }
     }
 }
@@ -90,4 +168,119 @@ mod tests {
         assert_eq!(xs[0].t, -6.0);
         assert_eq!(xs[1].t, -4.0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn intersect_within_range() {
+        let s = Sphere::new();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Intersections::new();
+        s.intersect_into(&r, 0.0, 5.0, &mut xs);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 4.0);
+        xs.clear();
+        s.intersect_into(&r, 4.5, 10.0, &mut xs);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 6.0);
+    }
+
+    #[test]
+    pub fn occluded() {
+        let s = Sphere::new();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(s.occluded(&r, 10.0));
+        assert!(s.occluded(&r, 4.5));
+        assert!(!s.occluded(&r, 3.5));
+        let behind = Ray::new(Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(!s.occluded(&behind, 100.0));
+        let inside = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(s.occluded(&inside, 2.0));
+    }
+
+    #[test]
+    pub fn intersect_sets_object() {
+        let s = Sphere::new();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = s.intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert!(::std::ptr::eq(xs[0].object.material(), s.material()));
+    }
+
+    #[test]
+    pub fn intersect_scaled_sphere() {
+        let mut s = Sphere::new();
+        s.set_transform(Matrix4::scaling(2.0, 2.0, 2.0));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = s.intersect(&r);
+        assert_eq!(xs[0].t, 3.0);
+        assert_eq!(xs[1].t, 7.0);
+    }
+
+    #[test]
+    pub fn intersect_translated_sphere() {
+        let mut s = Sphere::new();
+        s.set_transform(Matrix4::translation(5.0, 0.0, 0.0));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(s.intersect(&r).len(), 0);
+    }
+
+    #[test]
+    pub fn normal_at() {
+        let s = Sphere::new();
+        assert_eq!(s.normal_at(Tuple::point(1.0, 0.0, 0.0)), Tuple::vector(1.0, 0.0, 0.0));
+        assert_eq!(s.normal_at(Tuple::point(0.0, 0.0, 1.0)), Tuple::vector(0.0, 0.0, 1.0));
+        let k = Float::sqrt(3.0) / 3.0;
+        let n = s.normal_at(Tuple::point(k, k, k));
+        assert_eq!(n, Tuple::vector(k, k, k));
+        assert_eq!(n, n.normalize());
+    }
+
+    #[test]
+    pub fn normal_at_transformed() {
+        let mut s = Sphere::new();
+        s.set_transform(Matrix4::translation(0.0, 1.0, 0.0));
+        let k = Float::sqrt(2.0) / 2.0;
+        assert_eq!(s.normal_at(Tuple::point(0.0, 1.0 + k, -k)), Tuple::vector(0.0, k, -k));
+        let mut s = Sphere::new();
+        s.set_transform(Matrix4::scaling(1.0, 0.5, 1.0) * Matrix4::rotation_z(::util::consts::PI / 5.0));
+        assert_eq!(s.normal_at(Tuple::point(0.0, k, -k)), Tuple::vector(0.0, 0.97014, -0.24254));
+    }
+
+    #[test]
+    pub fn moving_sphere() {
+        let mut s = Sphere::new();
+        s.set_motion(Matrix4::identity(), Matrix4::translation(0.0, 4.0, 0.0));
+        let r = Ray::new(Tuple::point(0.0, 2.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(s.intersect(&r).len(), 0);
+        let xs = s.intersect(&r.with_time(0.5));
+        assert_eq!((xs.len(), xs[0].t, xs[1].t), (2, 4.0, 6.0));
+        assert!(s.occluded(&r.with_time(0.5), 10.0));
+        // Times past the end of the motion stay at the end.
+        assert_eq!(s.intersect(&r.with_time(2.0)).len(), 0);
+        assert_eq!(s.normal_at_time(Tuple::point(0.0, 3.0, 0.0), 0.5), Tuple::vector(0.0, 1.0, 0.0));
+        assert_eq!(s.normal_at(Tuple::point(0.0, 1.0, 0.0)), Tuple::vector(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    pub fn bounds() {
+        let mut s = Sphere::new();
+        s.set_transform(Matrix4::scaling(2.0, 1.0, 1.0).translate(0.0, 3.0, 0.0));
+        assert_eq!(s.bounds(), Bounds::new(Tuple::point(-2.0, 2.0, -1.0), Tuple::point(2.0, 4.0, 1.0)));
+        s.set_motion(Matrix4::identity(), Matrix4::translation(5.0, 0.0, 0.0));
+        assert_eq!(s.bounds(), Bounds::new(Tuple::point(-1.0, -1.0, -1.0), Tuple::point(6.0, 1.0, 1.0)));
+    }
+
+    #[test]
+    pub fn sample_surface() {
+        let mut s = Sphere::new();
+        s.set_transform(Matrix4::scaling(2.0, 2.0, 2.0).translate(0.0, 3.0, 0.0));
+        let mut rng = Rng::new(1);
+        for _ in 0..10 {
+            let sample = s.sample_surface(0.0, &mut rng).unwrap();
+            let center = Tuple::point(0.0, 3.0, 0.0);
+            assert!(::util::approx_eq((sample.point - center).magnitude(), 2.0));
+            assert_eq!(sample.normal, (sample.point - center).normalize());
+            assert!(::util::approx_eq(sample.pdf, 1.0 / (16.0 * PI)));
+            assert!(::util::approx_eq(s.surface_pdf(sample.point, 0.0), sample.pdf));
+        }
+    }
+}
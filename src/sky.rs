@@ -0,0 +1,161 @@
+use super::color::Color;
+use super::light::DirectionalLight;
+use super::tuple::Tuple;
+use super::util::consts::PI;
+use super::util::Float;
+
+// The sun's illuminance above the atmosphere, in kilolux. Sky luminances
+// are scaled by pi over this so that the sky and a sun of intensity 1 before
+// the atmosphere dims it light a surface in their true proportions.
+const SOLAR_ILLUMINANCE: Float = 128.0;
+
+// The sun seen from the earth is about half a degree across.
+pub const SUN_ANGULAR_DIAMETER: Float = 0.00935;
+
+// Preetham, Shirley and Smits' analytic daylight model ("A Practical
+// Analytic Model for Daylight"). `turbidity` measures haze, from about 2 for
+// a very clear sky to 10 for a hazy one. The sun sits `elevation` radians
+// above the horizon and `azimuth` radians round from +z towards +x.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sky {
+    turbidity: Float,
+    sun: Tuple,
+    // The sun's angle from the zenith.
+    theta_sun: Float,
+    // Perez distribution coefficients A to E for luminance Y and
+    // chromaticity x and y, and the values of each at the zenith.
+    perez: [[Float; 5]; 3],
+    zenith: [Float; 3],
+}
+
+impl Sky {
+    pub fn new(turbidity: Float, elevation: Float, azimuth: Float) -> Sky {
+        let t = turbidity;
+        let theta = PI / 2.0 - elevation;
+        let perez = [
+            [0.1787 * t - 1.463, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.067 * t + 0.3703],
+            [-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452],
+            [-0.0167 * t - 0.2608, -0.095 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529],
+        ];
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta);
+        let (t2, th2, th3) = (t * t, theta * theta, theta * theta * theta);
+        let zenith = [
+            (4.0453 * t - 4.971) * chi.tan() - 0.2155 * t + 2.4192,
+            t2 * (0.00166 * th3 - 0.00375 * th2 + 0.00209 * theta)
+                + t * (-0.02903 * th3 + 0.06377 * th2 - 0.03202 * theta + 0.00394)
+                + (0.11693 * th3 - 0.21196 * th2 + 0.06052 * theta + 0.25886),
+            t2 * (0.00275 * th3 - 0.0061 * th2 + 0.00317 * theta)
+                + t * (-0.04214 * th3 + 0.0897 * th2 - 0.04153 * theta + 0.00516)
+                + (0.15346 * th3 - 0.26756 * th2 + 0.0667 * theta + 0.26688),
+        ];
+        let sun = Tuple::vector(elevation.cos() * azimuth.sin(), elevation.sin(), elevation.cos() * azimuth.cos());
+        Sky { turbidity, sun, theta_sun: theta, perez, zenith }
+    }
+
+    pub fn turbidity(&self) -> Float {
+        self.turbidity
+    }
+
+    // A unit vector pointing at the sun.
+    pub fn sun_direction(&self) -> Tuple {
+        self.sun
+    }
+
+    pub fn radiance(&self, direction: &Tuple) -> Color {
+        let direction = direction.normalize();
+        // The model only covers the sky, so below the horizon it carries on
+        // with the horizon's color.
+        let cos_theta = direction.y().max(0.001);
+        let gamma = direction.dot(&self.sun).clamp(-1.0, 1.0).acos();
+        let value = |i: usize| {
+            self.zenith[i] * perez(&self.perez[i], cos_theta, gamma) / perez(&self.perez[i], 1.0, self.theta_sun)
+        };
+        let (luminance, x, y) = (value(0), value(1), value(2));
+        let scale = luminance * PI / SOLAR_ILLUMINANCE / y;
+        Color::from_xyz(x * scale, y * scale, (1.0 - x - y) * scale)
+    }
+
+    // The sunlight that makes it through the atmosphere, as a light
+    // `angular_diameter` across sampled `samples` times.
+    pub fn sun(&self, angular_diameter: Float, samples: usize) -> DirectionalLight {
+        let mut light = DirectionalLight::new(-self.sun, self.sun_color());
+        light.angular_diameter = angular_diameter;
+        light.samples = samples;
+        light
+    }
+
+    // A 5778K blackbody dimmed by Rayleigh scattering off air molecules and
+    // Mie scattering off haze, both of which grow with the length of the
+    // path through the air.
+    fn sun_color(&self) -> Color {
+        let theta = self.theta_sun.min(PI / 2.0);
+        let air_mass = 1.0 / (theta.cos() + 0.15 * (93.885 - theta.to_degrees()).powf(-1.253));
+        let beta = 0.04608365 * self.turbidity - 0.04586025;
+        // Wavelengths in micrometres standing in for red, green and blue.
+        let transmittance = |lambda: Float| {
+            let rayleigh = 0.008735 * lambda.powf(-4.08);
+            let aerosol = beta * lambda.powf(-1.3);
+            (-air_mass * (rayleigh + aerosol)).exp()
+        };
+        Color::from_kelvin(5778.0) * Color::new(transmittance(0.68), transmittance(0.55), transmittance(0.44))
+    }
+}
+
+// The Perez sky luminance distribution at zenith angle theta (given as its
+// cosine) and angle gamma from the sun.
+fn perez(c: &[Float; 5], cos_theta: Float, gamma: Float) -> Float {
+    let cos_gamma = gamma.cos();
+    (1.0 + c[0] * (c[1] / cos_theta).exp()) * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * cos_gamma * cos_gamma)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sun_position() {
+        let sky = Sky::new(3.0, PI / 6.0, PI / 2.0);
+        assert_eq!(sky.sun_direction(), Tuple::vector(Float::sqrt(3.0) / 2.0, 0.5, 0.0));
+        let sun = sky.sun(SUN_ANGULAR_DIAMETER, 4);
+        assert_eq!(sun.direction, -sky.sun_direction());
+        assert_eq!((sun.angular_diameter, sun.samples), (SUN_ANGULAR_DIAMETER, 4));
+    }
+
+    #[test]
+    fn zenith_luminance() {
+        // Straight up the Perez terms cancel, leaving the zenith luminance.
+        let sky = Sky::new(3.0, 0.8, 0.0);
+        let expected = sky.zenith[0] * PI / SOLAR_ILLUMINANCE;
+        assert!((sky.radiance(&Tuple::vector(0.0, 1.0, 0.0)).luminance() - expected).abs() < 1e-4);
+        // Around 8 kcd/m2 for a clear sky with the sun 45 degrees up.
+        assert!(sky.zenith[0] > 5.0 && sky.zenith[0] < 10.0);
+    }
+
+    #[test]
+    fn brighter_towards_the_sun_and_bluer_overhead() {
+        let sky = Sky::new(3.0, 0.5, 0.0);
+        let near = sky.radiance(&Tuple::vector(0.0, 0.6, 1.0)).luminance();
+        let away = sky.radiance(&Tuple::vector(0.0, 0.6, -1.0)).luminance();
+        assert!(near > 2.0 * away);
+        let blueness = |c: Color| {
+            let [r, _, b] = (c * (0.5 / c.luminance())).to_rgb8();
+            b as Float / r as Float
+        };
+        let overhead = blueness(sky.radiance(&Tuple::vector(0.0, 1.0, 0.0)));
+        let horizon = blueness(sky.radiance(&Tuple::vector(1.0, 0.05, 0.0)));
+        assert!(overhead > horizon && overhead > 1.0);
+    }
+
+    #[test]
+    fn sun_reddens_and_dims_near_the_horizon() {
+        let high = Sky::new(3.0, 1.2, 0.0).sun(0.0, 1).intensity;
+        let low = Sky::new(3.0, 0.05, 0.0).sun(0.0, 1).intensity;
+        assert!(high.luminance() > 0.6 && high.luminance() < 1.0);
+        assert!(low.luminance() < high.luminance() / 2.0);
+        let [hr, _, hb] = high.to_rgb8();
+        let [lr, _, lb] = (low * (1.0 / low.luminance()) * 0.3).to_rgb8();
+        assert!((lb as Float / lr as Float) < (hb as Float / hr as Float));
+        // Haze dims it too.
+        assert!(Sky::new(8.0, 1.2, 0.0).sun(0.0, 1).intensity.luminance() < high.luminance());
+    }
+}
@@ -0,0 +1,176 @@
+use super::camera::{Camera, RenderOptions};
+use super::canvas::Canvas;
+use super::color::Color;
+use super::matrix4::Matrix4;
+use super::world::World;
+use super::util::Float;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StereoLayout {
+    // Left eye on the left half, right eye on the right.
+    SideBySide,
+    // Left eye on top.
+    OverUnder,
+    // Red from the left eye, green and blue from the right, for red/cyan
+    // glasses.
+    Anaglyph,
+}
+
+impl StereoLayout {
+    pub fn from_name(name: &str) -> Option<StereoLayout> {
+        match name {
+            "side-by-side" => Some(StereoLayout::SideBySide),
+            "over-under" => Some(StereoLayout::OverUnder),
+            "anaglyph" => Some(StereoLayout::Anaglyph),
+            _ => None,
+        }
+    }
+}
+
+// A pair of cameras `interaxial` apart, sideways from a center camera. The
+// eyes stay parallel and shift their image windows instead of toeing in, so
+// things `convergence` away line up in both images without the vertical
+// misalignment toe-in causes at the edges.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StereoRig {
+    pub interaxial: Float,
+    pub convergence: Float,
+    pub layout: StereoLayout,
+}
+
+impl StereoRig {
+    pub fn new(interaxial: Float, convergence: Float, layout: StereoLayout) -> StereoRig {
+        StereoRig { interaxial, convergence, layout }
+    }
+
+    // The left and right eye cameras for `camera`.
+    pub fn eyes(&self, camera: &Camera) -> (Camera, Camera) {
+        // Camera space x points to the camera's left.
+        (self.eye(camera, self.interaxial / 2.0), self.eye(camera, -self.interaxial / 2.0))
+    }
+
+    fn eye(&self, camera: &Camera, offset: Float) -> Camera {
+        let mut eye = camera.clone();
+        eye.set_transform(Matrix4::translation(-offset, 0.0, 0.0) * *camera.transform());
+        let (x, y) = camera.shift();
+        eye.set_shift(x - offset / self.convergence, y);
+        eye
+    }
+
+    pub fn render(&self, camera: &Camera, world: &World, options: &RenderOptions) -> Canvas {
+        self.render_with_progress(camera, world, options, &|_, _| {})
+    }
+
+    // Renders both eyes in turn, reporting progress across the pair.
+    pub fn render_with_progress(
+        &self,
+        camera: &Camera,
+        world: &World,
+        options: &RenderOptions,
+        progress: &(dyn Fn(usize, usize) + Sync),
+    ) -> Canvas {
+        let (left, right) = self.eyes(camera);
+        let left = left.render_with_progress(world, options, &|done, total| progress(done, total * 2));
+        let right = right.render_with_progress(world, options, &|done, total| progress(total + done, total * 2));
+        self.compose(&left, &right)
+    }
+
+    pub fn compose(&self, left: &Canvas, right: &Canvas) -> Canvas {
+        let (width, height) = (left.width(), left.height());
+        let mut canvas = match self.layout {
+            StereoLayout::SideBySide => Canvas::new(width * 2, height),
+            StereoLayout::OverUnder => Canvas::new(width, height * 2),
+            StereoLayout::Anaglyph => Canvas::new(width, height),
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let (l, r) = (*left.pixel_at(x, y), *right.pixel_at(x, y));
+                match self.layout {
+                    StereoLayout::SideBySide => {
+                        canvas.write_pixel(x, y, l);
+                        canvas.write_pixel(width + x, y, r);
+                    }
+                    StereoLayout::OverUnder => {
+                        canvas.write_pixel(x, y, l);
+                        canvas.write_pixel(x, height + y, r);
+                    }
+                    StereoLayout::Anaglyph => {
+                        let color = l * Color::new(1.0, 0.0, 0.0) + r * Color::new(0.0, 1.0, 1.0);
+                        canvas.write_pixel(x, y, color);
+                    }
+                }
+            }
+        }
+        canvas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use light::PointLight;
+    use shapes::sphere::Sphere;
+    use tuple::Tuple;
+    use util::consts::PI;
+
+    fn camera() -> Camera {
+        let mut c = Camera::new(21, 11, PI / 3.0);
+        c.set_transform(Matrix4::view_transform(
+            Tuple::point(0.0, 0.0, -5.0),
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+        ));
+        c
+    }
+
+    #[test]
+    fn eyes_converge() {
+        let rig = StereoRig::new(0.5, 4.0, StereoLayout::SideBySide);
+        let (left, right) = rig.eyes(&camera());
+        let (l, r) = (left.ray_for_pixel(10, 5).unwrap(), right.ray_for_pixel(10, 5).unwrap());
+        // Looking from -z towards the origin, the camera's left is -x.
+        assert_eq!(l.origin, Tuple::point(-0.25, 0.0, -5.0));
+        assert_eq!(r.origin, Tuple::point(0.25, 0.0, -5.0));
+        // The center rays cross at the convergence distance.
+        assert_eq!(l.position(4.0 / l.direction.z()), Tuple::point(0.0, 0.0, -1.0));
+        assert_eq!(r.position(4.0 / r.direction.z()), Tuple::point(0.0, 0.0, -1.0));
+        assert_eq!(l.direction.y(), 0.0);
+    }
+
+    #[test]
+    fn compose_layouts() {
+        let mut left = Canvas::new(2, 1);
+        let mut right = Canvas::new(2, 1);
+        left.write_pixel(0, 0, Color::new(0.2, 0.4, 0.6));
+        right.write_pixel(0, 0, Color::new(0.8, 0.5, 0.1));
+        let side = StereoRig::new(0.1, 1.0, StereoLayout::SideBySide).compose(&left, &right);
+        assert_eq!((side.width(), side.height()), (4, 1));
+        assert_eq!(*side.pixel_at(2, 0), Color::new(0.8, 0.5, 0.1));
+        let over = StereoRig::new(0.1, 1.0, StereoLayout::OverUnder).compose(&left, &right);
+        assert_eq!((over.width(), over.height()), (2, 2));
+        assert_eq!(*over.pixel_at(0, 0), Color::new(0.2, 0.4, 0.6));
+        assert_eq!(*over.pixel_at(0, 1), Color::new(0.8, 0.5, 0.1));
+        let anaglyph = StereoRig::new(0.1, 1.0, StereoLayout::Anaglyph).compose(&left, &right);
+        assert_eq!(*anaglyph.pixel_at(0, 0), Color::new(0.2, 0.5, 0.1));
+    }
+
+    #[test]
+    fn render_pair() {
+        let mut w = World::new();
+        w.objects.push(Box::new(Sphere::new()));
+        w.lights.push(Box::new(PointLight::new(Tuple::point(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0))));
+        let rig = StereoRig::new(1.0, 8.0, StereoLayout::SideBySide);
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let image = rig.render_with_progress(&camera(), &w, &RenderOptions::default(), &|done, total| {
+            assert!(done <= total && total == 4);
+            calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        });
+        assert_eq!(calls.into_inner(), 4);
+        assert_eq!((image.width(), image.height()), (42, 11));
+        // The sphere sits in front of the convergence plane, so it appears
+        // further right in the left eye than in the right.
+        let lit = |x0: usize| (0..21).filter(|&x| image.pixel_at(x0 + x, 5).to_rgb8() != [0, 0, 0]).collect::<Vec<_>>();
+        let (l, r) = (lit(0), lit(21));
+        assert!(l[0] > r[0]);
+    }
+}
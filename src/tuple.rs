@@ -1,15 +1,16 @@
 use std::ops;
 use super::util;
+use super::util::Float;
 
 #[derive(Debug, Clone, Copy)]
-pub struct Tuple (f64, f64, f64, f64);
+pub struct Tuple (Float, Float, Float, Float);
 
 impl Tuple {
-    pub fn new(x: f64, y: f64, z: f64, w: f64) -> Tuple {
+    pub fn new(x: Float, y: Float, z: Float, w: Float) -> Tuple {
         Tuple(x, y, z, w)
     }
 
-    pub fn point(x: f64, y: f64, z: f64) -> Tuple {
+    pub fn point(x: Float, y: Float, z: Float) -> Tuple {
         Tuple(x, y, z, 1.0)
     }
 
@@ -17,7 +18,7 @@ impl Tuple {
         self.3 == 1.0
     }
 
-    pub fn vector(x: f64, y: f64, z: f64) -> Tuple {
+    pub fn vector(x: Float, y: Float, z: Float) -> Tuple {
         Tuple(x, y, z, 0.0)
     }
 
@@ -26,7 +27,23 @@ impl Tuple {
         util::approx_eq(w, 0.0)
     }
 
-    pub fn get(&self, i: usize) -> f64 {
+    pub fn x(&self) -> Float {
+        self.0
+    }
+
+    pub fn y(&self) -> Float {
+        self.1
+    }
+
+    pub fn z(&self) -> Float {
+        self.2
+    }
+
+    pub fn w(&self) -> Float {
+        self.3
+    }
+
+    pub fn get(&self, i: usize) -> Float {
       // gross
       match i {
           0 => self.0,
@@ -37,7 +54,7 @@ impl Tuple {
       }
     }
 
-    pub fn magnitude(&self) -> f64 {
+    pub fn magnitude(&self) -> Float {
         let Tuple (x, y, z, w) = self;
         (x.powi(2) + y.powi(2) + z.powi(2) + w.powi(2)).sqrt()
     }
@@ -48,7 +65,7 @@ impl Tuple {
         Tuple(x / mag, y / mag, z / mag, w / mag)
     }
 
-    pub fn dot(&self, Tuple (rx, ry, rz, rw): &Tuple) -> f64 {
+    pub fn dot(&self, Tuple (rx, ry, rz, rw): &Tuple) -> Float {
         let Tuple (x, y, z, w) = self;
         (x * rx) + (y * ry) + (z * rz) + (w * rw)
     }
@@ -62,6 +79,11 @@ impl Tuple {
           (x * ry) - (y * rx),
         )
     }
+
+    // Mirrors this vector about `normal`, which must be normalized.
+    pub fn reflect(&self, normal: &Tuple) -> Tuple {
+        *self - *normal * 2.0 * self.dot(normal)
+    }
 }
 
 impl PartialEq<Tuple> for Tuple {
@@ -92,19 +114,19 @@ impl ops::Sub<Tuple> for Tuple {
     }
 }
 
-impl ops::Mul<f64> for Tuple {
+impl ops::Mul<Float> for Tuple {
     type Output = Tuple;
 
-    fn mul(self, r: f64) -> Tuple {
+    fn mul(self, r: Float) -> Tuple {
         let Tuple (x, y, z, w) = self;
         Tuple(x * r, y * r, z * r, w * r)
     }
 }
 
-impl ops::Div<f64> for Tuple {
+impl ops::Div<Float> for Tuple {
     type Output = Tuple;
 
-    fn div(self, r: f64) -> Tuple {
+    fn div(self, r: Float) -> Tuple {
         let Tuple (x, y, z, w) = self;
         Tuple(x / r, y / r, z / r, w / r)
     }
@@ -199,9 +221,9 @@ mod tests {
         let v3 = Tuple::vector(0.0, 0.0, 1.0);
         assert_eq!(v3.magnitude(), 1.0);
         let v4 = Tuple::vector(1.0, 2.0, 3.0);
-        assert_eq!(v4.magnitude(), (14.0 as f64).sqrt());
+        assert_eq!(v4.magnitude(), Float::sqrt(14.0));
         let v5 = Tuple::vector(-1.0, -2.0, -3.0);
-        assert_eq!(v5.magnitude(), (14.0 as f64).sqrt());
+        assert_eq!(v5.magnitude(), Float::sqrt(14.0));
     }
 
     #[test]
@@ -212,9 +234,9 @@ mod tests {
         assert_eq!(
             v2.normalize(),
             Tuple::vector(
-              1.0 / (14.0 as f64).sqrt(),
-              2.0 / (14.0 as f64).sqrt(),
-              3.0 / (14.0 as f64).sqrt(),
+              1.0 / Float::sqrt(14.0),
+              2.0 / Float::sqrt(14.0),
+              3.0 / Float::sqrt(14.0),
            ),
        );
     }
@@ -233,4 +255,13 @@ mod tests {
         assert_eq!(v1.cross(&v2), Tuple::vector(-1.0, 2.0, -1.0));
         assert_eq!(v2.cross(&v1), Tuple::vector(1.0, -2.0, 1.0));
     }
+
+    #[test]
+    fn reflect() {
+        let v = Tuple::vector(1.0, -1.0, 0.0);
+        assert_eq!(v.reflect(&Tuple::vector(0.0, 1.0, 0.0)), Tuple::vector(1.0, 1.0, 0.0));
+        let k = Float::sqrt(2.0) / 2.0;
+        let v = Tuple::vector(0.0, -1.0, 0.0);
+        assert_eq!(v.reflect(&Tuple::vector(k, k, 0.0)), Tuple::vector(1.0, 0.0, 0.0));
+    }
 }
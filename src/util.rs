@@ -1,5 +1,24 @@
-pub fn approx_eq(x: f64, y: f64) -> bool {
-    (x - y).abs() < 0.00001
+// The scalar type used throughout the tracer. Building with the `f32` feature
+// halves the size of every tuple, color and matrix.
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+#[cfg(feature = "f32")]
+pub type Float = f32;
+
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
+
+#[cfg(not(feature = "f32"))]
+pub const EPSILON: Float = 0.00001;
+#[cfg(feature = "f32")]
+pub const EPSILON: Float = 0.0001;
+
+// Absolute near zero and relative for large magnitudes, so the same
+// tolerance works for both a normal and a distance of a few thousand units.
+pub fn approx_eq(x: Float, y: Float) -> bool {
+    (x - y).abs() < EPSILON * x.abs().max(y.abs()).max(1.0)
 }
 
 pub fn clamp(input: i32, min: i32, max: i32) -> i32 {
@@ -12,8 +31,8 @@ pub fn clamp(input: i32, min: i32, max: i32) -> i32 {
   }
 }
 
-pub fn scale(input: f64, max: i32) -> i32 {
-    clamp((input * (max as f64)) as i32, 0, max)
+pub fn scale(input: Float, max: i32) -> i32 {
+    clamp((input * (max as Float)) as i32, 0, max)
 }
 
 #[cfg(test)]
@@ -24,6 +43,8 @@ mod tests {
     fn test_approx_equal() {
         assert_eq!(approx_eq(0.9 - 0.7, 0.2), true);
         assert_eq!(approx_eq(0.9, 0.7), false);
+        assert!(approx_eq(4071.0, 4071.0 + EPSILON));
+        assert!(!approx_eq(EPSILON, 3.0 * EPSILON));
     }
 
     #[test]
@@ -0,0 +1,150 @@
+use super::tuple::Tuple;
+use super::util;
+use std::convert::TryFrom;
+use std::ops;
+use super::util::Float;
+
+// A direction in space. Unlike `Tuple` it can't be confused with a point:
+// the operators only allow combinations that make sense for directions, and
+// matrices leave it untouched by translation.
+#[derive(Debug, Clone, Copy)]
+pub struct Vector3(Float, Float, Float);
+
+impl Vector3 {
+    pub fn new(x: Float, y: Float, z: Float) -> Vector3 {
+        Vector3(x, y, z)
+    }
+
+    pub fn zero() -> Vector3 {
+        Vector3(0.0, 0.0, 0.0)
+    }
+
+    pub fn x(&self) -> Float {
+        self.0
+    }
+
+    pub fn y(&self) -> Float {
+        self.1
+    }
+
+    pub fn z(&self) -> Float {
+        self.2
+    }
+
+    pub fn magnitude(&self) -> Float {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Vector3 {
+        *self / self.magnitude()
+    }
+
+    pub fn dot(&self, other: &Vector3) -> Float {
+        self.0 * other.0 + self.1 * other.1 + self.2 * other.2
+    }
+
+    pub fn cross(&self, other: &Vector3) -> Vector3 {
+        Vector3(
+            self.1 * other.2 - self.2 * other.1,
+            self.2 * other.0 - self.0 * other.2,
+            self.0 * other.1 - self.1 * other.0,
+        )
+    }
+}
+
+impl PartialEq<Vector3> for Vector3 {
+    fn eq(&self, other: &Vector3) -> bool {
+        util::approx_eq(self.0, other.0) && util::approx_eq(self.1, other.1) && util::approx_eq(self.2, other.2)
+    }
+}
+
+impl ops::Add<Vector3> for Vector3 {
+    type Output = Vector3;
+
+    fn add(self, other: Vector3) -> Vector3 {
+        Vector3(self.0 + other.0, self.1 + other.1, self.2 + other.2)
+    }
+}
+
+impl ops::Sub<Vector3> for Vector3 {
+    type Output = Vector3;
+
+    fn sub(self, other: Vector3) -> Vector3 {
+        Vector3(self.0 - other.0, self.1 - other.1, self.2 - other.2)
+    }
+}
+
+impl ops::Mul<Float> for Vector3 {
+    type Output = Vector3;
+
+    fn mul(self, r: Float) -> Vector3 {
+        Vector3(self.0 * r, self.1 * r, self.2 * r)
+    }
+}
+
+impl ops::Div<Float> for Vector3 {
+    type Output = Vector3;
+
+    fn div(self, r: Float) -> Vector3 {
+        Vector3(self.0 / r, self.1 / r, self.2 / r)
+    }
+}
+
+impl ops::Neg for Vector3 {
+    type Output = Vector3;
+
+    fn neg(self) -> Vector3 {
+        Vector3(-self.0, -self.1, -self.2)
+    }
+}
+
+impl From<Vector3> for Tuple {
+    fn from(v: Vector3) -> Tuple {
+        Tuple::vector(v.0, v.1, v.2)
+    }
+}
+
+impl TryFrom<Tuple> for Vector3 {
+    type Error = String;
+
+    fn try_from(t: Tuple) -> Result<Vector3, String> {
+        if !t.is_vector() {
+            return Err(format!("expected a vector, got a tuple with w = {}", t.w()));
+        }
+        Ok(Vector3(t.x(), t.y(), t.z()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        let a = Vector3::new(3.0, 2.0, 1.0);
+        let b = Vector3::new(5.0, 6.0, 7.0);
+        assert_eq!(a + b, Vector3::new(8.0, 8.0, 8.0));
+        assert_eq!(a - b, Vector3::new(-2.0, -4.0, -6.0));
+        assert_eq!(-a * 2.0, Vector3::new(-6.0, -4.0, -2.0));
+        assert_eq!(b / 2.0, Vector3::new(2.5, 3.0, 3.5));
+    }
+
+    #[test]
+    fn products() {
+        let a = Vector3::new(1.0, 2.0, 3.0);
+        let b = Vector3::new(2.0, 3.0, 4.0);
+        assert_eq!(a.dot(&b), 20.0);
+        assert_eq!(a.cross(&b), Vector3::new(-1.0, 2.0, -1.0));
+        assert_eq!(b.cross(&a), Vector3::new(1.0, -2.0, 1.0));
+        assert_eq!(a.magnitude(), Float::sqrt(14.0));
+        assert_eq!(Vector3::new(4.0, 0.0, 0.0).normalize(), Vector3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn tuple_conversions() {
+        let v = Vector3::new(1.0, -2.0, 3.0);
+        assert_eq!(Tuple::from(v), Tuple::vector(1.0, -2.0, 3.0));
+        assert_eq!(Vector3::try_from(Tuple::vector(1.0, -2.0, 3.0)), Ok(v));
+        assert!(Vector3::try_from(Tuple::point(1.0, -2.0, 3.0)).is_err());
+    }
+}
@@ -0,0 +1,469 @@
+use super::color::Color;
+use super::environment::Environment;
+use super::intersection::{Computations, Intersectable, Intersections};
+use super::light::Light;
+use super::ray::Ray;
+use super::rng::Rng;
+use super::tuple::Tuple;
+use super::util::Float;
+
+pub struct World {
+    pub objects: Vec<Box<dyn Intersectable>>,
+    pub lights: Vec<Box<dyn Light>>,
+    // What rays that miss everything see, and optionally light from all
+    // around. Without one they see black.
+    pub environment: Option<Environment>,
+}
+
+impl World {
+    pub fn new() -> World {
+        World { objects: Vec::new(), lights: Vec::new(), environment: None }
+    }
+
+    pub fn intersect(&self, ray: &Ray) -> Intersections<'_> {
+        let mut xs = Intersections::new();
+        self.intersect_into(ray, &mut xs);
+        xs
+    }
+
+    // Replaces the contents of `xs` with every intersection along the ray.
+    pub fn intersect_into<'a>(&'a self, ray: &Ray, xs: &mut Intersections<'a>) {
+        xs.clear();
+        for object in self.objects.iter() {
+            object.intersect_into(ray, Float::NEG_INFINITY, Float::INFINITY, xs);
+        }
+        xs.sort();
+    }
+
+    pub fn color_at(&self, ray: &Ray, remaining: usize) -> Color {
+        self.trace(ray, remaining, &mut Intersections::new(), &mut Rng::new(0))
+    }
+
+    // Like `color_at`, but uses `xs` as scratch space for every ray spawned
+    // along the way instead of allocating, and draws light samples from `rng`.
+    pub fn trace<'a>(&'a self, ray: &Ray, remaining: usize, xs: &mut Intersections<'a>, rng: &mut Rng) -> Color {
+        self.intersect_into(ray, xs);
+        match xs.hit() {
+            Some(hit) => {
+                let comps = hit.prepare_computations(ray, xs);
+                self.shade_hit(&comps, remaining, xs, rng)
+            }
+            None => self.background(ray),
+        }
+    }
+
+    pub fn background(&self, ray: &Ray) -> Color {
+        self.environment.as_ref().map_or(Color::black(), |environment| environment.radiance(&ray.direction))
+    }
+
+    pub fn shade_hit<'a>(
+        &'a self,
+        comps: &Computations,
+        remaining: usize,
+        xs: &mut Intersections<'a>,
+        rng: &mut Rng,
+    ) -> Color {
+        let material = comps.object.material();
+        let mut color = material.emissive;
+        for light in self.lights.iter() {
+            let visibility = self.visibility(light.as_ref(), comps.over_point, comps.time, rng);
+            color = color + material.lighting(light.as_ref(), comps.over_point, comps.eyev, comps.normalv, visibility, rng);
+        }
+        if let Some(ref environment) = self.environment {
+            color = color + self.environment_lighting(environment, comps, rng);
+        }
+        let reflected = self.reflected_color(comps, remaining, xs, rng);
+        let refracted = self.refracted_color(comps, remaining, xs, rng);
+        if material.reflective > 0.0 && material.transparency > 0.0 {
+            // Glass reflects more at grazing angles and refracts less.
+            let reflectance = comps.schlick();
+            color + reflected * reflectance + refracted * (1.0 - reflectance)
+        } else {
+            color + reflected + refracted
+        }
+    }
+
+    pub fn reflected_color<'a>(
+        &'a self,
+        comps: &Computations,
+        remaining: usize,
+        xs: &mut Intersections<'a>,
+        rng: &mut Rng,
+    ) -> Color {
+        let reflective = comps.object.material().reflective;
+        if reflective <= 0.0 || remaining == 0 {
+            return Color::black();
+        }
+        let ray = Ray::new(comps.over_point, comps.reflectv).with_time(comps.time);
+        self.trace(&ray, remaining - 1, xs, rng) * reflective
+    }
+
+    pub fn refracted_color<'a>(
+        &'a self,
+        comps: &Computations,
+        remaining: usize,
+        xs: &mut Intersections<'a>,
+        rng: &mut Rng,
+    ) -> Color {
+        let transparency = comps.object.material().transparency;
+        if transparency <= 0.0 || remaining == 0 {
+            return Color::black();
+        }
+        match comps.refracted_direction() {
+            Some(direction) => {
+                let ray = Ray::new(comps.under_point, direction).with_time(comps.time);
+                self.trace(&ray, remaining - 1, xs, rng) * transparency
+            }
+            // Totally internally reflected, which the reflection accounts for.
+            None => Color::black(),
+        }
+    }
+
+    // Image-based lighting: the material's response to the whole environment,
+    // estimated from directions sampled by the environment's brightness.
+    fn environment_lighting(&self, environment: &Environment, comps: &Computations, rng: &mut Rng) -> Color {
+        let samples = environment.samples();
+        let material = comps.object.material();
+        let mut sum = Color::black();
+        for _ in 0..samples {
+            let sample = environment.sample(rng);
+            let cos = sample.direction.dot(&comps.normalv);
+            if cos <= 0.0 || sample.pdf <= 0.0 {
+                continue;
+            }
+            let ray = Ray::new(comps.over_point, sample.direction).with_time(comps.time);
+            if self.occluded(&ray, Float::INFINITY) {
+                continue;
+            }
+            let f = material.brdf(sample.direction, comps.eyev, comps.normalv);
+            sum = sum + f * sample.radiance * (cos / sample.pdf);
+        }
+        if samples == 0 { sum } else { sum * (1.0 / samples as Float) }
+    }
+
+    // The fraction of the light's samples visible from `point` at `time`.
+    pub fn visibility(&self, light: &dyn Light, point: Tuple, time: Float, rng: &mut Rng) -> Float {
+        let samples = light.samples();
+        let visible = (0..samples)
+            .filter(|&i| {
+                let sample = light.sample_direction(i, point, rng);
+                !self.occluded(&Ray::new(point, sample.direction).with_time(time), sample.distance)
+            })
+            .count();
+        visible as Float / samples as Float
+    }
+
+    pub fn is_shadowed(&self, point: Tuple, light_position: Tuple) -> bool {
+        let v = light_position - point;
+        self.occluded(&Ray::new(point, v.normalize()), v.magnitude())
+    }
+
+    // Whether any object crosses the ray in 0..tmax, stopping at the first.
+    pub fn occluded(&self, ray: &Ray, tmax: Float) -> bool {
+        self.objects.iter().any(|object| object.occluded(ray, tmax))
+    }
+}
+
+impl Default for World {
+    fn default() -> World {
+        World::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canvas::Canvas;
+    use environment::Background;
+    use light::{AreaLight, DirectionalLight, PointLight, SpotLight};
+    use material::Material;
+    use matrix4::Matrix4;
+    use shapes::plane::Plane;
+    use shapes::sphere::Sphere;
+    use util::consts::PI;
+
+    pub fn default_world() -> World {
+        let mut s1 = Sphere::new();
+        let mut m = Material::new();
+        m.color = Color::new(0.8, 1.0, 0.6);
+        m.diffuse = 0.7;
+        m.specular = 0.2;
+        s1.set_material(m);
+        let mut s2 = Sphere::new();
+        s2.set_transform(Matrix4::scaling(0.5, 0.5, 0.5));
+        let mut w = World::new();
+        w.objects.push(Box::new(s1));
+        w.objects.push(Box::new(s2));
+        w.lights.push(Box::new(PointLight::new(Tuple::point(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0))));
+        w
+    }
+
+    #[test]
+    fn intersect_world() {
+        let w = default_world();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let ts: Vec<Float> = w.intersect(&r).iter().map(|x| x.t).collect();
+        assert_eq!(ts, vec![4.0, 4.5, 5.5, 6.0]);
+    }
+
+    #[test]
+    fn color_when_ray_misses() {
+        let w = default_world();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0));
+        assert_eq!(w.color_at(&r, 5), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn color_when_ray_hits() {
+        let w = default_world();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&r, 5), Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn color_from_inside() {
+        let w = default_world();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        let light = PointLight::new(Tuple::point(0.0, 0.25, 0.0), Color::new(1.0, 1.0, 1.0));
+        let w = World { lights: vec![Box::new(light)], ..w };
+        assert_eq!(w.color_at(&r, 5), Color::new(0.90498, 0.90498, 0.90498));
+    }
+
+    #[test]
+    fn shadows() {
+        let w = default_world();
+        let light = Tuple::point(-10.0, 10.0, -10.0);
+        assert!(!w.is_shadowed(Tuple::point(0.0, 10.0, 0.0), light));
+        assert!(w.is_shadowed(Tuple::point(10.0, -10.0, 10.0), light));
+        assert!(!w.is_shadowed(Tuple::point(-20.0, 20.0, -20.0), light));
+        assert!(!w.is_shadowed(Tuple::point(-2.0, 2.0, -2.0), light));
+    }
+
+    #[test]
+    fn area_light_visibility() {
+        let w = default_world();
+        let mut light = AreaLight::new(
+            Tuple::point(-0.5, -0.5, -5.0),
+            Tuple::vector(1.0, 0.0, 0.0),
+            2,
+            Tuple::vector(0.0, 1.0, 0.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        light.jitter = false;
+        let cases = [
+            (Tuple::point(0.0, 0.0, 2.0), 0.0),
+            (Tuple::point(1.0, -1.0, 2.0), 0.25),
+            (Tuple::point(1.5, 0.0, 2.0), 0.5),
+            (Tuple::point(1.25, 1.25, 3.0), 0.75),
+            (Tuple::point(0.0, 0.0, -2.0), 1.0),
+        ];
+        for &(point, expected) in cases.iter() {
+            assert_eq!(w.visibility(&light, point, 0.0, &mut Rng::new(0)), expected);
+        }
+    }
+
+    #[test]
+    fn soft_shadow_penumbra() {
+        // A sphere between a floor and a wide area light casts a shadow that
+        // fades out at its edge instead of stopping abruptly.
+        let mut w = World::new();
+        w.lights.push(Box::new(AreaLight::new(
+            Tuple::point(-2.0, 5.0, -2.0),
+            Tuple::vector(4.0, 0.0, 0.0),
+            8,
+            Tuple::vector(0.0, 0.0, 4.0),
+            8,
+            Color::new(1.0, 1.0, 1.0),
+        )));
+        w.objects.push(Box::new(Sphere::new()));
+        let mut rng = Rng::new(3);
+        let visibility = |x: Float, rng: &mut Rng| {
+            w.visibility(w.lights[0].as_ref(), Tuple::point(x, -1.0, 0.0), 0.0, rng)
+        };
+        assert_eq!(visibility(0.0, &mut rng), 0.0);
+        let edge = visibility(1.3, &mut rng);
+        assert!(edge > 0.0 && edge < 1.0);
+        assert_eq!(visibility(10.0, &mut rng), 1.0);
+    }
+
+    #[test]
+    fn mixed_light_types() {
+        // A small sphere hovering over a floor, lit from straight above.
+        let mut w = World::new();
+        let mut ball = Sphere::new();
+        ball.set_transform(Matrix4::translation(0.0, 2.0, 0.0).scale(0.5, 0.5, 0.5));
+        w.objects.push(Box::new(ball));
+        let mut floor = Plane::new();
+        floor.set_material(Material { ambient: 0.0, specular: 0.0, ..Material::new() });
+        w.objects.push(Box::new(floor));
+        let down = Ray::new(Tuple::point(3.0, 5.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+
+        let sun = DirectionalLight::new(Tuple::vector(0.0, -1.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        w.lights = vec![Box::new(sun)];
+        assert_eq!(w.color_at(&down, 0), Color::new(0.9, 0.9, 0.9));
+        // The shadow falls straight down however far away the sun is.
+        assert_eq!(w.visibility(w.lights[0].as_ref(), Tuple::point(0.0, 0.01, 0.0), 0.0, &mut Rng::new(0)), 0.0);
+        assert_eq!(w.visibility(w.lights[0].as_ref(), Tuple::point(0.6, 0.01, 0.0), 0.0, &mut Rng::new(0)), 1.0);
+
+        let spot = SpotLight::new(
+            Tuple::point(3.0, 4.0, 0.0),
+            Tuple::vector(0.0, -1.0, 0.0),
+            0.5,
+            0.1,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        w.lights.push(Box::new(spot));
+        assert_eq!(w.color_at(&down, 0), Color::new(1.8, 1.8, 1.8));
+        // Outside the cone only the sun reaches the floor.
+        let aside = Ray::new(Tuple::point(-3.0, 5.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        assert_eq!(w.color_at(&aside, 0), Color::new(0.9, 0.9, 0.9));
+    }
+
+    #[test]
+    fn occluded() {
+        let w = default_world();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(w.occluded(&r, 4.25));
+        assert!(!w.occluded(&r, 3.75));
+        assert!(!w.occluded(&Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0)), 100.0));
+    }
+
+    #[test]
+    fn shade_hit_in_shadow() {
+        let mut w = World::new();
+        w.lights.push(Box::new(PointLight::new(Tuple::point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0))));
+        w.objects.push(Box::new(Sphere::new()));
+        let mut s2 = Sphere::new();
+        s2.set_transform(Matrix4::translation(0.0, 0.0, 10.0));
+        w.objects.push(Box::new(s2));
+        let r = Ray::new(Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&r, 5), Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn reflected_color() {
+        let mut w = default_world();
+        let mut floor = Plane::new();
+        let mut m = Material::new();
+        m.reflective = 0.5;
+        floor.set_material(m);
+        floor.set_transform(Matrix4::translation(0.0, -1.0, 0.0));
+        w.objects.push(Box::new(floor));
+        let k = Float::sqrt(2.0) / 2.0;
+        let r = Ray::new(Tuple::point(0.0, 0.0, -3.0), Tuple::vector(0.0, -k, k));
+        assert_eq!(w.color_at(&r, 5), Color::new(0.87676, 0.92434, 0.82917));
+        // With no recursion left the reflection is skipped entirely.
+        assert_eq!(w.color_at(&r, 0), Color::new(0.68643, 0.68643, 0.68643));
+    }
+
+    #[test]
+    fn environment_background() {
+        let mut w = default_world();
+        let mut floor = Plane::new();
+        floor.set_material(Material { reflective: 0.5, ..Material::new() });
+        floor.set_transform(Matrix4::translation(0.0, -1.0, 0.0));
+        w.objects.push(Box::new(floor));
+        let mut sky = Environment::new(Background::Gradient {
+            zenith: Color::new(0.0, 0.0, 1.0),
+            horizon: Color::new(1.0, 1.0, 1.0),
+            ground: Color::new(0.0, 0.0, 0.0),
+        });
+        sky.set_samples(0);
+        w.environment = Some(sky);
+        let up = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0));
+        assert_eq!(w.color_at(&up, 5), Color::new(0.0, 0.0, 1.0));
+        // The floor reflects the sky past the spheres.
+        let k = Float::sqrt(2.0) / 2.0;
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, -k, k));
+        let without = w.color_at(&r, 0);
+        assert_eq!(w.color_at(&r, 5), without + Color::new(0.5 * (1.0 - k), 0.5 * (1.0 - k), 0.5));
+    }
+
+    #[test]
+    fn environment_lighting() {
+        // A white floor under a uniform white sky reflects its diffuse
+        // fraction of the sky's radiance.
+        let mut w = World::new();
+        let matte = Material { ambient: 0.0, specular: 0.0, diffuse: 0.8, ..Material::new() };
+        let mut floor = Plane::new();
+        floor.set_material(matte);
+        w.objects.push(Box::new(floor));
+        let mut image = Canvas::new(8, 4);
+        for y in 0..4 {
+            for x in 0..8 {
+                image.write_pixel(x, y, Color::new(1.0, 1.0, 1.0));
+            }
+        }
+        let mut sky = Environment::new(Background::Image(image));
+        sky.set_samples(4000);
+        w.environment = Some(sky);
+        let down = Ray::new(Tuple::point(0.0, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        let lit = w.trace(&down, 0, &mut Intersections::new(), &mut Rng::new(2)).luminance();
+        assert!((lit - 0.8).abs() < 0.03, "{}", lit);
+        // A wall beside the point blocks half the sky.
+        let mut wall = Plane::new();
+        wall.set_transform(Matrix4::rotation_z(PI / 2.0).translate(0.01, 0.0, 0.0));
+        w.objects.push(Box::new(wall));
+        let shaded = w.trace(&down, 0, &mut Intersections::new(), &mut Rng::new(2)).luminance();
+        assert!((shaded - 0.4).abs() < 0.03, "{}", shaded);
+    }
+
+    #[test]
+    fn emissive_surfaces_glow() {
+        let mut w = default_world();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let lit = w.color_at(&r, 5);
+        let mut glowing = Sphere::new();
+        glowing.set_material(Material { color: Color::new(0.8, 1.0, 0.6), diffuse: 0.7, specular: 0.2, emissive: Color::new(0.5, 0.25, 0.0), ..Material::new() });
+        w.objects[0] = Box::new(glowing);
+        assert_eq!(w.color_at(&r, 5), lit + Color::new(0.5, 0.25, 0.0));
+    }
+
+    #[test]
+    fn mutually_reflective_surfaces_terminate() {
+        let mut w = World::new();
+        w.lights.push(Box::new(PointLight::new(Tuple::point(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0))));
+        let mut m = Material::new();
+        m.reflective = 1.0;
+        let mut lower = Plane::new();
+        lower.set_material(m);
+        lower.set_transform(Matrix4::translation(0.0, -1.0, 0.0));
+        let mut upper = Plane::new();
+        upper.set_material(m);
+        upper.set_transform(Matrix4::translation(0.0, 1.0, 0.0));
+        w.objects.push(Box::new(lower));
+        w.objects.push(Box::new(upper));
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+        w.color_at(&r, 5);
+    }
+
+    #[test]
+    fn refraction_shows_the_background() {
+        let mut w = World::new();
+        let mut glass = Sphere::new();
+        glass.set_material(Material {
+            ambient: 0.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            transparency: 1.0,
+            refractive_index: 1.5,
+            ..Material::new()
+        });
+        w.objects.push(Box::new(glass));
+        let mut sky = Environment::new(Background::Solid(Color::new(0.0, 1.0, 0.0)));
+        sky.set_samples(0);
+        w.environment = Some(sky);
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&r, 5), Color::new(0.0, 1.0, 0.0));
+        // Without the recursion to pass through, the sphere looks opaque.
+        assert_eq!(w.color_at(&r, 1), Color::black());
+        // Glancing rays trapped inside by total internal reflection see
+        // nothing through the surface.
+        let k = Float::sqrt(2.0) / 2.0;
+        let inside = Ray::new(Tuple::point(0.0, 0.0, k), Tuple::vector(0.0, 1.0, 0.0));
+        let xs = w.intersect(&inside);
+        let comps = xs[1].prepare_computations(&inside, &xs);
+        assert_eq!(w.refracted_color(&comps, 5, &mut Intersections::new(), &mut Rng::new(1)), Color::black());
+    }
+}